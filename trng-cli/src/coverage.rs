@@ -0,0 +1,70 @@
+// TRNG - Brainfucks pretty sister.
+// Copyright (C) 2023 Lukas Pfeifer
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::path::Path;
+use std::process::ExitCode;
+
+/// Runs the files given in the arguments and prints which lines and branches were executed.
+/// A file is run with the content of its sibling `.in` file as input if there is one.
+/// With `--lcov <path>` the records are also written to an lcov tracefile.
+pub fn run(args: &[String]) -> ExitCode {
+    let mut lcov = None;
+    let mut paths = vec![];
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--lcov" => match args.next() {
+                Some(path) => lcov = Some(path),
+                None => return super::usage(),
+            },
+            _ => paths.push(arg),
+        }
+    }
+
+    if paths.is_empty() {
+        return super::usage();
+    }
+
+    let mut code = ExitCode::SUCCESS;
+    let mut coverage = trng::Coverage::new();
+    for path in paths {
+        let input = std::fs::read(Path::new(path).with_extension("in")).unwrap_or_default();
+
+        let mut interpreter = trng::Interpreter::default();
+        interpreter.set_output(std::io::sink());
+        interpreter.set_input(std::io::Cursor::new(input));
+        interpreter.set_coverage(Some(coverage));
+
+        if let Err(e) = interpreter.run_file(path) {
+            eprintln!("{}: {}", path, e);
+            code = ExitCode::FAILURE;
+        }
+
+        coverage = interpreter.take_coverage().unwrap_or_default();
+    }
+
+    print!("{}", coverage.summary());
+
+    if let Some(lcov) = lcov {
+        if let Err(e) = std::fs::write(lcov, coverage.to_lcov()) {
+            eprintln!("{}: {}", lcov, e);
+            code = ExitCode::FAILURE;
+        }
+    }
+
+    code
+}
@@ -0,0 +1,90 @@
+// TRNG - Brainfucks pretty sister.
+// Copyright (C) 2023 Lukas Pfeifer
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::process::ExitCode;
+
+/// Formats the files given in the arguments in place.
+/// With `--check` no file is changed. Instead every unformatted file is listed and the exit code is 1.
+pub fn run(args: &[String]) -> ExitCode {
+    let check = args.iter().any(|a| a == "--check");
+    let paths: Vec<&String> = args.iter().filter(|a| *a != "--check").collect();
+
+    if paths.is_empty() {
+        return super::usage();
+    }
+
+    let mut code = ExitCode::SUCCESS;
+    for path in paths {
+        match format_file(path, check) {
+            Ok(true) => (),
+            Ok(false) => {
+                println!("{}", path);
+                code = ExitCode::FAILURE;
+            }
+            Err(e) => {
+                eprintln!("{}: {}", path, e);
+                code = ExitCode::FAILURE;
+            }
+        }
+    }
+
+    code
+}
+
+/// Formats a single file. Returns whether the file was already formatted.
+/// # Arguments
+/// * `path` - Path of the file.
+/// * `check` - Whether only to check the file instead of rewriting it.
+fn format_file(path: &str, check: bool) -> Result<bool, Box<dyn std::error::Error>> {
+    let source = std::fs::read_to_string(path)?;
+    let formatted = trng::format_source(source.as_bytes())?;
+
+    if formatted == source {
+        return Ok(true);
+    }
+
+    if !check {
+        std::fs::write(path, formatted)?;
+    }
+
+    Ok(!check)
+}
+
+#[cfg(test)]
+mod tests {
+
+    #[test]
+    fn check_does_not_change_file_test() {
+        let path = std::env::temp_dir().join(format!("trng-fmt-{}.trng", std::process::id()));
+        let path_str = path.to_str().unwrap();
+        std::fs::write(&path, "inc 1 lop dec 1 pol").unwrap();
+
+        assert!(!super::format_file(path_str, true).unwrap());
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "inc 1 lop dec 1 pol"
+        );
+
+        assert!(super::format_file(path_str, false).unwrap());
+        assert!(super::format_file(path_str, true).unwrap());
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "inc 1\nlop\n    dec 1\npol\n"
+        );
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,60 @@
+// TRNG - Brainfucks pretty sister.
+// Copyright (C) 2023 Lukas Pfeifer
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::process::ExitCode;
+
+/// Generates random programs and runs each with all engines.
+/// The first program the engines disagree on is shrunk and printed, and the exit code is 1.
+pub fn run(args: &[String]) -> ExitCode {
+    let mut seed: u64 = 0;
+    let mut count = 100;
+    let mut size = 100;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let target = match arg.as_str() {
+            "--seed" => &mut seed,
+            "--count" => &mut count,
+            "--size" => &mut size,
+            _ => return super::usage(),
+        };
+        match args.next().and_then(|v| v.parse().ok()) {
+            Some(value) => *target = value,
+            None => return super::usage(),
+        }
+    }
+
+    for seed in (0..count).map(|i| seed.wrapping_add(i)) {
+        let program = trng::Generator::new(seed).generate(size as usize);
+
+        if let Some(divergence) = trng::differential(&program) {
+            let shrunk = trng::shrink(&program, |p| {
+                trng::differential(p).is_some_and(|d| d.engine == divergence.engine)
+            });
+
+            println!("seed {}: {}", seed, divergence);
+            println!("Minimal program:");
+            print!("{}", trng::listing(&shrunk));
+            if let Some(d) = trng::differential(&shrunk) {
+                println!("{}", d);
+            }
+            return ExitCode::FAILURE;
+        }
+    }
+
+    println!("{} programs, no divergence", count);
+    ExitCode::SUCCESS
+}
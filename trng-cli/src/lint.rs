@@ -0,0 +1,57 @@
+// TRNG - Brainfucks pretty sister.
+// Copyright (C) 2023 Lukas Pfeifer
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::process::ExitCode;
+
+/// Lints the files given in the arguments and prints all diagnostics.
+/// The exit code is 1 if a file cannot be parsed or a diagnostic is an error.
+pub fn run(args: &[String]) -> ExitCode {
+    if args.is_empty() {
+        return super::usage();
+    }
+
+    let mut code = ExitCode::SUCCESS;
+    for path in args {
+        match lint_file(path) {
+            Ok(diagnostics) => {
+                for d in &diagnostics {
+                    println!("{}: {}", path, d);
+                }
+                if diagnostics
+                    .iter()
+                    .any(|d| d.severity == trng::Severity::Error)
+                {
+                    code = ExitCode::FAILURE;
+                }
+            }
+            Err(e) => {
+                eprintln!("{}: {}", path, e);
+                code = ExitCode::FAILURE;
+            }
+        }
+    }
+
+    code
+}
+
+/// Lints a single file.
+/// # Arguments
+/// * `path` - Path of the file.
+fn lint_file(path: &str) -> Result<Vec<trng::Diagnostic>, trng::ParseError> {
+    let program = trng::Program::from_file(path)?;
+
+    Ok(trng::lint(&program))
+}
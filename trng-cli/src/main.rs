@@ -0,0 +1,92 @@
+// TRNG - Brainfucks pretty sister.
+// Copyright (C) 2023 Lukas Pfeifer
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+mod coverage;
+mod fmt;
+mod fuzz;
+mod lint;
+mod profile;
+mod run;
+mod test;
+
+use std::process::ExitCode;
+
+const USAGE: &str = "Usage:
+    trng <file>                   Runs a TRNG or Brainfuck file.
+    trng run [--release] [--preload <file>[@<offset>]] [--dump <file>[@<start>..<end>]]
+             [--mmap <file>] <file>
+                                  Runs a TRNG or Brainfuck file.
+                                  With --release, assertions are not checked.
+                                  --preload copies a file onto the tape before the run,
+                                  --dump writes the tape to a file after the run and
+                                  --mmap uses a file as the tape and changes it in place.
+    trng coverage [--lcov <path>] <files>
+                                  Runs files and prints the executed lines and branches.
+    trng fmt [--check] <files>    Formats TRNG files in place.
+                                  With --check, lists unformatted files instead.
+    trng fuzz [--seed <n>] [--count <n>] [--size <n>]
+                                  Runs random programs with all engines and
+                                  prints the first one they disagree on.
+    trng lint <files>             Reports likely mistakes in TRNG files.
+    trng list <file>              Prints the instructions the interpreter executes.
+    trng profile [--json | --folded] <file>
+                                  Runs a file and prints a profile to stderr.
+    trng test <dirs or files>     Runs programs and compares them with their .out and .err files.";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    match args.first().map(|a| a.as_str()) {
+        Some("coverage") => coverage::run(&args[1..]),
+        Some("fmt") => fmt::run(&args[1..]),
+        Some("fuzz") => fuzz::run(&args[1..]),
+        Some("lint") => lint::run(&args[1..]),
+        Some("list") => list(&args[1..]),
+        Some("profile") => profile::run(&args[1..]),
+        Some("run") => run::run(&args[1..]),
+        Some("test") => test::run(&args[1..]),
+        Some("-h") | Some("--help") => {
+            println!("{}", USAGE);
+            ExitCode::SUCCESS
+        }
+        Some(_) => run::run(&args),
+        None => usage(),
+    }
+}
+
+/// Prints the usage and returns the exit code for invalid arguments.
+fn usage() -> ExitCode {
+    eprintln!("{}", USAGE);
+    ExitCode::from(2)
+}
+
+/// Prints the listing of the file given in the arguments.
+fn list(args: &[String]) -> ExitCode {
+    let [path] = args else {
+        return usage();
+    };
+
+    match trng::Program::from_file(path) {
+        Ok(program) => {
+            print!("{}", trng::listing(&program));
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,63 @@
+// TRNG - Brainfucks pretty sister.
+// Copyright (C) 2023 Lukas Pfeifer
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::process::ExitCode;
+
+/// Runs the file given in the arguments with the profiler and prints the profile to stderr.
+/// By default the annotated source and the hot loop report are printed.
+/// `--json` and `--folded` print the JSON and the folded stack export instead.
+pub fn run(args: &[String]) -> ExitCode {
+    let (format, path) = match args {
+        [path] => (None, path),
+        [flag, path] if flag == "--json" || flag == "--folded" => (Some(flag.as_str()), path),
+        _ => return super::usage(),
+    };
+
+    let (source, program) = match std::fs::read_to_string(path)
+        .map_err(trng::ParseError::from)
+        .and_then(|source| Ok((source, trng::Program::from_file(path)?)))
+    {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut interpreter = trng::Interpreter::default();
+    interpreter.set_profiler(Some(trng::Profiler::new()));
+    let result = interpreter.run_program(program.clone());
+
+    if let Some(profiler) = interpreter.take_profiler() {
+        match format {
+            Some("--json") => eprintln!("{}", profiler.to_json(&program)),
+            Some(_) => eprint!("{}", profiler.to_folded(&program)),
+            None => eprint!(
+                "{}\n{}",
+                profiler.annotate(&program, &source),
+                profiler.report(&program)
+            ),
+        }
+    }
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
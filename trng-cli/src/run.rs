@@ -0,0 +1,231 @@
+// TRNG - Brainfucks pretty sister.
+// Copyright (C) 2023 Lukas Pfeifer
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::process::ExitCode;
+
+/// The number of cells of the tape if no preloaded file needs more.
+const TAPE_SIZE: usize = 30000;
+
+/// The largest tape preloaded files may grow the tape to (1 GiB).
+const MAX_TAPE_SIZE: usize = 1 << 30;
+
+/// The first cell to dump and the end of the range, or `None` for the end of the tape.
+type DumpRange = (usize, Option<usize>);
+
+/// Runs the file given in the arguments. With `--release` assertions are removed first.
+/// `--preload <file>[@<offset>]` copies a file onto the tape before the run,
+/// `--dump <file>[@<start>..<end>]` writes the tape to a file after a successful run
+/// and `--mmap <file>` uses a file as the tape.
+/// If the program fails at runtime the cells around the pointer are printed after the error.
+pub fn run(args: &[String]) -> ExitCode {
+    let mut release = false;
+    let mut preloads = vec![];
+    let mut dump = None;
+    let mut mmap = None;
+    let mut path = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--release" => release = true,
+            "--preload" => match args.next().and_then(|a| parse_preload(a)) {
+                Some(preload) => preloads.push(preload),
+                None => return super::usage(),
+            },
+            "--dump" => match args.next().and_then(|a| parse_dump(a)) {
+                Some(d) => dump = Some(d),
+                None => return super::usage(),
+            },
+            "--mmap" => match args.next() {
+                Some(m) => mmap = Some(m),
+                None => return super::usage(),
+            },
+            _ if path.is_none() => path = Some(arg),
+            _ => return super::usage(),
+        }
+    }
+
+    let Some(path) = path else {
+        return super::usage();
+    };
+
+    let mut interpreter = match tape_size(&preloads) {
+        Ok(size) => trng::Interpreter::new(size),
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    interpreter.set_release(release);
+
+    if let Some(mmap) = mmap {
+        if let Err(e) = map(&mut interpreter, mmap) {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    }
+
+    for (file, offset) in &preloads {
+        if let Err(e) = interpreter.preload_tape_file(file, *offset) {
+            eprintln!("{}: {}", file, e);
+            return ExitCode::FAILURE;
+        }
+    }
+
+    if let Err(e) = interpreter.run_file(path) {
+        eprintln!("{}", e);
+        if e._type != trng::InterpreterErrorType::Syntax {
+            eprint!("{}", interpreter.dump(1));
+        }
+        return ExitCode::FAILURE;
+    }
+
+    if let Some((file, range)) = dump {
+        let len = interpreter.get_data().len();
+        let range = range.map(|(start, end)| start..end.unwrap_or(len));
+        if let Err(e) = interpreter.dump_tape_file(file, range) {
+            eprintln!("{}: {}", file, e);
+            return ExitCode::FAILURE;
+        }
+    }
+
+    ExitCode::SUCCESS
+}
+
+/// Returns the number of cells needed to hold all preloaded files, but at least the default size.
+/// Returns an error if a file would need more than `MAX_TAPE_SIZE` cells.
+/// # Arguments
+/// * `preloads` - The preloaded files with their offsets.
+fn tape_size(preloads: &[(&str, usize)]) -> Result<usize, String> {
+    let mut size = TAPE_SIZE;
+    for (file, offset) in preloads {
+        let len = std::fs::metadata(file)
+            .map_err(|e| format!("{}: {}", file, e))?
+            .len();
+        let end = usize::try_from(len)
+            .ok()
+            .and_then(|len| offset.checked_add(len))
+            .filter(|end| *end <= MAX_TAPE_SIZE)
+            .ok_or_else(|| {
+                format!(
+                    "{}: Preloading {} byte(s) at index {} exceeds the maximum tape size of {} cell(s).",
+                    file, len, offset, MAX_TAPE_SIZE
+                )
+            })?;
+        size = size.max(end);
+    }
+    Ok(size)
+}
+
+/// Uses the given file as the tape.
+#[cfg(feature = "mmap")]
+fn map(interpreter: &mut trng::Interpreter, file: &str) -> Result<(), String> {
+    interpreter
+        .map_tape_file(file)
+        .map_err(|e| format!("{}: {}", file, e))
+}
+
+/// Reports that memory mapping is not available in this build.
+#[cfg(not(feature = "mmap"))]
+fn map(_interpreter: &mut trng::Interpreter, _file: &str) -> Result<(), String> {
+    Err("--mmap requires trng to be built with the mmap feature.".to_string())
+}
+
+/// Parses `<file>[@<offset>]`.
+fn parse_preload(arg: &str) -> Option<(&str, usize)> {
+    match arg.rsplit_once('@') {
+        Some((file, offset)) => Some((file, parse_number(offset)?)),
+        None => Some((arg, 0)),
+    }
+}
+
+/// Parses `<file>[@<start>..<end>]`. Start and end may be omitted, e.g. `@16..`.
+/// A missing end is returned as `None` and stands for the end of the tape.
+fn parse_dump(arg: &str) -> Option<(&str, Option<DumpRange>)> {
+    let Some((file, range)) = arg.rsplit_once('@') else {
+        return Some((arg, None));
+    };
+
+    let (start, end) = range.split_once("..")?;
+    let start = match start {
+        "" => 0,
+        s => parse_number(s)?,
+    };
+    let end = match end {
+        "" => None,
+        e => Some(parse_number(e)?),
+    };
+
+    Some((file, Some((start, end))))
+}
+
+/// Parses a decimal number or a hexadecimal number prefixed with `0x`.
+fn parse_number(s: &str) -> Option<usize> {
+    match s.strip_prefix("0x") {
+        Some(hex) => usize::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    #[test]
+    fn parse_preload_test() {
+        assert_eq!(super::parse_preload("blob.bin"), Some(("blob.bin", 0)));
+        assert_eq!(
+            super::parse_preload("blob.bin@0x10"),
+            Some(("blob.bin", 16))
+        );
+        assert_eq!(super::parse_preload("blob.bin@x"), None);
+    }
+
+    #[test]
+    fn tape_size_test() {
+        let path = std::env::temp_dir().join(format!("trng-size-{}.bin", std::process::id()));
+        std::fs::write(&path, [0; 16]).unwrap();
+        let file = path.to_str().unwrap();
+
+        let fits = super::tape_size(&[(file, 0)]);
+        let grows = super::tape_size(&[(file, super::TAPE_SIZE)]);
+        let huge = super::tape_size(&[(file, 1 << 62)]);
+        let overflows = super::tape_size(&[(file, usize::MAX)]);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(fits, Ok(super::TAPE_SIZE));
+        assert_eq!(grows, Ok(super::TAPE_SIZE + 16));
+        assert!(huge.unwrap_err().contains("maximum tape size"));
+        assert!(overflows.unwrap_err().contains("maximum tape size"));
+    }
+
+    #[test]
+    fn parse_dump_test() {
+        assert_eq!(super::parse_dump("out.bin"), Some(("out.bin", None)));
+        assert_eq!(
+            super::parse_dump("out.bin@4..8"),
+            Some(("out.bin", Some((4, Some(8)))))
+        );
+        assert_eq!(
+            super::parse_dump("out.bin@..0x20"),
+            Some(("out.bin", Some((0, Some(32)))))
+        );
+        assert_eq!(
+            super::parse_dump("out.bin@16.."),
+            Some(("out.bin", Some((16, None))))
+        );
+        assert_eq!(super::parse_dump("out.bin@16"), None);
+    }
+}
@@ -0,0 +1,68 @@
+// TRNG - Brainfucks pretty sister.
+// Copyright (C) 2023 Lukas Pfeifer
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::path::Path;
+use std::process::ExitCode;
+
+/// Runs the golden tests in the directories and files given in the arguments.
+/// Directories are searched for programs with expectations, files are run as they are.
+/// The exit code is 1 if a test fails or cannot be loaded.
+pub fn run(args: &[String]) -> ExitCode {
+    if args.is_empty() {
+        return super::usage();
+    }
+
+    let mut code = ExitCode::SUCCESS;
+    let mut tests = vec![];
+    for path in args {
+        let loaded = if Path::new(path).is_dir() {
+            trng::discover(path)
+        } else {
+            trng::GoldenTest::from_file(path).map(|t| vec![t])
+        };
+
+        match loaded {
+            Ok(mut loaded) => tests.append(&mut loaded),
+            Err(e) => {
+                eprintln!("{}: {}", path, e);
+                code = ExitCode::FAILURE;
+            }
+        }
+    }
+
+    let (mut passed, mut failed) = (0, 0);
+    for test in tests {
+        let outcome = test.run();
+        match outcome.diff {
+            None => {
+                println!("ok     {}", outcome.path.display());
+                passed += 1;
+            }
+            Some(diff) => {
+                println!("FAILED {}", outcome.path.display());
+                print!("{}", diff);
+                failed += 1;
+            }
+        }
+    }
+
+    println!("{} passed, {} failed", passed, failed);
+    if failed > 0 {
+        code = ExitCode::FAILURE;
+    }
+
+    code
+}
@@ -0,0 +1,300 @@
+// TRNG - Brainfucks pretty sister.
+// Copyright (C) 2023 Lukas Pfeifer
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use lsp_types::{
+    CompletionItem, CompletionItemKind, Diagnostic, DiagnosticSeverity, DocumentHighlight,
+    DocumentHighlightKind, Documentation, Hover, HoverContents, MarkupContent, MarkupKind,
+    NumberOrString, Position, Range, TextEdit,
+};
+use trng::{Lexer, Program, Token, TokenType};
+
+/// The language specification. Hover texts and completion details are taken from its instruction table.
+const SPEC: &str = include_str!("../../doc/SPEC.md");
+
+/// Returns parse errors and lint diagnostics of the given document.
+/// # Arguments
+/// * `text` - The content of the document.
+pub fn diagnostics(text: &str) -> Vec<Diagnostic> {
+    let tokens = tokenize(text);
+
+    let program = match Program::parse_str(text) {
+        Ok(program) => program,
+        Err(e) => {
+            return vec![Diagnostic {
+                range: range_at(&tokens, e.line, e.column),
+                severity: Some(DiagnosticSeverity::ERROR),
+                source: Some("trng".to_string()),
+                message: e.description,
+                ..Default::default()
+            }];
+        }
+    };
+
+    trng::lint(&program)
+        .into_iter()
+        .map(|d| Diagnostic {
+            range: range_at(&tokens, d.line, d.column),
+            severity: Some(match d.severity {
+                trng::Severity::Error => DiagnosticSeverity::ERROR,
+                trng::Severity::Warning => DiagnosticSeverity::WARNING,
+            }),
+            code: Some(NumberOrString::String(d.code.code().to_string())),
+            source: Some("trng-lint".to_string()),
+            message: d.message,
+            ..Default::default()
+        })
+        .collect()
+}
+
+/// Returns a completion item for every instruction.
+pub fn completions() -> Vec<CompletionItem> {
+    TokenType::INSTRUCTIONS
+        .iter()
+        .filter_map(|t| t.op_code().map(|op| (t, op)))
+        .map(|(t, op)| {
+            let (arguments, description) = spec_entry(op);
+            CompletionItem {
+                label: op.to_string(),
+                kind: Some(CompletionItemKind::KEYWORD),
+                detail: Some(signature(t, op, &arguments)),
+                documentation: Some(Documentation::String(description)),
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+/// Returns the description of the instruction at the given position.
+/// # Arguments
+/// * `text` - The content of the document.
+/// * `position` - The position of the cursor.
+pub fn hover(text: &str, position: Position) -> Option<Hover> {
+    let tokens = tokenize(text);
+    let token = token_at(&tokens, position)?;
+    let op = token._type.op_code()?;
+    let (arguments, description) = spec_entry(op);
+
+    Some(Hover {
+        contents: HoverContents::Markup(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value: format!(
+                "```trng\n{}\n```\n\n{}",
+                signature(&token._type, op, &arguments),
+                description
+            ),
+        }),
+        range: Some(range(token)),
+    })
+}
+
+/// Returns the loop instruction at the given position and its matching counterpart.
+/// # Arguments
+/// * `text` - The content of the document.
+/// * `position` - The position of the cursor.
+pub fn highlights(text: &str, position: Position) -> Vec<DocumentHighlight> {
+    let tokens = tokenize(text);
+    let Some(selected) = token_at(&tokens, position) else {
+        return vec![];
+    };
+
+    let mut open: Vec<&Token> = vec![];
+    for token in &tokens {
+        match token._type {
+            TokenType::Lop | TokenType::Whl => open.push(token),
+            TokenType::Pol => {
+                let Some(start) = open.pop() else {
+                    continue;
+                };
+
+                if std::ptr::eq(start, selected) || std::ptr::eq(token, selected) {
+                    return [start, token]
+                        .iter()
+                        .map(|t| DocumentHighlight {
+                            range: range(t),
+                            kind: Some(DocumentHighlightKind::TEXT),
+                        })
+                        .collect();
+                }
+            }
+            _ => (),
+        }
+    }
+
+    vec![]
+}
+
+/// Returns an edit replacing the document with its formatted version.
+/// Returns `None` if the document cannot be parsed.
+/// # Arguments
+/// * `text` - The content of the document.
+pub fn formatting(text: &str) -> Option<Vec<TextEdit>> {
+    let formatted = trng::format_source(text.as_bytes()).ok()?;
+
+    let lines = text.split('\n').count() as u32;
+    let end = Position::new(lines, 0);
+
+    Some(vec![TextEdit::new(
+        Range::new(Position::new(0, 0), end),
+        formatted,
+    )])
+}
+
+fn tokenize(text: &str) -> Vec<Token<'_>> {
+    Lexer::new(text)
+        .collect::<Result<_, _>>()
+        .unwrap_or_default()
+}
+
+/// Returns the range of a token. Lines and columns of tokens start at 1, those of LSP at 0.
+fn range(token: &Token) -> Range {
+    let line = token.line.saturating_sub(1);
+    let column = token.column.saturating_sub(1);
+
+    Range::new(
+        Position::new(line, column),
+        Position::new(line, column + token.value.chars().count() as u32),
+    )
+}
+
+/// Returns the range of the token starting at the given line and column.
+/// If there is no such token an empty range is returned.
+fn range_at(tokens: &[Token], line: u32, column: u32) -> Range {
+    match tokens.iter().find(|t| t.line == line && t.column == column) {
+        Some(token) => range(token),
+        None => {
+            let position = Position::new(line.saturating_sub(1), column.saturating_sub(1));
+            Range::new(position, position)
+        }
+    }
+}
+
+/// Returns the token under the cursor.
+fn token_at<'a>(tokens: &'a [Token<'a>], position: Position) -> Option<&'a Token<'a>> {
+    tokens.iter().find(|t| {
+        let range = range(t);
+        range.start.line == position.line
+            && range.start.character <= position.character
+            && position.character <= range.end.character
+    })
+}
+
+/// Returns the instruction with its operand, e.g. `inc [integer]`.
+fn signature(_type: &TokenType, op: &str, arguments: &str) -> String {
+    if !_type.has_operand() {
+        return op.to_string();
+    }
+
+    match arguments {
+        "" => format!("{} [value]", op),
+        _ => format!("{} {}", op, arguments),
+    }
+}
+
+/// Returns the arguments and the description of an instruction from the instruction table of the specification.
+fn spec_entry(op: &str) -> (String, String) {
+    SPEC.lines()
+        .filter_map(|line| {
+            // |[inc](#inc)      |[integer]|Increments the value of the current cell by [integer].
+            let mut columns = line.strip_prefix("|[")?.split('|');
+            let name = columns.next()?.split(']').next()?;
+            let arguments = columns.next()?.trim();
+            let description = columns.next()?.trim();
+
+            (name == op).then(|| (arguments.to_string(), description.to_string()))
+        })
+        .next()
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use lsp_types::{DiagnosticSeverity, HoverContents, Position};
+
+    #[test]
+    fn parse_error_is_diagnostic_test() {
+        let diagnostics = super::diagnostics("inc 1\nfoo");
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Some(DiagnosticSeverity::ERROR));
+        assert_eq!(diagnostics[0].range.start, Position::new(1, 0));
+        assert_eq!(diagnostics[0].range.end, Position::new(1, 3));
+    }
+
+    #[test]
+    fn lint_warning_is_diagnostic_test() {
+        let diagnostics = super::diagnostics("inc 0");
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Some(DiagnosticSeverity::WARNING));
+    }
+
+    #[test]
+    fn every_instruction_is_documented_test() {
+        for item in super::completions() {
+            let documentation = match item.documentation {
+                Some(lsp_types::Documentation::String(s)) => s,
+                _ => String::new(),
+            };
+            assert!(
+                !documentation.is_empty(),
+                "{} is not documented",
+                item.label
+            );
+        }
+    }
+
+    #[test]
+    fn completion_shows_operand_test() {
+        let items = super::completions();
+        let inc = items.iter().find(|i| i.label == "inc").unwrap();
+        let wrt = items.iter().find(|i| i.label == "wrt").unwrap();
+
+        assert_eq!(inc.detail.as_deref(), Some("inc [integer]"));
+        assert_eq!(wrt.detail.as_deref(), Some("wrt"));
+    }
+
+    #[test]
+    fn hover_shows_description_test() {
+        let hover = super::hover("inc 1\nwrt", Position::new(1, 1)).unwrap();
+
+        let HoverContents::Markup(content) = hover.contents else {
+            panic!("Hover should be markdown.");
+        };
+        assert!(content.value.contains("standard output"));
+        assert!(super::hover("inc 1\nwrt", Position::new(0, 4)).is_none());
+    }
+
+    #[test]
+    fn highlights_match_loops_test() {
+        let code = "lop\n    whl\n    pol\npol";
+
+        let outer = super::highlights(code, Position::new(3, 1));
+        let inner = super::highlights(code, Position::new(1, 5));
+
+        assert_eq!(outer.len(), 2);
+        assert_eq!(outer[0].range.start, Position::new(0, 0));
+        assert_eq!(inner[0].range.start, Position::new(1, 4));
+        assert_eq!(inner[1].range.start, Position::new(2, 4));
+    }
+
+    #[test]
+    fn formatting_replaces_document_test() {
+        let edits = super::formatting("inc 1 lop dec 1 pol").unwrap();
+
+        assert_eq!(edits[0].new_text, "inc 1\nlop\n    dec 1\npol\n");
+        assert!(super::formatting("lop").is_none());
+    }
+}
@@ -0,0 +1,193 @@
+// TRNG - Brainfucks pretty sister.
+// Copyright (C) 2023 Lukas Pfeifer
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+mod analysis;
+
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument,
+    Notification as LspNotification, PublishDiagnostics,
+};
+use lsp_types::request::{
+    Completion, DocumentHighlightRequest, Formatting, HoverRequest, Request as LspRequest,
+};
+use lsp_types::{
+    CompletionOptions, CompletionResponse, HoverProviderCapability, OneOf,
+    PublishDiagnosticsParams, ServerCapabilities, TextDocumentSyncCapability, TextDocumentSyncKind,
+    Url,
+};
+use std::collections::HashMap;
+use std::error::Error;
+
+type ServerResult<T> = Result<T, Box<dyn Error + Sync + Send>>;
+
+fn main() -> ServerResult<()> {
+    let (connection, io_threads) = Connection::stdio();
+
+    connection.initialize(serde_json::to_value(capabilities())?)?;
+    // The connection has to be dropped before joining, otherwise the writer thread never ends.
+    Server::default().run(connection)?;
+
+    io_threads.join()?;
+    Ok(())
+}
+
+/// Returns the features the server supports.
+fn capabilities() -> ServerCapabilities {
+    ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        completion_provider: Some(CompletionOptions::default()),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        document_highlight_provider: Some(OneOf::Left(true)),
+        document_formatting_provider: Some(OneOf::Left(true)),
+        ..Default::default()
+    }
+}
+
+/// Keeps the content of all open documents.
+#[derive(Default)]
+struct Server {
+    documents: HashMap<Url, String>,
+}
+
+impl Server {
+    /// Handles messages until the client shuts the server down.
+    /// # Arguments
+    /// * `connection` - The connection to the client.
+    fn run(&mut self, connection: Connection) -> ServerResult<()> {
+        for message in &connection.receiver {
+            match message {
+                Message::Request(request) => {
+                    if connection.handle_shutdown(&request)? {
+                        return Ok(());
+                    }
+
+                    let response = self.handle_request(request);
+                    connection.sender.send(Message::Response(response))?;
+                }
+                Message::Notification(notification) => {
+                    let method = notification.method.clone();
+                    let uri = match self.handle_notification(notification) {
+                        Ok(uri) => uri,
+                        // Notifications cannot be answered, so malformed ones are only logged.
+                        Err(e) => {
+                            eprintln!("Ignoring invalid notification '{}': {}", method, e);
+                            None
+                        }
+                    };
+                    if let Some(uri) = uri {
+                        let params = PublishDiagnosticsParams::new(
+                            uri.clone(),
+                            analysis::diagnostics(self.text(&uri)),
+                            None,
+                        );
+                        connection
+                            .sender
+                            .send(Message::Notification(Notification::new(
+                                PublishDiagnostics::METHOD.to_string(),
+                                params,
+                            )))?;
+                    }
+                }
+                Message::Response(_) => (),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn handle_request(&self, request: Request) -> Response {
+        match request.method.as_str() {
+            Completion::METHOD => respond::<Completion>(request, |_| {
+                Some(CompletionResponse::Array(analysis::completions()))
+            }),
+            HoverRequest::METHOD => respond::<HoverRequest>(request, |params| {
+                let position = params.text_document_position_params;
+                analysis::hover(self.text(&position.text_document.uri), position.position)
+            }),
+            DocumentHighlightRequest::METHOD => {
+                respond::<DocumentHighlightRequest>(request, |params| {
+                    let position = params.text_document_position_params;
+                    Some(analysis::highlights(
+                        self.text(&position.text_document.uri),
+                        position.position,
+                    ))
+                })
+            }
+            Formatting::METHOD => respond::<Formatting>(request, |params| {
+                analysis::formatting(self.text(&params.text_document.uri))
+            }),
+            _ => Response::new_err(
+                request.id,
+                ErrorCode::MethodNotFound as i32,
+                format!("Unsupported request '{}'.", request.method),
+            ),
+        }
+    }
+
+    /// Updates the open documents. Returns the document whose diagnostics have to be published.
+    fn handle_notification(&mut self, notification: Notification) -> ServerResult<Option<Url>> {
+        match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params: <DidOpenTextDocument as LspNotification>::Params =
+                    serde_json::from_value(notification.params)?;
+                let uri = params.text_document.uri;
+                self.documents
+                    .insert(uri.clone(), params.text_document.text);
+                Ok(Some(uri))
+            }
+            DidChangeTextDocument::METHOD => {
+                let params: <DidChangeTextDocument as LspNotification>::Params =
+                    serde_json::from_value(notification.params)?;
+                let uri = params.text_document.uri;
+                // The whole document is synchronized, so the last change holds the full text.
+                if let Some(change) = params.content_changes.into_iter().last() {
+                    self.documents.insert(uri.clone(), change.text);
+                }
+                Ok(Some(uri))
+            }
+            DidCloseTextDocument::METHOD => {
+                let params: <DidCloseTextDocument as LspNotification>::Params =
+                    serde_json::from_value(notification.params)?;
+                self.documents.remove(&params.text_document.uri);
+                Ok(None)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Returns the text of an open document or an empty string if the document is unknown.
+    fn text(&self, uri: &Url) -> &str {
+        self.documents
+            .get(uri)
+            .map(String::as_str)
+            .unwrap_or_default()
+    }
+}
+
+/// Answers a request with the result of the given handler.
+/// # Arguments
+/// * `request` - The request to answer.
+/// * `handler` - Computes the result from the request parameters.
+fn respond<R>(request: Request, handler: impl FnOnce(R::Params) -> R::Result) -> Response
+where
+    R: LspRequest,
+{
+    match serde_json::from_value::<R::Params>(request.params) {
+        Ok(params) => Response::new_ok(request.id, handler(params)),
+        Err(e) => Response::new_err(request.id, ErrorCode::InvalidParams as i32, e.to_string()),
+    }
+}
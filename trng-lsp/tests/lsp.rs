@@ -0,0 +1,202 @@
+// TRNG - Brainfucks pretty sister.
+// Copyright (C) 2023 Lukas Pfeifer
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+/// Talks to a `trng-lsp` process over its standard input and output.
+struct Client {
+    process: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    next_id: i64,
+}
+
+impl Client {
+    fn start() -> Client {
+        let mut process = Command::new(env!("CARGO_BIN_EXE_trng-lsp"))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+
+        let stdin = process.stdin.take().unwrap();
+        let stdout = BufReader::new(process.stdout.take().unwrap());
+
+        Client {
+            process,
+            stdin,
+            stdout,
+            next_id: 1,
+        }
+    }
+
+    fn send(&mut self, message: Value) {
+        let body = message.to_string();
+        write!(self.stdin, "Content-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+        self.stdin.flush().unwrap();
+    }
+
+    fn receive(&mut self) -> Value {
+        let mut length = 0;
+        loop {
+            let mut header = String::new();
+            self.stdout.read_line(&mut header).unwrap();
+            let header = header.trim();
+
+            if header.is_empty() {
+                break;
+            }
+            if let Some(value) = header.strip_prefix("Content-Length: ") {
+                length = value.parse().unwrap();
+            }
+        }
+
+        let mut body = vec![0; length];
+        self.stdout.read_exact(&mut body).unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    fn request(&mut self, method: &str, params: Value) -> Value {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.send(json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params}));
+
+        let response = self.receive();
+        assert_eq!(response["id"], id);
+        response
+    }
+
+    fn notify(&mut self, method: &str, params: Value) {
+        self.send(json!({"jsonrpc": "2.0", "method": method, "params": params}));
+    }
+
+    fn shutdown(mut self) {
+        self.request("shutdown", Value::Null);
+        self.notify("exit", Value::Null);
+        assert!(self.process.wait().unwrap().success());
+    }
+}
+
+const URI: &str = "file:///test.trng";
+
+fn position(line: u32, character: u32) -> Value {
+    json!({"textDocument": {"uri": URI}, "position": {"line": line, "character": character}})
+}
+
+fn start_with_document(text: &str) -> (Client, Value) {
+    let mut client = Client::start();
+
+    let initialize = client.request("initialize", json!({"capabilities": {}}));
+    assert_eq!(
+        initialize["result"]["capabilities"]["hoverProvider"],
+        json!(true)
+    );
+    client.notify("initialized", json!({}));
+
+    client.notify(
+        "textDocument/didOpen",
+        json!({"textDocument": {"uri": URI, "languageId": "trng", "version": 1, "text": text}}),
+    );
+    let diagnostics = client.receive();
+    assert_eq!(diagnostics["method"], "textDocument/publishDiagnostics");
+
+    (client, diagnostics["params"]["diagnostics"].clone())
+}
+
+#[test]
+fn publishes_parse_errors_test() {
+    let (mut client, diagnostics) = start_with_document("inc 1\nlop\n");
+
+    assert_eq!(diagnostics.as_array().unwrap().len(), 1);
+    assert_eq!(diagnostics[0]["severity"], 1);
+    assert_eq!(
+        diagnostics[0]["range"]["start"],
+        json!({"line": 1, "character": 0})
+    );
+
+    client.notify(
+        "textDocument/didChange",
+        json!({"textDocument": {"uri": URI, "version": 2}, "contentChanges": [{"text": "inc 1\nlop\ndec 1\npol\n"}]}),
+    );
+    let published = client.receive();
+    assert_eq!(published["params"]["diagnostics"], json!([]));
+
+    client.shutdown();
+}
+
+#[test]
+fn ignores_invalid_notifications_test() {
+    let (mut client, _) = start_with_document("inc 1\n");
+
+    client.notify("textDocument/didChange", json!({"textDocument": {}}));
+
+    let hover = client.request("textDocument/hover", position(0, 1));
+    let text = hover["result"]["contents"]["value"].as_str().unwrap();
+    assert!(text.contains("inc [integer]"));
+
+    client.shutdown();
+}
+
+#[test]
+fn completion_hover_and_highlight_test() {
+    let (mut client, _) = start_with_document("inc 1\nlop\n    dec 1\npol\n");
+
+    let completion = client.request("textDocument/completion", position(0, 0));
+    let items = completion["result"].as_array().unwrap();
+    assert!(items
+        .iter()
+        .any(|i| i["label"] == "setu16" && i["detail"] == "setu16 [value]"));
+
+    let hover = client.request("textDocument/hover", position(0, 1));
+    let text = hover["result"]["contents"]["value"].as_str().unwrap();
+    assert!(text.contains("inc [integer]"));
+
+    let highlight = client.request("textDocument/documentHighlight", position(3, 0));
+    let ranges: Vec<&Value> = highlight["result"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|h| &h["range"]["start"])
+        .collect();
+    assert_eq!(
+        ranges,
+        vec![
+            &json!({"line": 1, "character": 0}),
+            &json!({"line": 3, "character": 0})
+        ]
+    );
+
+    client.shutdown();
+}
+
+#[test]
+fn formatting_test() {
+    let (mut client, _) = start_with_document("inc 1 lop dec 1 pol");
+
+    let formatting = client.request(
+        "textDocument/formatting",
+        json!({"textDocument": {"uri": URI}, "options": {"tabSize": 4, "insertSpaces": true}}),
+    );
+
+    assert_eq!(
+        formatting["result"][0]["newText"],
+        "inc 1\nlop\n    dec 1\npol\n"
+    );
+
+    client.shutdown();
+}
@@ -0,0 +1,240 @@
+// TRNG - Brainfucks pretty sister.
+// Copyright (C) 2023 Lukas Pfeifer
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use proc_macro2::{Span, TokenStream, TokenTree};
+use quote::{format_ident, quote, quote_spanned};
+use trng::{Instruction, Program};
+
+/// Parses TRNG code at compile time and expands to the resulting `trng::Program`.
+/// This embeds TRNG programs in Rust code without parsing them at run time.
+///
+/// Syntax errors are reported as compiler errors at the offending token.
+///
+/// ```ignore
+/// let program = trng_macros::trng! {
+///     set Hello
+///     pbw 5
+///     wra
+/// };
+/// ```
+#[proc_macro]
+pub fn trng(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    expand(input.into()).into()
+}
+
+/// A piece of the reconstructed TRNG source and the Rust token it came from.
+struct Piece {
+    /// Line of the piece in the reconstructed source.
+    line: u32,
+    /// First column of the piece in the reconstructed source.
+    column: u32,
+    span: Span,
+}
+
+/// Parses the given tokens as TRNG code and returns the expression for the program or a compiler error.
+/// # Arguments
+/// * `input` - The tokens passed to the macro.
+fn expand(input: TokenStream) -> TokenStream {
+    let (source, pieces) = reconstruct(input);
+
+    match Program::parse_str(&source) {
+        Ok(program) => program_tokens(&program),
+        Err(e) => {
+            let span = pieces
+                .iter()
+                .rev()
+                .find(|p| p.line == e.line && p.column <= e.column)
+                .map(|p| p.span)
+                .unwrap_or_else(Span::call_site);
+            let message = e.to_string();
+
+            quote_spanned! {span=> compile_error!(#message) }
+        }
+    }
+}
+
+/// Rebuilds the TRNG source from the Rust tokens.
+/// Tokens that touch in the Rust source, e.g. `-` and `5`, stay together.
+fn reconstruct(input: TokenStream) -> (String, Vec<Piece>) {
+    let mut source = String::new();
+    let mut pieces = vec![];
+
+    let mut line = 1;
+    let mut column = 1;
+    let mut last: Option<(usize, usize)> = None;
+
+    for tree in input {
+        let span = tree.span();
+        let start = span.start();
+        let end = span.end();
+
+        match last {
+            Some((l, c)) if l == start.line && c == start.column => {}
+            Some((l, _)) if l < start.line => {
+                source.push('\n');
+                line += 1;
+                column = 1;
+            }
+            Some(_) => {
+                source.push(' ');
+                column += 1;
+            }
+            None => {}
+        }
+
+        let text = token_text(&tree);
+        pieces.push(Piece { line, column, span });
+        column += text.chars().count() as u32;
+        source.push_str(&text);
+
+        last = Some((end.line, end.column));
+    }
+
+    (source, pieces)
+}
+
+fn token_text(tree: &TokenTree) -> String {
+    match tree {
+        TokenTree::Literal(l) => {
+            let text = l.to_string();
+            // Allow quoted text for `set`, e.g. `set "Hello"`.
+            match text.strip_prefix('"').and_then(|t| t.strip_suffix('"')) {
+                Some(inner) => inner.to_string(),
+                None => text,
+            }
+        }
+        other => other.to_string(),
+    }
+}
+
+/// Returns the expression that builds the given program.
+fn program_tokens(program: &Program) -> TokenStream {
+    let instructions = program.instructions.iter().map(instruction_tokens);
+    let lines = program.positions.iter().map(|p| p.line);
+    let columns = program.positions.iter().map(|p| p.column);
+    let count = program.len();
+
+    quote! {
+        ::trng::Program {
+            instructions: ::std::vec![#(#instructions),*],
+            positions: ::std::vec![#(::trng::Position { line: #lines, column: #columns }),*],
+            origins: ::std::vec![::std::vec::Vec::new(); #count],
+            wrapping: false,
+        }
+    }
+}
+
+/// Returns the expression that builds the given instruction.
+fn instruction_tokens(instruction: &Instruction) -> TokenStream {
+    let op_code = instruction.op_code();
+    let variant = format_ident!("{}{}", op_code[..1].to_uppercase(), &op_code[1..]);
+
+    let operand = match instruction {
+        Instruction::Pfw(v)
+        | Instruction::Pbw(v)
+        | Instruction::Ptr(v)
+        | Instruction::Lop(v)
+        | Instruction::Whl(v)
+        | Instruction::Pol(v) => quote!(#v),
+        Instruction::Inc(v) | Instruction::Dec(v) | Instruction::Setu8(v) => quote!(#v),
+        Instruction::Set(v) => quote!(::std::string::String::from(#v)),
+        Instruction::Seti8(v) => quote!(#v),
+        Instruction::Seti16(v) => quote!(#v),
+        Instruction::Seti32(v) => quote!(#v),
+        Instruction::Seti64(v) => quote!(#v),
+        Instruction::Setu16(v) => quote!(#v),
+        Instruction::Setu32(v) => quote!(#v),
+        Instruction::Setu64(v) => quote!(#v),
+        // Go through the bits so every value including NaN survives unchanged.
+        Instruction::Setf32(v) => {
+            let bits = v.to_bits();
+            quote!(f32::from_bits(#bits))
+        }
+        Instruction::Setf64(v) => {
+            let bits = v.to_bits();
+            quote!(f64::from_bits(#bits))
+        }
+        Instruction::Ast(v) | Instruction::Astu8(v) => quote!(#v),
+        Instruction::Asti8(v) => quote!(#v),
+        Instruction::Asti16(v) => quote!(#v),
+        Instruction::Asti32(v) => quote!(#v),
+        Instruction::Asti64(v) => quote!(#v),
+        Instruction::Astu16(v) => quote!(#v),
+        Instruction::Astu32(v) => quote!(#v),
+        Instruction::Astu64(v) => quote!(#v),
+        Instruction::Astf32(v, eps) => {
+            let (bits, eps) = (v.to_bits(), eps.to_bits());
+            quote!(f32::from_bits(#bits), f32::from_bits(#eps))
+        }
+        Instruction::Astf64(v, eps) => {
+            let (bits, eps) = (v.to_bits(), eps.to_bits());
+            quote!(f64::from_bits(#bits), f64::from_bits(#eps))
+        }
+        Instruction::Asp(v) => quote!(#v),
+        _ => return quote!(::trng::Instruction::#variant),
+    };
+
+    quote!(::trng::Instruction::#variant(#operand))
+}
+
+#[cfg(test)]
+mod tests {
+    use proc_macro2::TokenStream;
+    use std::str::FromStr;
+
+    fn reconstructed(code: &str) -> String {
+        super::reconstruct(TokenStream::from_str(code).unwrap()).0
+    }
+
+    #[test]
+    fn reconstruct_keeps_lines_test() {
+        assert_eq!(
+            reconstructed("inc 3\nlop\n  wrt dec 1\npol"),
+            "inc 3\nlop\nwrt dec 1\npol"
+        );
+    }
+
+    #[test]
+    fn reconstruct_joins_touching_tokens_test() {
+        assert_eq!(
+            reconstructed("seti8 -5 setf32 -1.5"),
+            "seti8 -5 setf32 -1.5"
+        );
+    }
+
+    #[test]
+    fn reconstruct_unquotes_strings_test() {
+        assert_eq!(reconstructed("set \"Hi!\""), "set Hi!");
+    }
+
+    #[test]
+    fn expand_builds_program_test() {
+        let expanded =
+            super::expand(TokenStream::from_str("inc 3 lop dec 1 pol").unwrap()).to_string();
+
+        assert!(expanded.contains(":: trng :: Instruction :: Inc (3u8)"));
+        assert!(expanded.contains(":: trng :: Instruction :: Lop (3usize)"));
+        assert!(expanded.contains(":: trng :: Instruction :: Pol (1usize)"));
+    }
+
+    #[test]
+    fn expand_unknown_token_is_compile_error_test() {
+        let expanded = super::expand(TokenStream::from_str("inc 3 foo").unwrap()).to_string();
+
+        assert!(expanded.starts_with("compile_error !"));
+        assert!(expanded.contains("Unknown token"));
+    }
+}
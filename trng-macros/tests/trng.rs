@@ -0,0 +1,56 @@
+// TRNG - Brainfucks pretty sister.
+// Copyright (C) 2023 Lukas Pfeifer
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use trng::{Interpreter, Program, SharedBuffer};
+use trng_macros::trng;
+
+#[test]
+fn macro_matches_runtime_parse_test() {
+    let program = trng! {
+        set Hello
+        pbw 5
+        wra
+        inc 3
+        lop
+            dec 1
+        pol
+        seti8 -5
+        setf32 1.25
+        astf64 -2.5 0.01
+        asp 4
+    };
+
+    let parsed = Program::parse(
+        "set Hello\npbw 5\nwra\ninc 3\nlop\ndec 1\npol\nseti8 -5\nsetf32 1.25\nastf64 -2.5 0.01\nasp 4"
+            .as_bytes(),
+    )
+    .unwrap();
+
+    assert_eq!(program.instructions, parsed.instructions);
+}
+
+#[test]
+fn macro_program_runs_test() {
+    let out = SharedBuffer::new();
+    let mut interpreter = Interpreter::default();
+    interpreter.set_output(out.clone());
+
+    interpreter
+        .run_program(trng! { set "Hi!" pbw 3 wra })
+        .unwrap();
+
+    assert_eq!(out.contents(), b"Hi!");
+}
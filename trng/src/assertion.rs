@@ -0,0 +1,163 @@
+// TRNG - Brainfucks pretty sister.
+// Copyright (C) 2023 Lukas Pfeifer
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::interpreter::InterpreterResult;
+use crate::program::{Instruction, Position};
+use crate::tape::{Num, Tape, TapeResult};
+
+/// Defines error types for assertion errors.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AssertionErrorType {
+    /// The value at the pointer differs from the expected value.
+    Value,
+    /// The pointer is not at the expected index.
+    Pointer,
+}
+
+impl std::fmt::Display for AssertionErrorType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AssertionErrorType::Value => {
+                write!(f, "Value error")
+            }
+            AssertionErrorType::Pointer => {
+                write!(f, "Pointer error")
+            }
+        }
+    }
+}
+
+/// Defines an error of a failed assertion like `ast` or `asp`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssertionError {
+    pub description: String,
+    pub _type: AssertionErrorType,
+    /// The value the assertion expected as written in the source, e.g. `1.5 +/- 0.01` for `astf64 1.5 0.01`.
+    pub expected: String,
+    /// The value that was found.
+    pub actual: String,
+    /// Line number of the assertion.
+    pub line: u32,
+    /// Column number of the assertion.
+    pub column: u32,
+}
+
+impl std::fmt::Display for AssertionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Assertion Error [{}]: {}", self._type, self.description)
+    }
+}
+
+impl std::error::Error for AssertionError {}
+
+impl AssertionError {
+    pub fn new(
+        _type: AssertionErrorType,
+        expected: String,
+        actual: String,
+        line: u32,
+        column: u32,
+    ) -> AssertionError {
+        let description = format!(
+            "Expected {} but found {}. - ln: {}, col: {}",
+            expected, actual, line, column
+        );
+
+        Self {
+            description,
+            _type,
+            expected,
+            actual,
+            line,
+            column,
+        }
+    }
+}
+
+/// Checks an assertion against the tape. Other instructions always pass.
+/// # Arguments
+/// * `tape` - The tape to check.
+/// * `instruction` - The assertion.
+/// * `position` - The source position of the assertion.
+pub(crate) fn check(
+    tape: &Tape,
+    instruction: &Instruction,
+    position: Position,
+) -> InterpreterResult<()> {
+    let (_type, expected, actual) = match instruction {
+        Instruction::Ast(v) | Instruction::Astu8(v) => value(tape, *v)?,
+        Instruction::Asti8(v) => value(tape, *v)?,
+        Instruction::Asti16(v) => value(tape, *v)?,
+        Instruction::Asti32(v) => value(tape, *v)?,
+        Instruction::Asti64(v) => value(tape, *v)?,
+        Instruction::Astu16(v) => value(tape, *v)?,
+        Instruction::Astu32(v) => value(tape, *v)?,
+        Instruction::Astu64(v) => value(tape, *v)?,
+        Instruction::Astf32(v, eps) => {
+            let actual: f32 = tape.get_num()?;
+            approximate(actual == *v || (actual - v).abs() <= *eps, v, eps, actual)
+        }
+        Instruction::Astf64(v, eps) => {
+            let actual: f64 = tape.get_num()?;
+            approximate(actual == *v || (actual - v).abs() <= *eps, v, eps, actual)
+        }
+        Instruction::Asp(index) => (
+            (tape.ptr_index != *index).then_some(AssertionErrorType::Pointer),
+            format!("pointer index {}", index),
+            format!("pointer index {}", tape.ptr_index),
+        ),
+        _ => return Ok(()),
+    };
+
+    match _type {
+        Some(_type) => {
+            Err(AssertionError::new(_type, expected, actual, position.line, position.column).into())
+        }
+        None => Ok(()),
+    }
+}
+
+/// Compares the number at the pointer with the expected value.
+/// Returns the error type if they differ together with both values.
+fn value<T>(tape: &Tape, expected: T) -> TapeResult<(Option<AssertionErrorType>, String, String)>
+where
+    T: Num + PartialEq,
+{
+    let actual: T = tape.get_num()?;
+
+    Ok((
+        (actual != expected).then_some(AssertionErrorType::Value),
+        expected.to_string(),
+        actual.to_string(),
+    ))
+}
+
+/// Returns the error type if a float is not close enough to the expected value together with both values.
+fn approximate<T>(
+    close: bool,
+    expected: &T,
+    epsilon: &T,
+    actual: T,
+) -> (Option<AssertionErrorType>, String, String)
+where
+    T: std::fmt::Display,
+{
+    (
+        (!close).then_some(AssertionErrorType::Value),
+        format!("{} +/- {}", expected, epsilon),
+        actual.to_string(),
+    )
+}
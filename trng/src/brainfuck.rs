@@ -0,0 +1,178 @@
+// TRNG - Brainfucks pretty sister.
+// Copyright (C) 2023 Lukas Pfeifer
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::program::{Instruction, ParseError, ParseErrorType, ParseResult, Position, Program};
+use std::io::{BufReader, Read};
+
+/// Parses Brainfuck code into a program for the TRNG interpreter.
+///
+/// Runs of `+`, `-`, `>` and `<` are folded into single `inc`, `dec`, `pfw` and `pbw` instructions,
+/// `[` and `]` become `whl` and `pol`, `.` becomes `wrt` and `,` becomes `rdi`.
+/// All other characters are comments.
+///
+/// Cells wrap around like in most Brainfuck implementations, i.e. `-` on a zero cell yields 255.
+/// # Arguments
+/// * `read_from` - Source for Brainfuck code. Can be anything that implements the `Read` trait.
+pub fn parse<T>(read_from: T) -> ParseResult<Program>
+where
+    T: Read,
+{
+    let mut program = Program {
+        wrapping: true,
+        ..Program::default()
+    };
+
+    let mut line = 1;
+    let mut column = 1;
+    let mut open: Vec<Position> = vec![];
+
+    for bres in BufReader::new(read_from).bytes() {
+        let byte = bres?;
+        let position = Position { line, column };
+
+        if byte == b'\n' {
+            line += 1;
+            column = 1;
+        } else {
+            column += 1;
+        }
+
+        match byte {
+            b'+' => fold(&mut program, position, Instruction::Inc(1)),
+            b'-' => fold(&mut program, position, Instruction::Dec(1)),
+            b'>' => fold(&mut program, position, Instruction::Pfw(1)),
+            b'<' => fold(&mut program, position, Instruction::Pbw(1)),
+            b'[' => {
+                open.push(position);
+                program.push(Instruction::Whl(0), position);
+            }
+            b']' => {
+                if open.pop().is_none() {
+                    return Err(bracket_error("']' without a matching '['", position));
+                }
+                program.push(Instruction::Pol(0), position);
+            }
+            b'.' => program.push(Instruction::Wrt, position),
+            b',' => program.push(Instruction::Rdi, position),
+            _ => (),
+        }
+    }
+
+    if let Some(position) = open.pop() {
+        return Err(bracket_error("'[' without a matching ']'", position));
+    }
+
+    program.resolve_loops()?;
+
+    Ok(program)
+}
+
+fn bracket_error(what: &str, position: Position) -> ParseError {
+    ParseError::new(
+        ParseErrorType::Loop,
+        format!(
+            "Found {}. - ln: {}, col: {}",
+            what, position.line, position.column
+        ),
+        position.line,
+        position.column,
+    )
+}
+
+/// Appends the given single step instruction or folds it into the previous instruction of the same kind.
+fn fold(program: &mut Program, position: Position, instruction: Instruction) {
+    let folded = match (program.instructions.last(), &instruction) {
+        (Some(Instruction::Inc(n)), Instruction::Inc(_)) if *n < u8::MAX => {
+            Some(Instruction::Inc(n + 1))
+        }
+        (Some(Instruction::Dec(n)), Instruction::Dec(_)) if *n < u8::MAX => {
+            Some(Instruction::Dec(n + 1))
+        }
+        (Some(Instruction::Pfw(n)), Instruction::Pfw(_)) => Some(Instruction::Pfw(n + 1)),
+        (Some(Instruction::Pbw(n)), Instruction::Pbw(_)) => Some(Instruction::Pbw(n + 1)),
+        _ => None,
+    };
+
+    match folded {
+        Some(f) => {
+            let last = program.len() - 1;
+            program.instructions[last] = f;
+
+            let origins = &mut program.origins[last];
+            if origins.is_empty() {
+                origins.push(program.positions[last]);
+            }
+            origins.push(position);
+        }
+        None => program.push(instruction, position),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::program::Instruction;
+
+    #[test]
+    fn parse_folds_runs_test() {
+        let program = super::parse("+++>>-<[-]. comment ,".as_bytes()).unwrap();
+
+        assert_eq!(
+            program.instructions,
+            vec![
+                Instruction::Inc(3),
+                Instruction::Pfw(2),
+                Instruction::Dec(1),
+                Instruction::Pbw(1),
+                Instruction::Whl(6),
+                Instruction::Dec(1),
+                Instruction::Pol(4),
+                Instruction::Wrt,
+                Instruction::Rdi,
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_records_origins_test() {
+        let program = super::parse("++\n+.".as_bytes()).unwrap();
+
+        let origins: Vec<(u32, u32)> = program.origins[0]
+            .iter()
+            .map(|p| (p.line, p.column))
+            .collect();
+        assert_eq!(origins, vec![(1, 1), (1, 2), (2, 1)]);
+        assert!(program.origins[1].is_empty());
+    }
+
+    #[test]
+    fn parse_splits_long_runs_test() {
+        let code = "+".repeat(300);
+        let program = super::parse(code.as_bytes()).unwrap();
+
+        assert_eq!(
+            program.instructions,
+            vec![Instruction::Inc(255), Instruction::Inc(45)]
+        );
+    }
+
+    #[test]
+    fn parse_unbalanced_is_err_test() {
+        let err = super::parse("+[".as_bytes()).unwrap_err();
+
+        assert!(err.description.contains("'['"));
+        assert_eq!(err.column, 2);
+    }
+}
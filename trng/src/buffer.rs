@@ -0,0 +1,76 @@
+// TRNG - Brainfucks pretty sister.
+// Copyright (C) 2023 Lukas Pfeifer
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+/// An in-memory output that can be handed to an interpreter while keeping access to what was written.
+///
+/// All clones of a buffer share the same contents.
+#[derive(Clone, Default, Debug)]
+pub struct SharedBuffer {
+    contents: Arc<Mutex<Vec<u8>>>,
+}
+
+impl SharedBuffer {
+    /// Creates a new empty buffer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a copy of everything written so far.
+    pub fn contents(&self) -> Vec<u8> {
+        self.lock().clone()
+    }
+
+    /// Removes everything written so far and returns it.
+    pub fn take(&self) -> Vec<u8> {
+        std::mem::take(&mut *self.lock())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<u8>> {
+        // A panic while holding the lock cannot leave the bytes in an invalid state.
+        self.contents.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.lock().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    #[test]
+    fn clones_share_contents_test() {
+        let buffer = super::SharedBuffer::new();
+        let mut clone = buffer.clone();
+
+        clone.write_all(b"Hello").unwrap();
+
+        assert_eq!(buffer.contents(), b"Hello");
+        assert_eq!(buffer.take(), b"Hello");
+        assert!(clone.contents().is_empty());
+    }
+}
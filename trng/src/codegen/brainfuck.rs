@@ -0,0 +1,406 @@
+// TRNG - Brainfucks pretty sister.
+// Copyright (C) 2023 Lukas Pfeifer
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::{error_at, CodegenError, CodegenErrorType, CodegenResult};
+use crate::program::{Instruction, Program};
+
+/// The statically known pointer state at a point in the program.
+#[derive(Clone, PartialEq)]
+struct State {
+    /// The pointer index if it is known.
+    ptr: Option<usize>,
+    /// The bookmark stack. Unknown positions are `None`.
+    bookmarks: Vec<Option<usize>>,
+}
+
+struct Emitter<'a> {
+    program: &'a Program,
+    out: String,
+}
+
+/// Lowers the given program to Brainfuck.
+/// Returns an error for instructions that cannot be lowered exactly.
+///
+/// The generated code behaves exactly like the TRNG program on a Brainfuck implementation
+/// with wrapping 8-bit cells, e.g. `Interpreter::run_brainfuck`.
+///
+/// * `lop` loops are lowered by emitting the loop body once in front of a `[...]` loop,
+///   so nested `lop` loops grow the generated code exponentially.
+/// * `set`, `seti*`, `setu*` and `setf*` clear each cell with `[-]` before setting its value.
+/// * `ptr`, `mrk` and `ret` are only supported where the pointer position is known statically,
+///   i.e. not after `wra`, `clr` or a loop that moves the pointer by a different amount on each pass.
+/// * `rda` reads until LF with `,` in a loop and leaves 0 instead of the previous value in the cell
+///   after the last byte read. It relies on `,` leaving the cell unchanged at the end of the input
+///   like `Interpreter::run_brainfuck` does. Implementations storing 0 or 255 at the end of the
+///   input keep reading forever if the input does not end with LF.
+///
+/// The typed output instructions (`wrti*`, `wrtu*`, `wrtf*`) and the value stack instructions
+/// cannot be lowered exactly.
+/// # Arguments
+/// * `program` - The program to lower.
+pub fn to_brainfuck(program: &Program) -> CodegenResult<String> {
+    let mut emitter = Emitter {
+        program,
+        out: String::new(),
+    };
+
+    let mut state = State {
+        ptr: Some(0),
+        bookmarks: vec![],
+    };
+
+    emitter.emit_range(0, program.len(), &mut state, 0)?;
+
+    Ok(emitter.out)
+}
+
+impl Emitter<'_> {
+    /// Emits the instructions in `start..end`.
+    fn emit_range(
+        &mut self,
+        start: usize,
+        end: usize,
+        state: &mut State,
+        depth: usize,
+    ) -> CodegenResult<()> {
+        let mut i = start;
+        while i < end {
+            match &self.program.instructions[i] {
+                Instruction::Lop(pol) => {
+                    // A do-while loop is its body followed by a while loop.
+                    self.emit_range(i + 1, *pol, state, depth)?;
+                    self.emit_loop(i + 1, *pol, state, depth)?;
+                    i = *pol + 1;
+                    continue;
+                }
+                Instruction::Whl(pol) => {
+                    self.emit_loop(i + 1, *pol, state, depth)?;
+                    i = *pol + 1;
+                    continue;
+                }
+                instruction => {
+                    let code = self.lower(i, instruction, state)?;
+                    self.line(depth, &code);
+                }
+            }
+
+            i += 1;
+        }
+
+        Ok(())
+    }
+
+    /// Emits the instructions in `start..end` as a `[...]` loop.
+    fn emit_loop(
+        &mut self,
+        start: usize,
+        end: usize,
+        state: &mut State,
+        depth: usize,
+    ) -> CodegenResult<()> {
+        self.line(depth, "[");
+        let mark = self.out.len();
+
+        // If the body leaves the pointer where it found it, every pass starts with the same state.
+        let entry = state.clone();
+        let mut body = entry.clone();
+        let balanced = self.emit_range(start, end, &mut body, depth + 1).is_ok() && body == entry;
+
+        if !balanced {
+            self.out.truncate(mark);
+
+            let unknown = State {
+                ptr: None,
+                bookmarks: entry.bookmarks.clone(),
+            };
+            let mut body = unknown.clone();
+            self.emit_range(start, end, &mut body, depth + 1)?;
+
+            if body.bookmarks.len() != unknown.bookmarks.len() {
+                return Err(self.error(
+                    end,
+                    CodegenErrorType::Bookmark,
+                    "Every 'mrk' inside a loop must be matched by a 'ret' in the same loop.",
+                ));
+            }
+
+            state.ptr = None;
+            state.bookmarks = entry
+                .bookmarks
+                .iter()
+                .zip(body.bookmarks.iter())
+                .map(|(a, b)| if a == b { *a } else { None })
+                .collect();
+        }
+
+        self.line(depth, "]");
+        Ok(())
+    }
+
+    /// Lowers a single instruction that is not a loop instruction.
+    fn lower(
+        &self,
+        i: usize,
+        instruction: &Instruction,
+        state: &mut State,
+    ) -> CodegenResult<String> {
+        let code = match instruction {
+            Instruction::Pfw(n) => {
+                state.ptr = state.ptr.and_then(|p| p.checked_add(*n));
+                ">".repeat(*n)
+            }
+            Instruction::Pbw(n) => {
+                state.ptr = state.ptr.and_then(|p| p.checked_sub(*n));
+                "<".repeat(*n)
+            }
+            Instruction::Ptr(target) => match state.ptr {
+                Some(p) => {
+                    state.ptr = Some(*target);
+                    moves(p, *target)
+                }
+                None => {
+                    return Err(self.error(
+                        i,
+                        CodegenErrorType::Pointer,
+                        "The pointer position is not known statically.",
+                    ))
+                }
+            },
+            Instruction::Mrk => {
+                state.bookmarks.push(state.ptr);
+                String::new()
+            }
+            Instruction::Ret => match (state.bookmarks.pop(), state.ptr) {
+                (Some(Some(target)), Some(p)) => {
+                    state.ptr = Some(target);
+                    moves(p, target)
+                }
+                (None, _) => {
+                    return Err(self.error(
+                        i,
+                        CodegenErrorType::Bookmark,
+                        "The bookmark stack is empty.",
+                    ))
+                }
+                _ => {
+                    return Err(self.error(
+                        i,
+                        CodegenErrorType::Pointer,
+                        "The pointer position is not known statically.",
+                    ))
+                }
+            },
+            Instruction::Inc(n) => "+".repeat(*n as usize),
+            Instruction::Dec(n) => "-".repeat(*n as usize),
+            Instruction::Rdi => ",".to_string(),
+            Instruction::Wrt => ".".to_string(),
+            Instruction::Set(v) => set_bytes(v.as_bytes(), state),
+            Instruction::Seti8(v) => set_bytes(&v.to_be_bytes(), state),
+            Instruction::Seti16(v) => set_bytes(&v.to_be_bytes(), state),
+            Instruction::Seti32(v) => set_bytes(&v.to_be_bytes(), state),
+            Instruction::Seti64(v) => set_bytes(&v.to_be_bytes(), state),
+            Instruction::Setu8(v) => set_bytes(&v.to_be_bytes(), state),
+            Instruction::Setu16(v) => set_bytes(&v.to_be_bytes(), state),
+            Instruction::Setu32(v) => set_bytes(&v.to_be_bytes(), state),
+            Instruction::Setu64(v) => set_bytes(&v.to_be_bytes(), state),
+            Instruction::Setf32(v) => set_bytes(&v.to_be_bytes(), state),
+            Instruction::Setf64(v) => set_bytes(&v.to_be_bytes(), state),
+            Instruction::Wra => {
+                state.ptr = None;
+                "[.>]".to_string()
+            }
+            Instruction::Rda => {
+                state.ptr = None;
+                // Each cell is preset to LF so that the end of the input stops the loop as well.
+                let lf = "+".repeat(10);
+                let back = "-".repeat(10);
+                format!("[-]{lf},{back}[{lf}>[-]{lf},{back}]")
+            }
+            Instruction::Clr => {
+                state.ptr = None;
+                "[-]>[[-]>]".to_string()
+            }
+            // Assertions are not checked in Brainfuck.
+            instruction if instruction.is_assertion() => String::new(),
+            other => {
+                return Err(self.error(
+                    i,
+                    CodegenErrorType::Unsupported,
+                    &format!("'{}' cannot be lowered to Brainfuck.", other.op_code()),
+                ))
+            }
+        };
+
+        Ok(code)
+    }
+
+    fn line(&mut self, depth: usize, code: &str) {
+        if code.is_empty() {
+            return;
+        }
+
+        for _ in 0..depth {
+            self.out.push_str("    ");
+        }
+        self.out.push_str(code);
+        self.out.push('\n');
+    }
+
+    fn error(&self, i: usize, _type: CodegenErrorType, what: &str) -> CodegenError {
+        error_at(self.program, i, _type, what)
+    }
+}
+
+/// Returns the moves from `from` to `to`.
+fn moves(from: usize, to: usize) -> String {
+    if to >= from {
+        ">".repeat(to - from)
+    } else {
+        "<".repeat(from - to)
+    }
+}
+
+/// Returns code that stores each byte in a separate cell and moves the pointer past them.
+fn set_bytes(bytes: &[u8], state: &mut State) -> String {
+    state.ptr = state.ptr.and_then(|p| p.checked_add(bytes.len()));
+
+    let mut code = String::new();
+    for b in bytes {
+        code.push_str("[-]");
+        code.push_str(&"+".repeat(*b as usize));
+        code.push('>');
+    }
+    code
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CodegenErrorType, Interpreter, Program, SharedBuffer};
+
+    /// Runs the TRNG code and its Brainfuck lowering and returns both outputs.
+    fn run_both(code: &str, input: &[u8]) -> (Vec<u8>, Vec<u8>) {
+        let program = Program::parse(code.as_bytes()).unwrap();
+        let bf = super::to_brainfuck(&program).unwrap();
+
+        let trng_out = SharedBuffer::new();
+        let mut interpreter = Interpreter::default();
+        interpreter.set_output(trng_out.clone());
+        interpreter.set_input(std::io::Cursor::new(input.to_vec()));
+        interpreter.run_program(program).unwrap();
+
+        let bf_out = SharedBuffer::new();
+        let mut interpreter = Interpreter::default();
+        interpreter.set_output(bf_out.clone());
+        interpreter.set_input(std::io::Cursor::new(input.to_vec()));
+        interpreter.run_brainfuck(bf.as_bytes()).unwrap();
+
+        (trng_out.contents(), bf_out.contents())
+    }
+
+    #[test]
+    fn example_roundtrip_test() {
+        let code = std::fs::read_to_string("../examples/example.trng").unwrap();
+
+        let (trng_out, bf_out) = run_both(&code, b"");
+
+        assert_eq!(trng_out, b"Hello World\r\n");
+        assert_eq!(bf_out, trng_out);
+    }
+
+    #[test]
+    fn loops_roundtrip_test() {
+        let code = "
+            inc 3
+            lop
+                pfw 1
+                inc 2
+                lop
+                    inc 1
+                    wrt
+                    dec 3
+                pol
+                pbw 1
+                dec 1
+            pol
+            whl
+                wrt
+            pol";
+
+        let (trng_out, bf_out) = run_both(code, b"");
+
+        assert!(!trng_out.is_empty());
+        assert_eq!(bf_out, trng_out);
+    }
+
+    #[test]
+    fn pointer_roundtrip_test() {
+        let code = "
+            rdi
+            inc 1
+            pfw 5
+            mrk
+            set Hi
+            seti16 16706
+            ptr 0
+            wrt
+            lop
+                dec 1
+                pfw 1
+                inc 1
+                pbw 1
+            pol
+            pfw 1
+            wrt
+            ret
+            wra";
+
+        let (trng_out, bf_out) = run_both(code, b"@");
+
+        assert_eq!(trng_out, b"AAHiAB");
+        assert_eq!(bf_out, trng_out);
+    }
+
+    #[test]
+    fn rda_roundtrip_test() {
+        let code = "pfw 1 rda pbw 2 wra";
+
+        for input in [&b"Hi\n!"[..], &b"Hi"[..]] {
+            let (trng_out, bf_out) = run_both(code, input);
+
+            assert_eq!(trng_out, b"Hi");
+            assert_eq!(bf_out, trng_out);
+        }
+    }
+
+    #[test]
+    fn typed_output_is_unsupported_test() {
+        let program = Program::parse("inc 1 wrti8".as_bytes()).unwrap();
+
+        let err = super::to_brainfuck(&program).unwrap_err();
+
+        assert_eq!(err._type, CodegenErrorType::Unsupported);
+        assert_eq!(err.line, 1);
+    }
+
+    #[test]
+    fn ptr_after_wra_is_err_test() {
+        let program = Program::parse("set Hi pbw 2 wra ptr 0".as_bytes()).unwrap();
+
+        let err = super::to_brainfuck(&program).unwrap_err();
+
+        assert_eq!(err._type, CodegenErrorType::Pointer);
+    }
+}
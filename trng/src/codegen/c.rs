@@ -0,0 +1,764 @@
+// TRNG - Brainfucks pretty sister.
+// Copyright (C) 2023 Lukas Pfeifer
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::program::{Instruction, Position, Program};
+
+/// Defines options for the generated C code.
+pub struct COptions {
+    /// The number of cells on the tape.
+    pub tape_size: usize,
+    /// The maximum number of values on the value stack.
+    pub max_stack_depth: usize,
+    /// Whether the generated code checks bounds and overflows by default.
+    pub checked: bool,
+}
+
+impl Default for COptions {
+    fn default() -> Self {
+        Self {
+            tape_size: 30000,
+            max_stack_depth: 1024,
+            checked: true,
+        }
+    }
+}
+
+/// The runtime every generated program starts with.
+const RUNTIME: &str = r#"#include <inttypes.h>
+#include <math.h>
+#include <stdarg.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#define TAPE_ERROR(kind) "Interpreter Error [Tape error]: Tape Error [" kind "]: "
+#define STACK_ERROR(kind) "Interpreter Error [Stack error]: Stack Error [" kind "]: "
+#define ASSERTION_ERROR(kind) "Interpreter Error [Assertion error]: Assertion Error [" kind "]: "
+
+static uint8_t tape[TAPE_SIZE];
+static size_t p;
+
+static size_t *bookmarks;
+static size_t bookmarks_len;
+static size_t bookmarks_cap;
+
+typedef struct {
+    size_t len;
+    uint8_t bytes[8];
+} trng_value;
+
+static trng_value stack[MAX_STACK_DEPTH];
+static size_t stack_len;
+
+static void trng_fail(const char *fmt, ...) {
+    va_list args;
+
+    fflush(stdout);
+    va_start(args, fmt);
+    vfprintf(stderr, fmt, args);
+    va_end(args);
+    fputc('\n', stderr);
+    exit(1);
+}
+
+static inline void trng_check_range(size_t n, const char *what) {
+    if (TRNG_CHECKED && (p > TAPE_SIZE || n > TAPE_SIZE - p)) {
+        trng_fail(TAPE_ERROR("Index error") "%s %zu cell(s) at pointer index %zu would result in overshooting the tape.", what, n, p);
+    }
+}
+
+static inline uint8_t trng_cell(void) {
+    if (TRNG_CHECKED && p >= TAPE_SIZE) {
+        trng_fail(TAPE_ERROR("Index error") "Getting the current value at pointer index %zu is invalid.", p);
+    }
+    return tape[p];
+}
+
+static inline void trng_store(uint8_t b) {
+    trng_check_range(1, "Writing");
+    tape[p] = b;
+}
+
+static inline void trng_pfw(size_t n) {
+    if (TRNG_CHECKED && p + n >= TAPE_SIZE) {
+        trng_fail(TAPE_ERROR("Index error") "Moving the pointer %zu step(s) forward would result in overshooting the tape.", n);
+    }
+    p += n;
+}
+
+static inline void trng_pbw(size_t n) {
+    if (TRNG_CHECKED && n > p) {
+        trng_fail(TAPE_ERROR("Index error") "Moving the pointer %zu step(s) backward would result in overshooting the tape.", n);
+    }
+    p -= n;
+}
+
+static inline void trng_ptr(size_t index) {
+    if (TRNG_CHECKED && index >= TAPE_SIZE) {
+        trng_fail(TAPE_ERROR("Index error") "Moving the pointer to index %zu would result in overshooting the tape.", index);
+    }
+    p = index;
+}
+
+static inline void trng_mrk(void) {
+    if (bookmarks_len == bookmarks_cap) {
+        bookmarks_cap = bookmarks_cap ? bookmarks_cap * 2 : 16;
+        bookmarks = realloc(bookmarks, bookmarks_cap * sizeof(size_t));
+        if (bookmarks == NULL) {
+            trng_fail("Out of memory.");
+        }
+    }
+    bookmarks[bookmarks_len++] = p;
+}
+
+static inline void trng_ret(void) {
+    if (bookmarks_len == 0) {
+        trng_fail(TAPE_ERROR("Index error") "Returning to a bookmark is invalid. The bookmark stack is empty.");
+    }
+    trng_ptr(bookmarks[--bookmarks_len]);
+}
+
+static inline void trng_inc(uint8_t by) {
+    uint8_t v = trng_cell();
+    if (TRNG_CHECKED && !TRNG_WRAPPING && v > UINT8_MAX - by) {
+        trng_fail(TAPE_ERROR("Overflow error") "Adding %u to the current cell value would result in an overflow.", by);
+    }
+    tape[p] = v + by;
+}
+
+static inline void trng_dec(uint8_t by) {
+    uint8_t v = trng_cell();
+    if (TRNG_CHECKED && !TRNG_WRAPPING && v < by) {
+        trng_fail(TAPE_ERROR("Overflow error") "Subtracting %u from the current cell value would result in an overflow.", by);
+    }
+    tape[p] = v - by;
+}
+
+static inline void trng_wrt(void) {
+    putchar(trng_cell());
+}
+
+static inline uint64_t trng_load(size_t n) {
+    uint64_t v = 0;
+    size_t i;
+
+    trng_check_range(n, "Reading");
+    for (i = 0; i < n; i++) {
+        v = (v << 8) | tape[p + i];
+    }
+    return v;
+}
+
+static inline void trng_write_float(FILE *out, double v, int is_f32) {
+    char buf[64];
+    char digits[32];
+    const char *s = buf;
+    int prec, exp, n = 0, i;
+
+    if (isnan(v)) {
+        fputs("NaN", out);
+        return;
+    }
+    if (isinf(v)) {
+        fputs(v < 0 ? "-inf" : "inf", out);
+        return;
+    }
+
+    /* Find the shortest representation that reads back as the same value. */
+    for (prec = 0; prec < 17; prec++) {
+        snprintf(buf, sizeof(buf), "%.*e", prec, v);
+        if (is_f32 ? strtof(buf, NULL) == (float)v : strtod(buf, NULL) == v) {
+            break;
+        }
+    }
+
+    if (*s == '-') {
+        fputc('-', out);
+        s++;
+    }
+    for (; *s != 'e'; s++) {
+        if (*s != '.') {
+            digits[n++] = *s;
+        }
+    }
+    exp = atoi(s + 1);
+    while (n > 1 && digits[n - 1] == '0') {
+        n--;
+    }
+
+    if (exp < 0) {
+        fputs("0.", out);
+        for (i = 0; i < -exp - 1; i++) {
+            fputc('0', out);
+        }
+        fwrite(digits, 1, n, out);
+    } else {
+        for (i = 0; i <= exp; i++) {
+            fputc(i < n ? digits[i] : '0', out);
+        }
+        if (n > exp + 1) {
+            fputc('.', out);
+            fwrite(digits + exp + 1, 1, n - exp - 1, out);
+        }
+    }
+}
+
+static inline void trng_wrti8(void) { printf("%" PRId8, (int8_t)trng_load(1)); }
+static inline void trng_wrti16(void) { printf("%" PRId16, (int16_t)trng_load(2)); }
+static inline void trng_wrti32(void) { printf("%" PRId32, (int32_t)trng_load(4)); }
+static inline void trng_wrti64(void) { printf("%" PRId64, (int64_t)trng_load(8)); }
+static inline void trng_wrtu8(void) { printf("%" PRIu8, (uint8_t)trng_load(1)); }
+static inline void trng_wrtu16(void) { printf("%" PRIu16, (uint16_t)trng_load(2)); }
+static inline void trng_wrtu32(void) { printf("%" PRIu32, (uint32_t)trng_load(4)); }
+static inline void trng_wrtu64(void) { printf("%" PRIu64, trng_load(8)); }
+
+static inline void trng_wrtf32(void) {
+    uint32_t bits = (uint32_t)trng_load(4);
+    float v;
+
+    memcpy(&v, &bits, sizeof(v));
+    trng_write_float(stdout, v, 1);
+}
+
+static inline void trng_wrtf64(void) {
+    uint64_t bits = trng_load(8);
+    double v;
+
+    memcpy(&v, &bits, sizeof(v));
+    trng_write_float(stdout, v, 0);
+}
+
+static inline void trng_rdi(void) {
+    int c = getchar();
+    if (c != EOF) {
+        trng_store((uint8_t)c);
+    }
+}
+
+static inline void trng_set(const uint8_t *bytes, size_t n) {
+    size_t i;
+
+    for (i = 0; i < n; i++) {
+        trng_store(bytes[i]);
+        p++;
+    }
+}
+
+static inline void trng_wra(void) {
+    uint8_t b;
+
+    while ((b = trng_cell()) != 0) {
+        putchar(b);
+        p++;
+    }
+}
+
+static inline void trng_rda(void) {
+    int c;
+
+    while ((c = getchar()) != EOF && c != '\n') {
+        trng_store((uint8_t)c);
+        p++;
+    }
+}
+
+static inline void trng_clr(void) {
+    trng_store(0);
+    for (;;) {
+        p++;
+        if (trng_cell() == 0) {
+            break;
+        }
+        tape[p] = 0;
+    }
+}
+
+static inline void trng_psh(size_t n) {
+    trng_check_range(n, "Reading");
+    if (TRNG_CHECKED && stack_len >= MAX_STACK_DEPTH) {
+        trng_fail(STACK_ERROR("Overflow error") "Pushing a value would exceed the maximum stack depth of %d.", MAX_STACK_DEPTH);
+    }
+    stack[stack_len].len = n;
+    memcpy(stack[stack_len].bytes, &tape[p], n);
+    stack_len++;
+}
+
+static inline void trng_pek(void) {
+    if (TRNG_CHECKED && stack_len == 0) {
+        trng_fail(STACK_ERROR("Underflow error") "Peeking a value is invalid. The stack is empty.");
+    }
+    trng_check_range(stack[stack_len - 1].len, "Writing");
+    memcpy(&tape[p], stack[stack_len - 1].bytes, stack[stack_len - 1].len);
+}
+
+static inline void trng_pop(void) {
+    if (TRNG_CHECKED && stack_len == 0) {
+        trng_fail(STACK_ERROR("Underflow error") "Popping a value is invalid. The stack is empty.");
+    }
+    stack_len--;
+    trng_check_range(stack[stack_len].len, "Writing");
+    memcpy(&tape[p], stack[stack_len].bytes, stack[stack_len].len);
+}
+
+static inline void trng_dup(void) {
+    if (TRNG_CHECKED && stack_len == 0) {
+        trng_fail(STACK_ERROR("Underflow error") "Peeking a value is invalid. The stack is empty.");
+    }
+    if (TRNG_CHECKED && stack_len >= MAX_STACK_DEPTH) {
+        trng_fail(STACK_ERROR("Overflow error") "Pushing a value would exceed the maximum stack depth of %d.", MAX_STACK_DEPTH);
+    }
+    stack[stack_len] = stack[stack_len - 1];
+    stack_len++;
+}
+
+static inline void trng_swp(void) {
+    trng_value tmp;
+
+    if (TRNG_CHECKED && stack_len < 2) {
+        trng_fail(STACK_ERROR("Underflow error") "Swapping needs two values on the stack. Found %zu instead.", stack_len);
+    }
+    tmp = stack[stack_len - 1];
+    stack[stack_len - 1] = stack[stack_len - 2];
+    stack[stack_len - 2] = tmp;
+}
+
+static inline void trng_asti(size_t n, int64_t expected, unsigned line, unsigned column) {
+    uint64_t bits;
+    int64_t actual;
+
+    if (!TRNG_CHECKED) {
+        return;
+    }
+    bits = trng_load(n);
+    switch (n) {
+    case 1: actual = (int8_t)bits; break;
+    case 2: actual = (int16_t)bits; break;
+    case 4: actual = (int32_t)bits; break;
+    default: actual = (int64_t)bits; break;
+    }
+    if (actual != expected) {
+        trng_fail(ASSERTION_ERROR("Value error") "Expected %" PRId64 " but found %" PRId64 ". - ln: %u, col: %u", expected, actual, line, column);
+    }
+}
+
+static inline void trng_astu(size_t n, uint64_t expected, unsigned line, unsigned column) {
+    uint64_t actual;
+
+    if (!TRNG_CHECKED) {
+        return;
+    }
+    actual = trng_load(n);
+    if (actual != expected) {
+        trng_fail(ASSERTION_ERROR("Value error") "Expected %" PRIu64 " but found %" PRIu64 ". - ln: %u, col: %u", expected, actual, line, column);
+    }
+}
+
+static void trng_float_failed(const char *expected, double actual, int is_f32, unsigned line, unsigned column) {
+    fflush(stdout);
+    fprintf(stderr, ASSERTION_ERROR("Value error") "Expected %s but found ", expected);
+    trng_write_float(stderr, actual, is_f32);
+    fprintf(stderr, ". - ln: %u, col: %u\n", line, column);
+    exit(1);
+}
+
+/* The expected value and the epsilon are passed as bits to keep them exact. */
+static inline void trng_astf32(uint32_t expected_bits, uint32_t epsilon_bits, const char *expected_text, unsigned line, unsigned column) {
+    uint32_t bits;
+    float actual, expected, epsilon;
+
+    if (!TRNG_CHECKED) {
+        return;
+    }
+    bits = (uint32_t)trng_load(4);
+    memcpy(&actual, &bits, sizeof(actual));
+    memcpy(&expected, &expected_bits, sizeof(expected));
+    memcpy(&epsilon, &epsilon_bits, sizeof(epsilon));
+    if (!(actual == expected || fabsf(actual - expected) <= epsilon)) {
+        trng_float_failed(expected_text, actual, 1, line, column);
+    }
+}
+
+static inline void trng_astf64(uint64_t expected_bits, uint64_t epsilon_bits, const char *expected_text, unsigned line, unsigned column) {
+    uint64_t bits;
+    double actual, expected, epsilon;
+
+    if (!TRNG_CHECKED) {
+        return;
+    }
+    bits = trng_load(8);
+    memcpy(&actual, &bits, sizeof(actual));
+    memcpy(&expected, &expected_bits, sizeof(expected));
+    memcpy(&epsilon, &epsilon_bits, sizeof(epsilon));
+    if (!(actual == expected || fabs(actual - expected) <= epsilon)) {
+        trng_float_failed(expected_text, actual, 0, line, column);
+    }
+}
+
+static inline void trng_asp(size_t index, unsigned line, unsigned column) {
+    if (TRNG_CHECKED && p != index) {
+        trng_fail(ASSERTION_ERROR("Pointer error") "Expected pointer index %zu but found pointer index %zu. - ln: %u, col: %u", index, p, line, column);
+    }
+}
+"#;
+
+/// Compiles the given program ahead of time to a self-contained C translation unit.
+///
+/// Every instruction becomes a call to a small `static` helper, loops become `do`/`while` loops.
+/// The helpers report the same errors as the interpreter, including failed assertions, and exit
+/// with status 1. Defining `TRNG_CHECKED` as 0 when compiling the C code removes all bounds and
+/// overflow checks and all assertions.
+/// # Arguments
+/// * `program` - The program to compile.
+/// * `options` - The options for the generated code.
+pub fn to_c(program: &Program, options: &COptions) -> String {
+    let mut out = String::new();
+
+    out.push_str("/* Generated by trng. */\n");
+    out.push_str("#ifndef TRNG_CHECKED\n");
+    out.push_str(&format!("#define TRNG_CHECKED {}\n", options.checked as u8));
+    out.push_str("#endif\n");
+    out.push_str(&format!(
+        "#define TRNG_WRAPPING {}\n",
+        program.wrapping as u8
+    ));
+    out.push_str(&format!("#define TAPE_SIZE {}\n", options.tape_size));
+    out.push_str(&format!(
+        "#define MAX_STACK_DEPTH {}\n\n",
+        options.max_stack_depth
+    ));
+    out.push_str(RUNTIME);
+    out.push_str("\nint main(void) {\n");
+
+    let mut depth = 1;
+    for (instruction, position) in program.instructions.iter().zip(&program.positions) {
+        if let Instruction::Pol(start) = instruction {
+            depth -= 1;
+            match program.instructions[*start] {
+                Instruction::Lop(_) => line(&mut out, depth, "} while (trng_cell());"),
+                _ => line(&mut out, depth, "}"),
+            }
+            continue;
+        }
+
+        line(&mut out, depth, &statement(instruction, *position));
+
+        if let Instruction::Lop(_) | Instruction::Whl(_) = instruction {
+            depth += 1;
+        }
+    }
+
+    line(&mut out, 1, "fflush(stdout);");
+    line(&mut out, 1, "return 0;");
+    out.push_str("}\n");
+
+    out
+}
+
+/// Returns the C statement for a single instruction other than `pol`.
+fn statement(instruction: &Instruction, position: Position) -> String {
+    let at = format!("{}, {}", position.line, position.column);
+
+    match instruction {
+        Instruction::Pfw(n) => format!("trng_pfw({});", n),
+        Instruction::Pbw(n) => format!("trng_pbw({});", n),
+        Instruction::Ptr(n) => format!("trng_ptr({});", n),
+        Instruction::Mrk => "trng_mrk();".to_string(),
+        Instruction::Ret => "trng_ret();".to_string(),
+        Instruction::Inc(n) => format!("trng_inc({});", n),
+        Instruction::Dec(n) => format!("trng_dec({});", n),
+        Instruction::Lop(_) => "do {".to_string(),
+        Instruction::Whl(_) => "while (trng_cell()) {".to_string(),
+        Instruction::Pol(_) => "}".to_string(),
+        Instruction::Wrt => "trng_wrt();".to_string(),
+        Instruction::Wrti8 => "trng_wrti8();".to_string(),
+        Instruction::Wrti16 => "trng_wrti16();".to_string(),
+        Instruction::Wrti32 => "trng_wrti32();".to_string(),
+        Instruction::Wrti64 => "trng_wrti64();".to_string(),
+        Instruction::Wrtu8 => "trng_wrtu8();".to_string(),
+        Instruction::Wrtu16 => "trng_wrtu16();".to_string(),
+        Instruction::Wrtu32 => "trng_wrtu32();".to_string(),
+        Instruction::Wrtu64 => "trng_wrtu64();".to_string(),
+        Instruction::Wrtf32 => "trng_wrtf32();".to_string(),
+        Instruction::Wrtf64 => "trng_wrtf64();".to_string(),
+        Instruction::Rdi => "trng_rdi();".to_string(),
+        Instruction::Set(v) => set(v.as_bytes()),
+        Instruction::Seti8(v) => set(&v.to_be_bytes()),
+        Instruction::Seti16(v) => set(&v.to_be_bytes()),
+        Instruction::Seti32(v) => set(&v.to_be_bytes()),
+        Instruction::Seti64(v) => set(&v.to_be_bytes()),
+        Instruction::Setu8(v) => set(&v.to_be_bytes()),
+        Instruction::Setu16(v) => set(&v.to_be_bytes()),
+        Instruction::Setu32(v) => set(&v.to_be_bytes()),
+        Instruction::Setu64(v) => set(&v.to_be_bytes()),
+        Instruction::Setf32(v) => set(&v.to_be_bytes()),
+        Instruction::Setf64(v) => set(&v.to_be_bytes()),
+        Instruction::Wra => "trng_wra();".to_string(),
+        Instruction::Rda => "trng_rda();".to_string(),
+        Instruction::Clr => "trng_clr();".to_string(),
+        Instruction::Psh => "trng_psh(1);".to_string(),
+        Instruction::Psh16 => "trng_psh(2);".to_string(),
+        Instruction::Psh32 => "trng_psh(4);".to_string(),
+        Instruction::Psh64 => "trng_psh(8);".to_string(),
+        Instruction::Pop => "trng_pop();".to_string(),
+        Instruction::Pek => "trng_pek();".to_string(),
+        Instruction::Dup => "trng_dup();".to_string(),
+        Instruction::Swp => "trng_swp();".to_string(),
+        Instruction::Ast(v) | Instruction::Astu8(v) => format!("trng_astu(1, {}u, {});", v, at),
+        Instruction::Astu16(v) => format!("trng_astu(2, {}u, {});", v, at),
+        Instruction::Astu32(v) => format!("trng_astu(4, {}u, {});", v, at),
+        Instruction::Astu64(v) => format!("trng_astu(8, UINT64_C({}), {});", v, at),
+        Instruction::Asti8(v) => format!("trng_asti(1, {}, {});", v, at),
+        Instruction::Asti16(v) => format!("trng_asti(2, {}, {});", v, at),
+        Instruction::Asti32(v) => format!("trng_asti(4, INT64_C({}), {});", v, at),
+        Instruction::Asti64(v) if *v == i64::MIN => format!("trng_asti(8, INT64_MIN, {});", at),
+        Instruction::Asti64(v) => format!("trng_asti(8, INT64_C({}), {});", v, at),
+        Instruction::Astf32(v, eps) => format!(
+            "trng_astf32({:#x}u, {:#x}u, \"{} +/- {}\", {});",
+            v.to_bits(),
+            eps.to_bits(),
+            v,
+            eps,
+            at
+        ),
+        Instruction::Astf64(v, eps) => format!(
+            "trng_astf64(UINT64_C({:#x}), UINT64_C({:#x}), \"{} +/- {}\", {});",
+            v.to_bits(),
+            eps.to_bits(),
+            v,
+            eps,
+            at
+        ),
+        Instruction::Asp(v) => format!("trng_asp({}, {});", v, at),
+    }
+}
+
+/// Returns a call of `trng_set` with the given bytes.
+fn set(bytes: &[u8]) -> String {
+    let list: Vec<String> = bytes.iter().map(|b| b.to_string()).collect();
+    format!(
+        "trng_set((const uint8_t[]){{{}}}, {});",
+        list.join(", "),
+        bytes.len()
+    )
+}
+
+fn line(out: &mut String, depth: usize, code: &str) {
+    for _ in 0..depth {
+        out.push_str("    ");
+    }
+    out.push_str(code);
+    out.push('\n');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::COptions;
+    use crate::{Interpreter, Program, SharedBuffer};
+    use std::path::PathBuf;
+    use std::process::{Command, Output, Stdio};
+
+    /// Compiles the C code with `cc` and runs the binary.
+    /// Returns `None` if no C compiler is available.
+    fn compile_and_run(name: &str, code: &str, flags: &[&str]) -> Option<Output> {
+        let dir = std::env::temp_dir().join(format!("trng-c-{}-{}", std::process::id(), name));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let source = dir.join("main.c");
+        let binary: PathBuf = dir.join("main");
+        std::fs::write(&source, code).unwrap();
+
+        let status = Command::new("cc")
+            .args(flags)
+            .args(["-O1", "-Wall", "-Werror"])
+            .arg("-o")
+            .arg(&binary)
+            .arg(&source)
+            .arg("-lm")
+            .status();
+
+        let output = match status {
+            Ok(status) => {
+                assert!(status.success(), "cc failed to compile the generated code");
+                Command::new(&binary).stdin(Stdio::null()).output().unwrap()
+            }
+            Err(e) => {
+                eprintln!("skipping {}: cc cannot be run ({})", name, e);
+                std::fs::remove_dir_all(&dir).ok();
+                return None;
+            }
+        };
+
+        std::fs::remove_dir_all(&dir).ok();
+        Some(output)
+    }
+
+    /// Runs the program in the interpreter and returns its output and the error message if any.
+    fn interpret(program: Program) -> (Vec<u8>, Option<String>) {
+        let out = SharedBuffer::new();
+        let mut interpreter = Interpreter::default();
+        interpreter.set_output(out.clone());
+        interpreter.set_input(std::io::empty());
+
+        let err = interpreter.run_program(program).err();
+
+        (out.contents(), err.map(|e| e.to_string()))
+    }
+
+    fn assert_same_as_interpreter(name: &str, code: &str) {
+        let program = Program::parse(code.as_bytes()).unwrap();
+        let c = super::to_c(&program, &COptions::default());
+
+        let Some(output) = compile_and_run(name, &c, &[]) else {
+            return;
+        };
+        let (expected, err) = interpret(program);
+
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&expected)
+        );
+        match err {
+            Some(message) => {
+                assert_eq!(output.status.code(), Some(1));
+                assert_eq!(String::from_utf8_lossy(&output.stderr).trim_end(), message);
+            }
+            None => assert!(output.status.success()),
+        }
+    }
+
+    #[test]
+    fn examples_match_interpreter_test() {
+        for entry in std::fs::read_dir("../examples").unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().and_then(|e| e.to_str()) != Some("trng") {
+                continue;
+            }
+
+            let name = path.file_stem().unwrap().to_string_lossy().to_string();
+            let code = std::fs::read_to_string(&path).unwrap();
+
+            assert_same_as_interpreter(&name, &code);
+        }
+    }
+
+    #[test]
+    fn numbers_match_interpreter_test() {
+        let code = "
+            seti64 -9223372036854775807 pbw 8 wrti64
+            setu32 4294967295 pbw 4 wrtu32
+            seti16 -300 pbw 2 wrti16
+            setf64 0.000001234 pbw 8 wrtf64
+            setf64 1e21 pbw 8 wrtf64
+            setf32 -2.5 pbw 4 wrtf32
+            setf32 0.1 pbw 4 wrtf32";
+
+        assert_same_as_interpreter("numbers", code);
+    }
+
+    #[test]
+    fn stack_and_bookmarks_match_interpreter_test() {
+        let code = "
+            set AB pbw 2 psh16 pfw 5 mrk pop wra
+            ret psh dup pfw 10 pop pfw 1 pop pbw 1 wra
+            ptr 0 psh pfw 1 psh swp pop pbw 1 pop wra
+            inc 3 whl dec 1 wrtu8 pol";
+
+        assert_same_as_interpreter("stack", code);
+    }
+
+    #[test]
+    fn assertions_match_interpreter_test() {
+        let passing = "
+            inc 3 ast 3 astu8 3 asp 0
+            seti16 -300 pbw 2 asti16 -300 astu16 65236
+            seti64 -9223372036854775808 pbw 8 asti64 -9223372036854775808
+            setu64 18446744073709551615 pbw 8 astu64 18446744073709551615
+            setf32 1.5 pbw 4 astf32 1.5 0 astf32 1.25 0.25
+            setf64 0.1 pbw 8 astf64 0.1 0 asp 0";
+        assert_same_as_interpreter("passing", passing);
+
+        let failing = [
+            "inc 3 wrt ast 4",
+            "seti32 -7 pbw 4 asti32 7",
+            "setu32 7 pbw 4 astu32 8",
+            "setf32 -2.5 pbw 4 astf32 0.1 0.5",
+            "setf64 1e21 pbw 8 astf64 1 0.001",
+            "pfw 2 asp 1",
+            "ptr 29999 asti16 1",
+        ];
+        for (i, code) in failing.iter().enumerate() {
+            assert_same_as_interpreter(&format!("failing-{}", i), code);
+        }
+    }
+
+    #[test]
+    fn unchecked_drops_assertions_test() {
+        let program = Program::parse("inc 65 ast 1 asp 3 wrt".as_bytes()).unwrap();
+        let options = COptions {
+            checked: false,
+            ..COptions::default()
+        };
+        let c = super::to_c(&program, &options);
+
+        let Some(output) = compile_and_run("unchecked-assertions", &c, &[]) else {
+            return;
+        };
+
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"A");
+    }
+
+    #[test]
+    fn overflow_error_matches_interpreter_test() {
+        assert_same_as_interpreter("overflow", "inc 65 wrt inc 200");
+    }
+
+    #[test]
+    fn brainfuck_wraps_around_test() {
+        let program = Program::parse_brainfuck("-.>+[+]".as_bytes()).unwrap();
+        let c = super::to_c(&program, &COptions::default());
+
+        let Some(output) = compile_and_run("wrapping", &c, &[]) else {
+            return;
+        };
+
+        assert!(output.status.success());
+        assert_eq!(output.stdout, vec![255]);
+    }
+
+    #[test]
+    fn index_error_matches_interpreter_test() {
+        assert_same_as_interpreter("index", "inc 66 wrt pbw 1");
+    }
+
+    #[test]
+    fn unchecked_drops_checks_test() {
+        let program = Program::parse("dec 1 inc 66 wrt".as_bytes()).unwrap();
+        let options = COptions {
+            checked: false,
+            ..COptions::default()
+        };
+        let c = super::to_c(&program, &options);
+
+        let Some(output) = compile_and_run("unchecked", &c, &[]) else {
+            return;
+        };
+
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"A");
+    }
+}
@@ -0,0 +1,73 @@
+// TRNG - Brainfucks pretty sister.
+// Copyright (C) 2023 Lukas Pfeifer
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+/// Defines error types for code generation errors.
+#[derive(Debug, PartialEq, Eq)]
+pub enum CodegenErrorType {
+    Unsupported,
+    Pointer,
+    Bookmark,
+}
+
+impl std::fmt::Display for CodegenErrorType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CodegenErrorType::Unsupported => {
+                write!(f, "Unsupported instruction error")
+            }
+            CodegenErrorType::Pointer => {
+                write!(f, "Pointer error")
+            }
+            CodegenErrorType::Bookmark => {
+                write!(f, "Bookmark error")
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct CodegenError {
+    pub description: String,
+    pub _type: CodegenErrorType,
+    /// Line number of the offending instruction.
+    pub line: u32,
+    /// Column number of the offending instruction.
+    pub column: u32,
+}
+
+impl std::fmt::Display for CodegenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Codegen Error [{}]: {}", self._type, self.description)
+    }
+}
+
+impl std::error::Error for CodegenError {}
+
+impl CodegenError {
+    pub fn new(
+        _type: CodegenErrorType,
+        description: String,
+        line: u32,
+        column: u32,
+    ) -> CodegenError {
+        Self {
+            _type,
+            description,
+            line,
+            column,
+        }
+    }
+}
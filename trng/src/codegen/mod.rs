@@ -0,0 +1,49 @@
+// TRNG - Brainfucks pretty sister.
+// Copyright (C) 2023 Lukas Pfeifer
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+mod brainfuck;
+mod c;
+mod error;
+mod wat;
+
+pub use brainfuck::to_brainfuck;
+pub use c::{to_c, COptions};
+pub use error::{CodegenError, CodegenErrorType};
+pub use wat::{to_wat, WatOptions};
+
+use crate::program::Program;
+
+/// Type alias for a simple result with a CodegenError.
+pub type CodegenResult<T> = Result<T, CodegenError>;
+
+/// Returns an error for the instruction at the given index, including its source position.
+/// # Arguments
+/// * `program` - The program being compiled.
+/// * `i` - The index of the offending instruction.
+/// * `_type` - The type of the error.
+/// * `what` - The description of the problem.
+fn error_at(program: &Program, i: usize, _type: CodegenErrorType, what: &str) -> CodegenError {
+    let pos = program.positions.get(i).copied().unwrap_or_default();
+    CodegenError::new(
+        _type,
+        format!(
+            "{} At instruction index {}. - ln: {}, col: {}",
+            what, i, pos.line, pos.column
+        ),
+        pos.line,
+        pos.column,
+    )
+}
@@ -0,0 +1,822 @@
+// TRNG - Brainfucks pretty sister.
+// Copyright (C) 2023 Lukas Pfeifer
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::{error_at, CodegenErrorType, CodegenResult};
+use crate::program::{Instruction, Program};
+
+/// Defines options for the generated module.
+pub struct WatOptions {
+    /// The number of cells on the tape.
+    pub tape_size: usize,
+    /// The maximum number of values on the value stack.
+    pub max_stack_depth: usize,
+    /// The maximum number of bookmarks.
+    pub max_bookmarks: usize,
+}
+
+impl Default for WatOptions {
+    fn default() -> Self {
+        Self {
+            tape_size: 30000,
+            max_stack_depth: 1024,
+            max_bookmarks: 1024,
+        }
+    }
+}
+
+/// The helper functions every module contains.
+/// `TAPE_SIZE`, `SCRATCH`, `BOOKMARKS`, `MAX_BOOKMARKS`, `STACK` and `MAX_STACK` are replaced by their values.
+const RUNTIME: &str = r#"  (func $fail (param $code i32)
+    local.get $code
+    global.set $error
+    unreachable)
+
+  (func $check_range (param $n i32)
+    global.get $p
+    local.get $n
+    i32.add
+    i32.const TAPE_SIZE
+    i32.gt_u
+    if
+      i32.const 1
+      call $fail
+    end)
+
+  (func $cell (result i32)
+    global.get $p
+    i32.const TAPE_SIZE
+    i32.ge_u
+    if
+      i32.const 1
+      call $fail
+    end
+    global.get $p
+    i32.load8_u)
+
+  (func $store (param $b i32)
+    global.get $p
+    i32.const TAPE_SIZE
+    i32.ge_u
+    if
+      i32.const 1
+      call $fail
+    end
+    global.get $p
+    local.get $b
+    i32.store8)
+
+  (func $step
+    global.get $p
+    i32.const 1
+    i32.add
+    global.set $p)
+
+  (func $set_byte (param $b i32)
+    local.get $b
+    call $store
+    call $step)
+
+  (func $pfw (param $n i64)
+    global.get $p
+    i64.extend_i32_u
+    local.get $n
+    i64.add
+    i64.const TAPE_SIZE
+    i64.ge_u
+    if
+      i32.const 1
+      call $fail
+    end
+    global.get $p
+    local.get $n
+    i32.wrap_i64
+    i32.add
+    global.set $p)
+
+  (func $pbw (param $n i64)
+    local.get $n
+    global.get $p
+    i64.extend_i32_u
+    i64.gt_u
+    if
+      i32.const 1
+      call $fail
+    end
+    global.get $p
+    local.get $n
+    i32.wrap_i64
+    i32.sub
+    global.set $p)
+
+  (func $ptr (param $i i64)
+    local.get $i
+    i64.const TAPE_SIZE
+    i64.ge_u
+    if
+      i32.const 1
+      call $fail
+    end
+    local.get $i
+    i32.wrap_i64
+    global.set $p)
+
+  (func $mrk
+    global.get $bookmarks
+    i32.const MAX_BOOKMARKS
+    i32.ge_u
+    if
+      i32.const 1
+      call $fail
+    end
+    global.get $bookmarks
+    i32.const 4
+    i32.mul
+    i32.const BOOKMARKS
+    i32.add
+    global.get $p
+    i32.store
+    global.get $bookmarks
+    i32.const 1
+    i32.add
+    global.set $bookmarks)
+
+  (func $ret
+    global.get $bookmarks
+    i32.eqz
+    if
+      i32.const 1
+      call $fail
+    end
+    global.get $bookmarks
+    i32.const 1
+    i32.sub
+    global.set $bookmarks
+    global.get $bookmarks
+    i32.const 4
+    i32.mul
+    i32.const BOOKMARKS
+    i32.add
+    i32.load
+    i64.extend_i32_u
+    call $ptr)
+
+  (func $inc (param $by i32) (local $v i32)
+    call $cell
+    local.get $by
+    i32.add
+    local.tee $v
+    i32.const 255
+    i32.gt_u
+    global.get $wrapping
+    i32.eqz
+    i32.and
+    if
+      i32.const 2
+      call $fail
+    end
+    local.get $v
+    call $store)
+
+  (func $dec (param $by i32) (local $v i32)
+    call $cell
+    local.tee $v
+    local.get $by
+    i32.lt_u
+    global.get $wrapping
+    i32.eqz
+    i32.and
+    if
+      i32.const 2
+      call $fail
+    end
+    local.get $v
+    local.get $by
+    i32.sub
+    call $store)
+
+  (func $wrt
+    call $cell
+    call $write_byte)
+
+  (func $load (param $n i32) (result i64) (local $i i32) (local $v i64)
+    local.get $n
+    call $check_range
+    block $done
+      loop $next
+        local.get $i
+        local.get $n
+        i32.ge_u
+        br_if $done
+        local.get $v
+        i64.const 8
+        i64.shl
+        global.get $p
+        local.get $i
+        i32.add
+        i64.load8_u
+        i64.or
+        local.set $v
+        local.get $i
+        i32.const 1
+        i32.add
+        local.set $i
+        br $next
+      end
+    end
+    local.get $v)
+
+  (func $write_u64 (param $v i64) (local $pos i32)
+    i32.const SCRATCH
+    i32.const 32
+    i32.add
+    local.set $pos
+    loop $next
+      local.get $pos
+      i32.const 1
+      i32.sub
+      local.tee $pos
+      local.get $v
+      i64.const 10
+      i64.rem_u
+      i64.const 48
+      i64.add
+      i64.store8
+      local.get $v
+      i64.const 10
+      i64.div_u
+      local.tee $v
+      i64.const 0
+      i64.ne
+      br_if $next
+    end
+    local.get $pos
+    i32.const SCRATCH
+    i32.const 32
+    i32.add
+    local.get $pos
+    i32.sub
+    call $write_str)
+
+  (func $write_i64 (param $v i64)
+    local.get $v
+    i64.const 0
+    i64.lt_s
+    if
+      i32.const 45
+      call $write_byte
+      i64.const 0
+      local.get $v
+      i64.sub
+      local.set $v
+    end
+    local.get $v
+    call $write_u64)
+
+  (func $rdi (local $c i32)
+    call $read_byte
+    local.tee $c
+    i32.const 0
+    i32.ge_s
+    if
+      local.get $c
+      call $store
+    end)
+
+  (func $rda (local $c i32)
+    block $done
+      loop $next
+        call $read_byte
+        local.tee $c
+        i32.const 0
+        i32.lt_s
+        br_if $done
+        local.get $c
+        i32.const 10
+        i32.eq
+        br_if $done
+        local.get $c
+        call $set_byte
+        br $next
+      end
+    end)
+
+  (func $wra (local $b i32)
+    block $done
+      loop $next
+        call $cell
+        local.tee $b
+        i32.eqz
+        br_if $done
+        local.get $b
+        call $write_byte
+        call $step
+        br $next
+      end
+    end)
+
+  (func $clr
+    i32.const 0
+    call $store
+    loop $next
+      call $step
+      call $cell
+      if
+        i32.const 0
+        call $store
+        br $next
+      end
+    end)
+
+  (func $entry (param $i i32) (result i32)
+    local.get $i
+    i32.const 16
+    i32.mul
+    i32.const STACK
+    i32.add)
+
+  (func $copy (param $to i32) (param $from i32) (param $n i32) (local $i i32)
+    block $done
+      loop $next
+        local.get $i
+        local.get $n
+        i32.ge_u
+        br_if $done
+        local.get $to
+        local.get $i
+        i32.add
+        local.get $from
+        local.get $i
+        i32.add
+        i32.load8_u
+        i32.store8
+        local.get $i
+        i32.const 1
+        i32.add
+        local.set $i
+        br $next
+      end
+    end)
+
+  (func $psh (param $n i32) (local $e i32)
+    local.get $n
+    call $check_range
+    global.get $stack
+    i32.const MAX_STACK
+    i32.ge_u
+    if
+      i32.const 4
+      call $fail
+    end
+    global.get $stack
+    call $entry
+    local.tee $e
+    local.get $n
+    i32.store
+    local.get $e
+    i32.const 8
+    i32.add
+    global.get $p
+    local.get $n
+    call $copy
+    global.get $stack
+    i32.const 1
+    i32.add
+    global.set $stack)
+
+  (func $put (param $e i32)
+    local.get $e
+    i32.load
+    call $check_range
+    global.get $p
+    local.get $e
+    i32.const 8
+    i32.add
+    local.get $e
+    i32.load
+    call $copy)
+
+  (func $check_depth (param $n i32)
+    global.get $stack
+    local.get $n
+    i32.lt_u
+    if
+      i32.const 3
+      call $fail
+    end)
+
+  (func $pop
+    i32.const 1
+    call $check_depth
+    global.get $stack
+    i32.const 1
+    i32.sub
+    global.set $stack
+    global.get $stack
+    call $entry
+    call $put)
+
+  (func $pek
+    i32.const 1
+    call $check_depth
+    global.get $stack
+    i32.const 1
+    i32.sub
+    call $entry
+    call $put)
+
+  (func $dup (local $e i32)
+    i32.const 1
+    call $check_depth
+    global.get $stack
+    i32.const MAX_STACK
+    i32.ge_u
+    if
+      i32.const 4
+      call $fail
+    end
+    global.get $stack
+    call $entry
+    local.tee $e
+    local.get $e
+    i32.const 16
+    i32.sub
+    i64.load
+    i64.store
+    local.get $e
+    local.get $e
+    i32.const 8
+    i32.sub
+    i64.load
+    i64.store offset=8
+    global.get $stack
+    i32.const 1
+    i32.add
+    global.set $stack)
+
+  (func $swp (local $a i32) (local $b i32) (local $len i64) (local $bytes i64)
+    i32.const 2
+    call $check_depth
+    global.get $stack
+    i32.const 1
+    i32.sub
+    call $entry
+    local.tee $a
+    i32.const 16
+    i32.sub
+    local.set $b
+    local.get $a
+    i64.load
+    local.set $len
+    local.get $a
+    i64.load offset=8
+    local.set $bytes
+    local.get $a
+    local.get $b
+    i64.load
+    i64.store
+    local.get $a
+    local.get $b
+    i64.load offset=8
+    i64.store offset=8
+    local.get $b
+    local.get $len
+    i64.store
+    local.get $b
+    local.get $bytes
+    i64.store offset=8)
+"#;
+
+/// Compiles the given program to a WebAssembly module in text format.
+/// Returns an error for instructions that cannot be compiled.
+///
+/// The module imports the following host functions from `env`:
+///
+/// * `read_byte () -> i32` returns the next input byte or -1 at the end of the input.
+/// * `write_byte (i32)` writes a single byte.
+/// * `write_str (i32, i32)` writes `len` bytes starting at `ptr` in the exported memory.
+///
+/// It exports its `memory`, the function `run` which executes the program and the global `error`.
+/// Every runtime error traps after setting `error` to one of the following codes:
+///
+/// * 1 - `TapeErrorType::Index`
+/// * 2 - `TapeErrorType::Overflow`
+/// * 3 - `StackErrorType::Underflow`
+/// * 4 - `StackErrorType::Overflow`
+///
+/// Assertions are not checked and only kept as comments.
+/// # Arguments
+/// * `program` - The program to compile.
+/// * `options` - The options for the generated module.
+pub fn to_wat(program: &Program, options: &WatOptions) -> CodegenResult<String> {
+    let scratch = align(options.tape_size);
+    let bookmarks = scratch + 32;
+    let stack = align(bookmarks + 4 * options.max_bookmarks);
+    let pages = (stack + 16 * options.max_stack_depth).div_ceil(65536);
+
+    let mut out = String::new();
+    out.push_str("(module\n");
+    out.push_str("  (import \"env\" \"read_byte\" (func $read_byte (result i32)))\n");
+    out.push_str("  (import \"env\" \"write_byte\" (func $write_byte (param i32)))\n");
+    out.push_str("  (import \"env\" \"write_str\" (func $write_str (param i32 i32)))\n\n");
+    out.push_str(&format!("  (memory (export \"memory\") {})\n\n", pages));
+    out.push_str("  (global $p (mut i32) (i32.const 0))\n");
+    out.push_str("  (global $bookmarks (mut i32) (i32.const 0))\n");
+    out.push_str("  (global $stack (mut i32) (i32.const 0))\n");
+    out.push_str("  (global $error (export \"error\") (mut i32) (i32.const 0))\n");
+    out.push_str(&format!(
+        "  (global $wrapping i32 (i32.const {}))\n\n",
+        program.wrapping as u8
+    ));
+    out.push_str(
+        &RUNTIME
+            .replace("TAPE_SIZE", &options.tape_size.to_string())
+            .replace("SCRATCH", &scratch.to_string())
+            .replace("MAX_BOOKMARKS", &options.max_bookmarks.to_string())
+            .replace("BOOKMARKS", &bookmarks.to_string())
+            .replace("MAX_STACK", &options.max_stack_depth.to_string())
+            .replace("STACK", &stack.to_string()),
+    );
+    out.push_str("\n  (func (export \"run\")\n");
+
+    let mut depth = 2;
+    for (i, instruction) in program.instructions.iter().enumerate() {
+        let code = match instruction {
+            Instruction::Pfw(n) => format!("i64.const {} call $pfw", n),
+            Instruction::Pbw(n) => format!("i64.const {} call $pbw", n),
+            Instruction::Ptr(n) => format!("i64.const {} call $ptr", n),
+            Instruction::Mrk => "call $mrk".to_string(),
+            Instruction::Ret => "call $ret".to_string(),
+            Instruction::Inc(n) => format!("i32.const {} call $inc", n),
+            Instruction::Dec(n) => format!("i32.const {} call $dec", n),
+            Instruction::Lop(_) => {
+                line(&mut out, depth, "loop");
+                depth += 1;
+                continue;
+            }
+            Instruction::Whl(_) => {
+                line(&mut out, depth, "block call $cell i32.eqz br_if 0 loop");
+                depth += 1;
+                continue;
+            }
+            Instruction::Pol(start) => {
+                depth -= 1;
+                match program.instructions[*start] {
+                    Instruction::Lop(_) => line(&mut out, depth, "call $cell br_if 0 end"),
+                    _ => line(&mut out, depth, "call $cell br_if 0 end end"),
+                }
+                continue;
+            }
+            Instruction::Wrt => "call $wrt".to_string(),
+            Instruction::Wrti8 => signed(1),
+            Instruction::Wrti16 => signed(2),
+            Instruction::Wrti32 => signed(4),
+            Instruction::Wrti64 => signed(8),
+            Instruction::Wrtu8 => "i32.const 1 call $load call $write_u64".to_string(),
+            Instruction::Wrtu16 => "i32.const 2 call $load call $write_u64".to_string(),
+            Instruction::Wrtu32 => "i32.const 4 call $load call $write_u64".to_string(),
+            Instruction::Wrtu64 => "i32.const 8 call $load call $write_u64".to_string(),
+            Instruction::Wrtf32 | Instruction::Wrtf64 => {
+                return Err(error_at(
+                    program,
+                    i,
+                    CodegenErrorType::Unsupported,
+                    &format!(
+                        "'{}' cannot be compiled to WebAssembly.",
+                        instruction.op_code()
+                    ),
+                ))
+            }
+            Instruction::Rdi => "call $rdi".to_string(),
+            Instruction::Set(v) => set(v.as_bytes()),
+            Instruction::Seti8(v) => set(&v.to_be_bytes()),
+            Instruction::Seti16(v) => set(&v.to_be_bytes()),
+            Instruction::Seti32(v) => set(&v.to_be_bytes()),
+            Instruction::Seti64(v) => set(&v.to_be_bytes()),
+            Instruction::Setu8(v) => set(&v.to_be_bytes()),
+            Instruction::Setu16(v) => set(&v.to_be_bytes()),
+            Instruction::Setu32(v) => set(&v.to_be_bytes()),
+            Instruction::Setu64(v) => set(&v.to_be_bytes()),
+            Instruction::Setf32(v) => set(&v.to_be_bytes()),
+            Instruction::Setf64(v) => set(&v.to_be_bytes()),
+            Instruction::Wra => "call $wra".to_string(),
+            Instruction::Rda => "call $rda".to_string(),
+            Instruction::Clr => "call $clr".to_string(),
+            Instruction::Psh => "i32.const 1 call $psh".to_string(),
+            Instruction::Psh16 => "i32.const 2 call $psh".to_string(),
+            Instruction::Psh32 => "i32.const 4 call $psh".to_string(),
+            Instruction::Psh64 => "i32.const 8 call $psh".to_string(),
+            Instruction::Pop => "call $pop".to_string(),
+            Instruction::Pek => "call $pek".to_string(),
+            Instruction::Dup => "call $dup".to_string(),
+            Instruction::Swp => "call $swp".to_string(),
+            Instruction::Ast(_)
+            | Instruction::Asti8(_)
+            | Instruction::Asti16(_)
+            | Instruction::Asti32(_)
+            | Instruction::Asti64(_)
+            | Instruction::Astu8(_)
+            | Instruction::Astu16(_)
+            | Instruction::Astu32(_)
+            | Instruction::Astu64(_)
+            | Instruction::Astf32(..)
+            | Instruction::Astf64(..)
+            | Instruction::Asp(_) => format!(";; {}", instruction),
+        };
+
+        line(&mut out, depth, &code);
+    }
+
+    out.push_str("  )\n)\n");
+    Ok(out)
+}
+
+/// Rounds the given address up to a multiple of 16.
+fn align(address: usize) -> usize {
+    address.div_ceil(16) * 16
+}
+
+/// Returns the code that writes the signed integer made of the next `n` cells.
+fn signed(n: usize) -> String {
+    let shift = 64 - 8 * n;
+    format!(
+        "i32.const {} call $load i64.const {} i64.shl i64.const {} i64.shr_s call $write_i64",
+        n, shift, shift
+    )
+}
+
+/// Returns the code that sets the given bytes.
+fn set(bytes: &[u8]) -> String {
+    let calls: Vec<String> = bytes
+        .iter()
+        .map(|b| format!("i32.const {} call $set_byte", b))
+        .collect();
+    calls.join(" ")
+}
+
+fn line(out: &mut String, depth: usize, code: &str) {
+    for _ in 0..depth {
+        out.push_str("  ");
+    }
+    out.push_str(code);
+    out.push('\n');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WatOptions;
+    use crate::{CodegenErrorType, Interpreter, Program, SharedBuffer};
+    use wasmi::{Caller, Engine, Linker, Module, Store};
+
+    struct Host {
+        input: Vec<u8>,
+        output: Vec<u8>,
+    }
+
+    /// Runs the module and returns its output and the error code if it trapped.
+    fn run_wat(wat: &str, input: &[u8]) -> (Vec<u8>, Option<i32>) {
+        let wasm = wat::parse_str(wat).unwrap();
+        let engine = Engine::default();
+        let module = Module::new(&engine, &wasm[..]).unwrap();
+
+        let mut store = Store::new(
+            &engine,
+            Host {
+                input: input.iter().rev().copied().collect(),
+                output: vec![],
+            },
+        );
+
+        let mut linker = <Linker<Host>>::new(&engine);
+        linker
+            .func_wrap("env", "read_byte", |mut caller: Caller<'_, Host>| {
+                caller.data_mut().input.pop().map_or(-1, i32::from)
+            })
+            .unwrap();
+        linker
+            .func_wrap(
+                "env",
+                "write_byte",
+                |mut caller: Caller<'_, Host>, b: i32| {
+                    caller.data_mut().output.push(b as u8);
+                },
+            )
+            .unwrap();
+        linker
+            .func_wrap(
+                "env",
+                "write_str",
+                |mut caller: Caller<'_, Host>, ptr: i32, len: i32| {
+                    let memory = caller.get_export("memory").unwrap().into_memory().unwrap();
+                    let bytes = memory.data(&caller)[ptr as usize..(ptr + len) as usize].to_vec();
+                    caller.data_mut().output.extend(bytes);
+                },
+            )
+            .unwrap();
+
+        let instance = linker
+            .instantiate(&mut store, &module)
+            .unwrap()
+            .start(&mut store)
+            .unwrap();
+        let run = instance.get_typed_func::<(), ()>(&store, "run").unwrap();
+
+        let trapped = run.call(&mut store, ()).is_err();
+        let error = instance
+            .get_global(&store, "error")
+            .unwrap()
+            .get(&store)
+            .i32()
+            .unwrap();
+
+        let output = std::mem::take(&mut store.data_mut().output);
+        (output, if trapped { Some(error) } else { None })
+    }
+
+    /// Runs the code in the interpreter and as WebAssembly and compares both.
+    fn assert_same_as_interpreter(code: &str, input: &[u8]) -> Option<i32> {
+        let program = Program::parse(code.as_bytes()).unwrap();
+        let wat = super::to_wat(&program, &WatOptions::default()).unwrap();
+
+        let out = SharedBuffer::new();
+        let mut interpreter = Interpreter::default();
+        interpreter.set_output(out.clone());
+        interpreter.set_input(std::io::Cursor::new(input.to_vec()));
+        let result = interpreter.run_program(program);
+
+        let (output, error) = run_wat(&wat, input);
+
+        assert_eq!(output, out.contents());
+        assert_eq!(result.is_err(), error.is_some());
+        error
+    }
+
+    #[test]
+    fn examples_match_interpreter_test() {
+        for name in ["example", "loop", "seti", "setu"] {
+            let code = std::fs::read_to_string(format!("../examples/{}.trng", name)).unwrap();
+
+            assert_eq!(assert_same_as_interpreter(&code, b""), None);
+        }
+    }
+
+    #[test]
+    fn io_and_stack_match_interpreter_test() {
+        let code = "
+            rda ptr 0 wra
+            pfw 1 rdi wrt
+            seti64 -9223372036854775808 pbw 8 wrti64
+            seti16 -300 pbw 2 psh16 pfw 9 mrk pop wrti16 ret
+            set AB pbw 2 psh pfw 1 psh swp pop pbw 1 pop wra
+            inc 3 whl dec 1 psh dup wrtu8 pol";
+
+        assert_eq!(assert_same_as_interpreter(code, b"Hi\n!"), None);
+    }
+
+    #[test]
+    fn overflow_traps_test() {
+        assert_eq!(
+            assert_same_as_interpreter("inc 65 wrt inc 200", b""),
+            Some(2)
+        );
+    }
+
+    #[test]
+    fn brainfuck_wraps_around_test() {
+        let program = Program::parse_brainfuck("-.>+[+]".as_bytes()).unwrap();
+        let wat = super::to_wat(&program, &WatOptions::default()).unwrap();
+
+        assert_eq!(run_wat(&wat, b""), (vec![255], None));
+    }
+
+    #[test]
+    fn index_error_traps_test() {
+        assert_eq!(assert_same_as_interpreter("pbw 1", b""), Some(1));
+    }
+
+    #[test]
+    fn stack_underflow_traps_test() {
+        assert_eq!(assert_same_as_interpreter("pop", b""), Some(3));
+    }
+
+    #[test]
+    fn float_output_is_unsupported_test() {
+        let program = Program::from_file("../examples/setf.trng").unwrap();
+
+        let err = super::to_wat(&program, &WatOptions::default()).unwrap_err();
+
+        assert_eq!(err._type, CodegenErrorType::Unsupported);
+    }
+}
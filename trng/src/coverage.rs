@@ -0,0 +1,281 @@
+// TRNG - Brainfucks pretty sister.
+// Copyright (C) 2023 Lukas Pfeifer
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::program::{Instruction, Program};
+use std::collections::BTreeMap;
+
+/// The name used for programs that were not loaded from a file.
+const UNNAMED: &str = "<program>";
+
+/// Executions of a single source file.
+#[derive(Default)]
+struct FileCoverage {
+    /// The instructions the records belong to.
+    instructions: Vec<Instruction>,
+    /// The source line of each instruction.
+    lines: Vec<u32>,
+    /// How often each instruction was executed.
+    hits: Vec<u64>,
+    /// How often each `pol` jumped back and how often it ended its loop. Indexed by instruction index.
+    branches: Vec<[u64; 2]>,
+    /// Whether each instruction is a `pol`.
+    is_pol: Vec<bool>,
+}
+
+impl FileCoverage {
+    /// Returns the highest execution count of the instructions on each line.
+    fn line_hits(&self) -> BTreeMap<u32, u64> {
+        let mut lines: BTreeMap<u32, u64> = BTreeMap::new();
+        for (line, hits) in self.lines.iter().zip(&self.hits) {
+            let entry = lines.entry(*line).or_default();
+            *entry = (*entry).max(*hits);
+        }
+        lines
+    }
+
+    /// Returns the line, the instruction index and the counts of every `pol` branch.
+    fn pol_branches(&self) -> impl Iterator<Item = (u32, usize, [u64; 2])> + '_ {
+        self.branches
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| self.is_pol[*i])
+            .map(|(i, b)| (self.lines[i], i, *b))
+    }
+}
+
+/// Records which source lines and `pol` branches were executed.
+///
+/// Set it with `Interpreter::set_coverage`. Executions of all runs are added up per source file,
+/// so running a program with several inputs shows what the inputs exercise together.
+#[derive(Default)]
+pub struct Coverage {
+    files: BTreeMap<String, FileCoverage>,
+    /// The file the next runs are attributed to.
+    source: Option<String>,
+}
+
+impl Coverage {
+    /// Creates a collector without any records.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the file name the following runs are attributed to.
+    /// `Interpreter::run_file` sets it to the path of the file.
+    /// # Arguments
+    /// * `name` - The name of the source file.
+    pub fn set_source(&mut self, name: &str) {
+        self.source = Some(name.to_string());
+    }
+
+    /// Prepares the records of the current file before the execution starts.
+    /// # Arguments
+    /// * `program` - The executed program.
+    pub(crate) fn begin(&mut self, program: &Program) {
+        let name = self.source.clone().unwrap_or_else(|| UNNAMED.to_string());
+        let file = self.files.entry(name).or_default();
+
+        let lines: Vec<u32> = program.positions.iter().map(|p| p.line).collect();
+        // Records of a different program run under the same name are replaced, not merged.
+        if file.lines != lines || file.instructions != program.instructions {
+            *file = FileCoverage {
+                instructions: program.instructions.clone(),
+                lines,
+                hits: vec![0; program.len()],
+                branches: vec![[0; 2]; program.len()],
+                is_pol: program
+                    .instructions
+                    .iter()
+                    .map(|i| matches!(i, Instruction::Pol(_)))
+                    .collect(),
+            };
+        }
+    }
+
+    /// Records an executed instruction.
+    /// # Arguments
+    /// * `index` - The index of the executed instruction.
+    /// * `next` - The index of the instruction executed next.
+    pub(crate) fn record(&mut self, index: usize, next: usize) {
+        let name = self.source.as_deref().unwrap_or(UNNAMED);
+        if let Some(file) = self.files.get_mut(name) {
+            file.hits[index] += 1;
+
+            if file.is_pol[index] {
+                let branch = if next == index + 1 { 1 } else { 0 };
+                file.branches[index][branch] += 1;
+            }
+        }
+    }
+
+    /// Returns the records in the lcov tracefile format.
+    pub fn to_lcov(&self) -> String {
+        let mut lcov = String::new();
+
+        for (name, file) in &self.files {
+            lcov += &format!("TN:\nSF:{}\n", name);
+
+            let mut branches_found = 0;
+            let mut branches_hit = 0;
+            for (line, index, counts) in file.pol_branches() {
+                let reached = counts.iter().sum::<u64>() > 0;
+                for (branch, count) in counts.iter().enumerate() {
+                    let taken = if reached {
+                        count.to_string()
+                    } else {
+                        "-".to_string()
+                    };
+                    lcov += &format!("BRDA:{},{},{},{}\n", line, index, branch, taken);
+
+                    branches_found += 1;
+                    if *count > 0 {
+                        branches_hit += 1;
+                    }
+                }
+            }
+            lcov += &format!("BRF:{}\nBRH:{}\n", branches_found, branches_hit);
+
+            let lines = file.line_hits();
+            for (line, hits) in &lines {
+                lcov += &format!("DA:{},{}\n", line, hits);
+            }
+            lcov += &format!(
+                "LF:{}\nLH:{}\nend_of_record\n",
+                lines.len(),
+                lines.values().filter(|h| **h > 0).count()
+            );
+        }
+
+        lcov
+    }
+
+    /// Returns the percentage of executed lines and branches of every file and in total.
+    pub fn summary(&self) -> String {
+        let mut summary = String::new();
+        let mut total = [0; 4];
+
+        for (name, file) in &self.files {
+            let lines = file.line_hits();
+            let line_counts = [lines.values().filter(|h| **h > 0).count(), lines.len()];
+
+            let branches: Vec<u64> = file.pol_branches().flat_map(|(_, _, b)| b).collect();
+            let branch_counts = [branches.iter().filter(|b| **b > 0).count(), branches.len()];
+
+            summary += &format!(
+                "{}: {}, {}\n",
+                name,
+                ratio("lines", line_counts),
+                ratio("branches", branch_counts)
+            );
+
+            total[0] += line_counts[0];
+            total[1] += line_counts[1];
+            total[2] += branch_counts[0];
+            total[3] += branch_counts[1];
+        }
+
+        summary += &format!(
+            "total: {}, {}\n",
+            ratio("lines", [total[0], total[1]]),
+            ratio("branches", [total[2], total[3]])
+        );
+        summary
+    }
+}
+
+/// Formats covered and found items, e.g. `lines 3/4 (75.0%)`.
+fn ratio(what: &str, [covered, found]: [usize; 2]) -> String {
+    let percent = if found == 0 {
+        100.0
+    } else {
+        covered as f64 * 100.0 / found as f64
+    };
+
+    format!("{} {}/{} ({:.1}%)", what, covered, found, percent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Coverage;
+    use crate::{Interpreter, SharedBuffer};
+
+    fn cover(coverage: Coverage, code: &str) -> Coverage {
+        let mut interpreter = Interpreter::default();
+        interpreter.set_output(SharedBuffer::new());
+        interpreter.set_coverage(Some(coverage));
+        interpreter.run(code.as_bytes()).unwrap();
+        interpreter.take_coverage().unwrap()
+    }
+
+    #[test]
+    fn lcov_contains_lines_and_branches_test() {
+        let mut coverage = Coverage::new();
+        coverage.set_source("a.trng");
+        let coverage = cover(coverage, "inc 2\nlop\n    dec 1\npol\nwhl\n    wrt\npol");
+
+        assert_eq!(
+            coverage.to_lcov(),
+            "TN:\nSF:a.trng\n\
+             BRDA:4,3,0,1\nBRDA:4,3,1,1\nBRDA:7,6,0,-\nBRDA:7,6,1,-\nBRF:4\nBRH:2\n\
+             DA:1,1\nDA:2,1\nDA:3,2\nDA:4,2\nDA:5,1\nDA:6,0\nDA:7,0\n\
+             LF:7\nLH:5\nend_of_record\n"
+        );
+    }
+
+    #[test]
+    fn runs_are_added_up_test() {
+        let code = "rdi\nwhl\n    dec 1\npol";
+
+        let mut coverage = Coverage::new();
+        for input in ["", "\u{1}"] {
+            let mut interpreter = Interpreter::default();
+            interpreter.set_input(std::io::Cursor::new(input.as_bytes().to_vec()));
+            interpreter.set_coverage(Some(coverage));
+            interpreter.run(code.as_bytes()).unwrap();
+            coverage = interpreter.take_coverage().unwrap();
+        }
+
+        assert_eq!(
+            coverage.summary(),
+            "<program>: lines 4/4 (100.0%), branches 1/2 (50.0%)\n\
+             total: lines 4/4 (100.0%), branches 1/2 (50.0%)\n"
+        );
+    }
+
+    #[test]
+    fn different_program_replaces_records_test() {
+        let coverage = cover(Coverage::new(), "inc 1\nwrt\nwrt\nwrt");
+        let coverage = cover(coverage, "inc 1\nlop\ndec 1\npol");
+
+        assert_eq!(
+            coverage.summary(),
+            "<program>: lines 4/4 (100.0%), branches 1/2 (50.0%)\n\
+             total: lines 4/4 (100.0%), branches 1/2 (50.0%)\n"
+        );
+    }
+
+    #[test]
+    fn run_file_sets_source_test() {
+        let mut interpreter = Interpreter::default();
+        interpreter.set_output(SharedBuffer::new());
+        interpreter.set_coverage(Some(Coverage::new()));
+        interpreter.run_file("../examples/loop.trng").unwrap();
+
+        let summary = interpreter.take_coverage().unwrap().summary();
+
+        assert!(summary.starts_with("../examples/loop.trng: lines 5/5 (100.0%), branches 2/2"));
+    }
+}
@@ -0,0 +1,156 @@
+// TRNG - Brainfucks pretty sister.
+// Copyright (C) 2023 Lukas Pfeifer
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::lexer::{ReadLexer, Token, TokenType};
+use crate::program::{ParseResult, Program};
+use std::io::Read;
+
+/// The indentation of one loop level.
+const INDENT: &str = "    ";
+
+/// Formats TRNG code in the canonical style.
+///
+/// Every instruction is placed on its own line together with its operand and loop bodies are
+/// indented by four spaces. Comments are kept, single blank lines between instructions as well.
+/// Returns an error if the code cannot be parsed.
+/// # Arguments
+/// * `read_from` - Source for TRNG code. Can be anything that implements the `Read` trait.
+pub fn format_source<T>(read_from: T) -> ParseResult<String>
+where
+    T: Read,
+{
+    let tokens = ReadLexer::new(read_from).collect::<Result<Vec<Token>, _>>()?;
+
+    // Only well-formed programs are formatted.
+    Program::from_tokens(
+        tokens
+            .iter()
+            .filter(|t| t._type != TokenType::Comment)
+            .cloned(),
+    )?;
+
+    let mut out = String::new();
+    let mut depth = 0;
+    let mut last_line: Option<u32> = None;
+
+    let mut i = 0;
+    while let Some(token) = tokens.get(i) {
+        let trailing = last_line == Some(token.line);
+
+        if token._type == TokenType::Comment && trailing {
+            out.push(' ');
+            out.push_str(&token.value);
+            i += 1;
+            continue;
+        }
+
+        if last_line.is_some() {
+            out.push('\n');
+        }
+
+        if let Some(line) = last_line {
+            if token.line > line + 1 {
+                out.push('\n');
+            }
+        }
+
+        if token._type == TokenType::Pol {
+            depth -= 1;
+        }
+
+        out.push_str(&INDENT.repeat(depth));
+        out.push_str(&token.value);
+        last_line = Some(token.line);
+
+        for _ in 0..token._type.operand_count() {
+            i += 1;
+            let operand = &tokens[i];
+            out.push(' ');
+            out.push_str(&operand.value);
+            last_line = Some(operand.line);
+        }
+
+        if let TokenType::Lop | TokenType::Whl = token._type {
+            depth += 1;
+        }
+
+        i += 1;
+    }
+
+    if last_line.is_some() {
+        out.push('\n');
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+
+    #[test]
+    fn format_indents_loops_test() {
+        let code = "inc 10\nlop\n  wrti8\n        dec 1\npol";
+
+        let formatted = super::format_source(code.as_bytes()).unwrap();
+
+        assert_eq!(formatted, "inc 10\nlop\n    wrti8\n    dec 1\npol\n");
+    }
+
+    #[test]
+    fn format_one_instruction_per_line_test() {
+        let code = "set Hello pbw\n5 wra";
+
+        let formatted = super::format_source(code.as_bytes()).unwrap();
+
+        assert_eq!(formatted, "set Hello\npbw 5\nwra\n");
+    }
+
+    #[test]
+    fn format_keeps_all_operands_test() {
+        let code = "setf64 1.5 pbw 8 astf64\n1.5 0.01";
+
+        let formatted = super::format_source(code.as_bytes()).unwrap();
+
+        assert_eq!(formatted, "setf64 1.5\npbw 8\nastf64 1.5 0.01\n");
+    }
+
+    #[test]
+    fn format_keeps_comments_and_blank_lines_test() {
+        let code = "; Prints A\ninc 65 ;the letter\n\n\n  whl   ; loop\nwrt dec 65\npol";
+
+        let formatted = super::format_source(code.as_bytes()).unwrap();
+
+        assert_eq!(
+            formatted,
+            "; Prints A\ninc 65 ;the letter\n\nwhl ; loop\n    wrt\n    dec 65\npol\n"
+        );
+    }
+
+    #[test]
+    fn format_is_idempotent_test() {
+        let code = std::fs::read_to_string("../examples/loop.trng").unwrap();
+
+        let once = super::format_source(code.as_bytes()).unwrap();
+        let twice = super::format_source(once.as_bytes()).unwrap();
+
+        assert_eq!(once, twice);
+    }
+
+    #[test]
+    fn format_invalid_code_is_err_test() {
+        assert!(super::format_source("lop inc 1".as_bytes()).is_err());
+    }
+}
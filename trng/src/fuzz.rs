@@ -0,0 +1,908 @@
+// TRNG - Brainfucks pretty sister.
+// Copyright (C) 2023 Lukas Pfeifer
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::buffer::SharedBuffer;
+use crate::codegen::{to_brainfuck, to_c, COptions};
+#[cfg(feature = "wasm")]
+use crate::codegen::{to_wat, WatOptions};
+use crate::coverage::Coverage;
+use crate::interpreter::{Interpreter, InterpreterErrorType};
+use crate::profile::Profiler;
+use crate::program::{Instruction, Position, Program};
+use crate::trace::{TraceFormat, Tracer};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::OnceLock;
+
+/// The number of cells generated programs use.
+const REGION: usize = 48;
+
+/// The deepest loop nesting of generated programs.
+const MAX_DEPTH: usize = 3;
+
+/// The number of instructions a program may execute in the reference engine.
+/// Programs that run longer are not compared, which keeps shrinking from getting stuck in endless loops.
+const FUEL: u64 = 1_000_000;
+
+/// The number of rejected instructions after which the generator closes a loop or stops.
+const MAX_REJECTED: usize = 32;
+
+/// A small deterministic pseudo random number generator (SplitMix64).
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a number in `0..n`.
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+
+    /// Returns a number in `lo..=hi`.
+    fn range(&mut self, lo: usize, hi: usize) -> usize {
+        lo + self.below((hi - lo + 1) as u64) as usize
+    }
+}
+
+/// A loop that is open while its body is generated.
+#[derive(Clone)]
+struct Frame {
+    /// The cell the loop counts down.
+    counter: usize,
+    /// The number of bookmarks set inside the body that are not returned to yet.
+    marks: usize,
+}
+
+/// Generates random well-formed programs. The same seed always results in the same programs.
+///
+/// Generated programs are well-formed: loops are balanced and count a cell down to 0, the pointer
+/// stays inside the first `REGION` cells and no instruction fails. Every generated instruction is
+/// checked by running the program with the interpreter before it is accepted.
+pub struct Generator {
+    rng: Rng,
+}
+
+impl Generator {
+    /// Creates a generator.
+    /// # Arguments
+    /// * `seed` - The seed of the random numbers.
+    pub fn new(seed: u64) -> Self {
+        Self { rng: Rng(seed) }
+    }
+
+    /// Generates a program with about the given number of instructions.
+    /// # Arguments
+    /// * `size` - The number of instructions to aim for.
+    pub fn generate(&mut self, size: usize) -> Program {
+        let mut code: Vec<Instruction> = vec![];
+        let mut frames: Vec<Frame> = vec![];
+        let mut ptr = 0;
+        let mut bookmarks: Vec<usize> = vec![];
+        let mut rejected = 0;
+
+        while code.len() < size && rejected < MAX_REJECTED {
+            if !frames.is_empty() && self.rng.below(8) == 0 {
+                close(&mut code, &mut frames, &mut ptr, &mut bookmarks);
+                continue;
+            }
+
+            let (candidate, next_ptr) = self.candidate(&code, &frames, ptr, &bookmarks);
+            let mut extended = code.clone();
+            extended.extend(candidate.iter().cloned());
+
+            let mut opens = false;
+            let mut next_bookmarks = bookmarks.clone();
+            for instruction in &candidate {
+                match instruction {
+                    Instruction::Lop(_) | Instruction::Whl(_) => opens = true,
+                    Instruction::Mrk => next_bookmarks.push(ptr),
+                    Instruction::Ret => {
+                        next_bookmarks.pop();
+                    }
+                    _ => (),
+                }
+            }
+
+            let mut next_frames = frames.clone();
+            if opens {
+                next_frames.push(Frame {
+                    counter: next_ptr,
+                    marks: 0,
+                });
+            } else if let Some(frame) = next_frames.last_mut() {
+                if candidate.contains(&Instruction::Mrk) {
+                    frame.marks += 1;
+                }
+                if candidate.contains(&Instruction::Ret) {
+                    frame.marks -= 1;
+                }
+            }
+
+            if run(&completed(&extended, &next_frames)).is_some() {
+                code = extended;
+                frames = next_frames;
+                ptr = next_ptr;
+                bookmarks = next_bookmarks;
+                rejected = 0;
+            } else {
+                rejected += 1;
+                if rejected == MAX_REJECTED && !frames.is_empty() {
+                    close(&mut code, &mut frames, &mut ptr, &mut bookmarks);
+                    rejected = 0;
+                }
+            }
+        }
+
+        while !frames.is_empty() {
+            close(&mut code, &mut frames, &mut ptr, &mut bookmarks);
+        }
+
+        build(&code)
+    }
+
+    /// Returns random instructions that may follow the code and the pointer index after them.
+    /// Whether they fail is checked by the caller.
+    fn candidate(
+        &mut self,
+        code: &[Instruction],
+        frames: &[Frame],
+        ptr: usize,
+        bookmarks: &[usize],
+    ) -> (Vec<Instruction>, usize) {
+        let rng = &mut self.rng;
+        let room = REGION - 1 - ptr;
+
+        match rng.below(24) {
+            0 | 1 => {
+                let n = rng.range(0, room.min(6));
+                (vec![Instruction::Pfw(n)], ptr + n)
+            }
+            2 | 3 => {
+                let n = rng.range(0, ptr.min(6));
+                (vec![Instruction::Pbw(n)], ptr - n)
+            }
+            4 => {
+                let index = rng.range(0, REGION - 1);
+                (vec![Instruction::Ptr(index)], index)
+            }
+            5..=7 => (vec![Instruction::Inc(rng.range(0, 40) as u8)], ptr),
+            8 | 9 => (vec![Instruction::Dec(rng.range(0, 40) as u8)], ptr),
+            10 if room >= 8 => {
+                let bits = rng.next();
+                let (instruction, width) = match rng.below(11) {
+                    0 => (Instruction::Set(text(rng)), 0),
+                    1 => (Instruction::Seti8(bits as i8), 1),
+                    2 => (Instruction::Seti16(bits as i16), 2),
+                    3 => (Instruction::Seti32(bits as i32), 4),
+                    4 => (Instruction::Seti64(bits as i64), 8),
+                    5 => (Instruction::Setu8(bits as u8), 1),
+                    6 => (Instruction::Setu16(bits as u16), 2),
+                    7 => (Instruction::Setu32(bits as u32), 4),
+                    8 => (Instruction::Setu64(bits), 8),
+                    9 => (Instruction::Setf32(f32::from_bits(bits as u32)), 4),
+                    _ => (Instruction::Setf64(f64::from_bits(bits)), 8),
+                };
+                let width = match &instruction {
+                    Instruction::Set(v) => v.len(),
+                    _ => width,
+                };
+                (vec![instruction], ptr + width)
+            }
+            11 | 12 => (vec![Instruction::Wrt], ptr),
+            13 if room >= 8 => {
+                let instruction = match rng.below(10) {
+                    0 => Instruction::Wrti8,
+                    1 => Instruction::Wrti16,
+                    2 => Instruction::Wrti32,
+                    3 => Instruction::Wrti64,
+                    4 => Instruction::Wrtu8,
+                    5 => Instruction::Wrtu16,
+                    6 => Instruction::Wrtu32,
+                    7 => Instruction::Wrtu64,
+                    8 => Instruction::Wrtf32,
+                    _ => Instruction::Wrtf64,
+                };
+                (vec![instruction], ptr)
+            }
+            14 => {
+                // The pointer position after `wra` and `clr` depends on the data.
+                let index = rng.range(0, REGION - 1);
+                let instruction = match rng.below(2) {
+                    0 => Instruction::Wra,
+                    _ => Instruction::Clr,
+                };
+                (vec![instruction, Instruction::Ptr(index)], index)
+            }
+            15 if room >= 8 => {
+                let instruction = match rng.below(7) {
+                    0 => Instruction::Psh,
+                    1 => Instruction::Psh16,
+                    2 => Instruction::Psh32,
+                    3 => Instruction::Psh64,
+                    4 => Instruction::Pop,
+                    5 => Instruction::Pek,
+                    _ => Instruction::Dup,
+                };
+                (vec![instruction], ptr)
+            }
+            16 => (vec![Instruction::Swp], ptr),
+            17 => (vec![Instruction::Mrk], ptr),
+            18 if frames.last().map_or(!bookmarks.is_empty(), |f| f.marks > 0) => {
+                (vec![Instruction::Ret], bookmarks[bookmarks.len() - 1])
+            }
+            19 => (vec![Instruction::Asp(ptr)], ptr),
+            // Cell values are only known outside of loops.
+            20 if frames.is_empty() && room >= 8 => {
+                let data = run(&build(code)).unwrap_or_default();
+                let cells = &data[ptr..ptr + 8];
+                let instruction = match rng.below(4) {
+                    0 => Instruction::Ast(cells[0]),
+                    1 => Instruction::Asti16(i16::from_be_bytes([cells[0], cells[1]])),
+                    2 => Instruction::Asti32(i32::from_be_bytes([
+                        cells[0], cells[1], cells[2], cells[3],
+                    ])),
+                    _ => Instruction::Astu64(u64::from_be_bytes([
+                        cells[0], cells[1], cells[2], cells[3], cells[4], cells[5], cells[6],
+                        cells[7],
+                    ])),
+                };
+                (vec![instruction], ptr)
+            }
+            21..=23 if frames.len() < MAX_DEPTH && room >= 2 => {
+                let (count, start) = match rng.below(2) {
+                    0 => (rng.range(1, 4), Instruction::Lop(0)),
+                    _ => (rng.range(0, 3), Instruction::Whl(0)),
+                };
+                let code = vec![Instruction::Setu8(count as u8), Instruction::Pbw(1), start];
+                (code, ptr)
+            }
+            _ => (vec![Instruction::Wrt], ptr),
+        }
+    }
+}
+
+/// Closes the innermost loop. Returns to the bookmarks set inside of it and moves to its counter first.
+fn close(
+    code: &mut Vec<Instruction>,
+    frames: &mut Vec<Frame>,
+    ptr: &mut usize,
+    bookmarks: &mut Vec<usize>,
+) {
+    if let Some(frame) = frames.pop() {
+        code.extend(closing(&frame));
+        bookmarks.truncate(bookmarks.len() - frame.marks);
+        *ptr = frame.counter;
+    }
+}
+
+/// Returns the instructions that close the loop of a frame.
+fn closing(frame: &Frame) -> Vec<Instruction> {
+    let mut code = vec![Instruction::Ret; frame.marks];
+    code.extend([
+        Instruction::Ptr(frame.counter),
+        Instruction::Dec(1),
+        Instruction::Pol(0),
+    ]);
+    code
+}
+
+/// Returns the code with all open loops closed.
+fn completed(code: &[Instruction], frames: &[Frame]) -> Program {
+    let mut code = code.to_vec();
+    for frame in frames.iter().rev() {
+        code.extend(closing(frame));
+    }
+    build(&code)
+}
+
+/// Returns random printable text without whitespace.
+fn text(rng: &mut Rng) -> String {
+    (0..rng.range(1, 6))
+        .map(|_| (b'!' + rng.below(94) as u8) as char)
+        .collect()
+}
+
+/// Creates a program with the instructions. Each instruction gets its own line.
+fn build(code: &[Instruction]) -> Program {
+    let mut program = Program::default();
+    for (i, instruction) in code.iter().enumerate() {
+        program.push(
+            instruction.clone(),
+            Position {
+                line: i as u32 + 1,
+                column: 1,
+            },
+        );
+    }
+
+    // Loops that were cut apart while shrinking are rejected later on.
+    let _ = program.resolve_loops();
+    program
+}
+
+/// Runs a program with the reference engine.
+/// Returns the tape if it ran successfully within the fuel.
+fn run(program: &Program) -> Option<Vec<u8>> {
+    let mut interpreter = Interpreter::default();
+    interpreter.set_output(std::io::sink());
+    interpreter.set_input(std::io::empty());
+    interpreter.set_fuel(Some(FUEL));
+
+    interpreter.run_program(program.clone()).ok()?;
+    Some(interpreter.get_data().to_vec())
+}
+
+/// The result of running a program with an engine.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Outcome {
+    /// The bytes the program wrote.
+    pub output: Vec<u8>,
+    /// The error the program stopped with.
+    pub error: Option<String>,
+}
+
+/// An engine or mode that executes programs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Engine {
+    /// `Interpreter::run_program` with fuel. The reference all other engines are compared with.
+    Interpreter,
+    /// The interpreter with a tracer, a profiler and a coverage collector.
+    Observed,
+    /// The interpreter with a single instruction of fuel, resumed after every instruction.
+    Stepped,
+    /// The interpreter with assertions removed.
+    Release,
+    /// `Interpreter::run_jit`.
+    #[cfg(feature = "jit")]
+    Jit,
+    /// The program lowered to Brainfuck. Only runs programs that can be lowered and do not fail.
+    Brainfuck,
+    /// The program compiled to C with `cc`. Only used if `cc` can be run.
+    C,
+    /// The program compiled to WebAssembly and run with wasmi. Only runs programs that can be
+    /// compiled and do not fail.
+    #[cfg(feature = "wasm")]
+    Wat,
+}
+
+impl std::fmt::Display for Engine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Engine::Interpreter => write!(f, "interpreter"),
+            Engine::Observed => write!(f, "observed"),
+            Engine::Stepped => write!(f, "stepped"),
+            Engine::Release => write!(f, "release"),
+            #[cfg(feature = "jit")]
+            Engine::Jit => write!(f, "jit"),
+            Engine::Brainfuck => write!(f, "brainfuck"),
+            Engine::C => write!(f, "c"),
+            #[cfg(feature = "wasm")]
+            Engine::Wat => write!(f, "wat"),
+        }
+    }
+}
+
+impl Engine {
+    /// All engines that are available. The first one is the reference.
+    pub fn all() -> Vec<Engine> {
+        let mut engines = vec![
+            Engine::Interpreter,
+            Engine::Observed,
+            Engine::Stepped,
+            Engine::Release,
+            #[cfg(feature = "jit")]
+            Engine::Jit,
+            Engine::Brainfuck,
+            #[cfg(feature = "wasm")]
+            Engine::Wat,
+        ];
+
+        if cc_available() {
+            engines.push(Engine::C);
+        }
+
+        engines
+    }
+
+    /// Returns whether the engine runs a lowered program that does not report the errors of the
+    /// interpreter.
+    fn lowered(&self) -> bool {
+        match self {
+            Engine::Brainfuck => true,
+            #[cfg(feature = "wasm")]
+            Engine::Wat => true,
+            _ => false,
+        }
+    }
+
+    /// Runs a program without input.
+    /// Returns `None` if the engine cannot run the program.
+    /// # Arguments
+    /// * `program` - The program to run.
+    pub fn run(&self, program: &Program) -> Option<Outcome> {
+        match self {
+            Engine::C => return run_c(program),
+            #[cfg(feature = "wasm")]
+            Engine::Wat => return run_wat(program),
+            _ => (),
+        }
+
+        let output = SharedBuffer::new();
+        let mut interpreter = Interpreter::default();
+        interpreter.set_output(output.clone());
+        interpreter.set_input(std::io::empty());
+
+        let result = match self {
+            Engine::Interpreter => {
+                interpreter.set_fuel(Some(FUEL));
+                interpreter.run_program(program.clone())
+            }
+            Engine::Observed => {
+                interpreter.set_tracer(Some(Tracer::new(std::io::sink(), TraceFormat::Text)));
+                interpreter.set_profiler(Some(Profiler::new()));
+                interpreter.set_coverage(Some(Coverage::new()));
+                interpreter.run_program(program.clone())
+            }
+            Engine::Stepped => {
+                interpreter.set_fuel(Some(0));
+                interpreter.load_program(program.clone());
+                loop {
+                    interpreter.add_fuel(1);
+                    match interpreter.resume() {
+                        Err(e) if e._type == InterpreterErrorType::BudgetExhausted => continue,
+                        result => break result,
+                    }
+                }
+            }
+            Engine::Release => {
+                interpreter.set_release(true);
+                interpreter.run_program(program.clone())
+            }
+            #[cfg(feature = "jit")]
+            Engine::Jit => interpreter.run_jit(program.clone()),
+            Engine::Brainfuck => {
+                let code = to_brainfuck(program).ok()?;
+                interpreter.run_brainfuck(code.as_bytes())
+            }
+            Engine::C => unreachable!("C programs do not run in the interpreter."),
+            #[cfg(feature = "wasm")]
+            Engine::Wat => unreachable!("WebAssembly modules do not run in the interpreter."),
+        };
+
+        Some(Outcome {
+            output: output.take(),
+            error: result.err().map(|e| e.to_string()),
+        })
+    }
+}
+
+/// Returns whether `cc` can be run. Checked once per process.
+fn cc_available() -> bool {
+    static AVAILABLE: OnceLock<bool> = OnceLock::new();
+    *AVAILABLE.get_or_init(|| {
+        Command::new("cc")
+            .arg("--version")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|s| s.success())
+    })
+}
+
+/// Compiles a program to C, builds it with `cc` and runs it without input.
+/// Returns `None` if the program cannot be built.
+/// # Arguments
+/// * `program` - The program to run.
+fn run_c(program: &Program) -> Option<Outcome> {
+    static BUILDS: AtomicUsize = AtomicUsize::new(0);
+
+    let dir = std::env::temp_dir().join(format!(
+        "trng-fuzz-{}-{}",
+        std::process::id(),
+        BUILDS.fetch_add(1, Ordering::Relaxed)
+    ));
+    std::fs::create_dir_all(&dir).ok()?;
+
+    let source = dir.join("main.c");
+    let binary = dir.join("main");
+    let output = std::fs::write(&source, to_c(program, &COptions::default()))
+        .ok()
+        .and_then(|_| {
+            Command::new("cc")
+                .arg("-o")
+                .arg(&binary)
+                .arg(&source)
+                .arg("-lm")
+                .stderr(Stdio::null())
+                .status()
+                .ok()
+        })
+        .filter(|status| status.success())
+        .and_then(|_| Command::new(&binary).stdin(Stdio::null()).output().ok());
+    std::fs::remove_dir_all(&dir).ok();
+    let output = output?;
+
+    let error = (!output.status.success()).then(|| {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        match stderr.trim_end() {
+            "" => output.status.to_string(),
+            message => message.to_string(),
+        }
+    });
+
+    Some(Outcome {
+        output: output.stdout,
+        error,
+    })
+}
+
+/// Compiles a program to WebAssembly and runs it with wasmi without input.
+/// Returns `None` if the program cannot be compiled. Traps are reported with their error code.
+/// # Arguments
+/// * `program` - The program to run.
+#[cfg(feature = "wasm")]
+fn run_wat(program: &Program) -> Option<Outcome> {
+    use wasmi::{Caller, Engine, Linker, Module, Store};
+
+    let wasm = wat::parse_str(to_wat(program, &WatOptions::default()).ok()?).ok()?;
+    let engine = Engine::default();
+    let module = Module::new(&engine, &wasm[..]).ok()?;
+    let mut store = Store::new(&engine, Vec::<u8>::new());
+
+    let mut linker = <Linker<Vec<u8>>>::new(&engine);
+    linker
+        .func_wrap("env", "read_byte", || -1)
+        .ok()?
+        .func_wrap(
+            "env",
+            "write_byte",
+            |mut caller: Caller<'_, Vec<u8>>, b: i32| {
+                caller.data_mut().push(b as u8);
+            },
+        )
+        .ok()?
+        .func_wrap(
+            "env",
+            "write_str",
+            |mut caller: Caller<'_, Vec<u8>>, ptr: i32, len: i32| {
+                let Some(memory) = caller.get_export("memory").and_then(|e| e.into_memory()) else {
+                    return;
+                };
+                let bytes = memory.data(&caller)[ptr as usize..(ptr + len) as usize].to_vec();
+                caller.data_mut().extend(bytes);
+            },
+        )
+        .ok()?;
+
+    let instance = linker
+        .instantiate(&mut store, &module)
+        .ok()?
+        .start(&mut store)
+        .ok()?;
+    let run = instance.get_typed_func::<(), ()>(&store, "run").ok()?;
+
+    let trapped = run.call(&mut store, ()).is_err();
+    let code = instance
+        .get_global(&store, "error")
+        .and_then(|g| g.get(&store).i32())
+        .unwrap_or(0);
+
+    Some(Outcome {
+        output: std::mem::take(store.data_mut()),
+        error: trapped.then(|| format!("trap with error code {}", code)),
+    })
+}
+
+/// Two engines that disagree about a program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    /// The engine that disagrees with the reference.
+    pub engine: Engine,
+    /// The index of the first output byte that differs. `None` if only the errors differ.
+    pub offset: Option<usize>,
+    /// The outcome of the reference engine.
+    pub expected: Outcome,
+    /// The outcome of the disagreeing engine.
+    pub actual: Outcome,
+}
+
+impl std::fmt::Display for Divergence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let none = "none".to_string();
+        match self.offset {
+            Some(offset) => write!(
+                f,
+                "{} differs at output byte {}: expected {:?}, found {:?}",
+                self.engine,
+                offset,
+                self.expected.output.get(offset),
+                self.actual.output.get(offset)
+            ),
+            None => write!(
+                f,
+                "{} differs in the error: expected {}, found {}",
+                self.engine,
+                self.expected.error.as_ref().unwrap_or(&none),
+                self.actual.error.as_ref().unwrap_or(&none)
+            ),
+        }
+    }
+}
+
+/// Runs a program with all engines and returns the first one that disagrees with the reference.
+/// Programs that do not finish within the fuel of the reference are not compared.
+/// # Arguments
+/// * `program` - The program to run.
+pub fn differential(program: &Program) -> Option<Divergence> {
+    compare(program, &Engine::all())
+}
+
+/// Runs a program with the given engines and returns the first one that disagrees with the first.
+/// # Arguments
+/// * `program` - The program to run.
+/// * `engines` - The engines to run. The first one is the reference.
+fn compare(program: &Program, engines: &[Engine]) -> Option<Divergence> {
+    let expected = engines[0].run(program)?;
+    let exhausted = InterpreterErrorType::BudgetExhausted.to_string();
+    if expected
+        .error
+        .as_ref()
+        .is_some_and(|e| e.contains(&exhausted))
+    {
+        return None;
+    }
+
+    for engine in &engines[1..] {
+        // Lowered programs only produce the same output, not the same errors.
+        if engine.lowered() && expected.error.is_some() {
+            continue;
+        }
+
+        let Some(actual) = engine.run(program) else {
+            continue;
+        };
+
+        if actual != expected {
+            let offset = expected
+                .output
+                .iter()
+                .zip(&actual.output)
+                .position(|(a, b)| a != b)
+                .or_else(|| {
+                    (expected.output.len() != actual.output.len())
+                        .then(|| expected.output.len().min(actual.output.len()))
+                });
+
+            return Some(Divergence {
+                engine: *engine,
+                offset,
+                expected,
+                actual,
+            });
+        }
+    }
+
+    None
+}
+
+/// Removes instructions from a program as long as it still fails the check.
+/// Ranges are only removed if they contain complete loops. Loops are also unwrapped by
+/// removing their start and their `pol` but keeping the body.
+/// # Arguments
+/// * `program` - The failing program.
+/// * `fails` - Returns whether a program still shows the failure.
+pub fn shrink<F>(program: &Program, mut fails: F) -> Program
+where
+    F: FnMut(&Program) -> bool,
+{
+    let mut code = program.instructions.clone();
+
+    loop {
+        let before = code.len();
+
+        let mut chunk = code.len();
+        while chunk > 0 {
+            let mut start = 0;
+            while start < code.len() {
+                let end = (start + chunk).min(code.len());
+                let mut candidate = code[..start].to_vec();
+                candidate.extend_from_slice(&code[end..]);
+
+                if balanced(&code[start..end]) && fails(&build(&candidate)) {
+                    code = candidate;
+                } else {
+                    start += chunk;
+                }
+            }
+            chunk /= 2;
+        }
+
+        let mut start = 0;
+        while start < code.len() {
+            let candidate = match matching_pol(&code, start) {
+                Some(end) => {
+                    let mut candidate = code.clone();
+                    candidate.remove(end);
+                    candidate.remove(start);
+                    candidate
+                }
+                None => {
+                    start += 1;
+                    continue;
+                }
+            };
+
+            if fails(&build(&candidate)) {
+                code = candidate;
+            } else {
+                start += 1;
+            }
+        }
+
+        if code.len() == before {
+            return build(&code);
+        }
+    }
+}
+
+/// Returns the index of the `pol` closing the loop that starts at `start`.
+fn matching_pol(code: &[Instruction], start: usize) -> Option<usize> {
+    if !matches!(code[start], Instruction::Lop(_) | Instruction::Whl(_)) {
+        return None;
+    }
+
+    let mut depth = 0;
+    for (i, instruction) in code.iter().enumerate().skip(start) {
+        match instruction {
+            Instruction::Lop(_) | Instruction::Whl(_) => depth += 1,
+            Instruction::Pol(_) => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => (),
+        }
+    }
+    None
+}
+
+/// Returns whether the instructions close every loop they open and open every loop they close.
+fn balanced(code: &[Instruction]) -> bool {
+    let mut depth = 0usize;
+    for instruction in code {
+        match instruction {
+            Instruction::Lop(_) | Instruction::Whl(_) => depth += 1,
+            Instruction::Pol(_) => match depth.checked_sub(1) {
+                Some(d) => depth = d,
+                None => return false,
+            },
+            _ => (),
+        }
+    }
+    depth == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::program::Instruction;
+
+    #[test]
+    fn generate_is_deterministic_test() {
+        let first = super::Generator::new(7).generate(60);
+        let second = super::Generator::new(7).generate(60);
+
+        assert_eq!(first, second);
+        assert!(first.len() >= 60);
+    }
+
+    #[test]
+    fn generated_programs_are_well_formed_test() {
+        for seed in 0..20 {
+            let program = super::Generator::new(seed).generate(80);
+
+            assert!(super::balanced(&program.instructions));
+            assert!(super::run(&program).is_some(), "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn engines_agree_on_generated_programs_test() {
+        for seed in 0..20 {
+            let program = super::Generator::new(seed).generate(80);
+
+            if let Some(divergence) = super::differential(&program) {
+                panic!(
+                    "seed {}: {}\n{}",
+                    seed,
+                    divergence,
+                    crate::listing(&program)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn shrink_keeps_failure_test() {
+        let program = super::Generator::new(3).generate(80);
+        let fails = |p: &crate::Program| p.instructions.contains(&Instruction::Wrt);
+        assert!(fails(&program));
+
+        let shrunk = super::shrink(&program, fails);
+
+        assert_eq!(shrunk.instructions, vec![Instruction::Wrt]);
+    }
+
+    #[test]
+    fn shrink_keeps_loops_balanced_test() {
+        let program = crate::Program::parse("inc 2 lop wrt dec 1 pol wrt".as_bytes()).unwrap();
+
+        let shrunk = super::shrink(&program, |p| {
+            p.instructions
+                .iter()
+                .any(|i| matches!(i, Instruction::Lop(_)))
+        });
+
+        assert_eq!(
+            shrunk.instructions,
+            vec![Instruction::Lop(1), Instruction::Pol(0)]
+        );
+    }
+
+    #[test]
+    fn differential_reports_first_divergent_byte_test() {
+        // Lowered to Brainfuck, `rda` leaves 0 in the current cell at the end of the input.
+        let program = crate::Program::parse("inc 66 wrt dec 1 rda wrt".as_bytes()).unwrap();
+        let engines = [super::Engine::Interpreter, super::Engine::Brainfuck];
+
+        let divergence = super::compare(&program, &engines).unwrap();
+
+        assert_eq!(divergence.engine, super::Engine::Brainfuck);
+        assert_eq!(divergence.offset, Some(1));
+        assert_eq!(divergence.expected.output, b"BA");
+        assert_eq!(divergence.actual.output, b"B\0");
+        assert_eq!(
+            divergence.to_string(),
+            "brainfuck differs at output byte 1: expected Some(65), found Some(0)"
+        );
+    }
+
+    #[test]
+    fn all_engines_include_available_backends_test() {
+        let program = crate::Program::parse("inc 65 wrt dec 66".as_bytes()).unwrap();
+        let expected = super::Engine::Interpreter.run(&program).unwrap();
+
+        let engines = super::Engine::all();
+
+        assert_eq!(engines.contains(&super::Engine::C), super::cc_available());
+        if super::cc_available() {
+            assert_eq!(super::Engine::C.run(&program), Some(expected.clone()));
+        }
+        #[cfg(feature = "wasm")]
+        {
+            assert!(engines.contains(&super::Engine::Wat));
+            let actual = super::Engine::Wat.run(&program).unwrap();
+            assert_eq!(actual.output, expected.output);
+            assert_eq!(actual.error.as_deref(), Some("trap with error code 2"));
+        }
+    }
+}
@@ -0,0 +1,666 @@
+// TRNG - Brainfucks pretty sister.
+// Copyright (C) 2023 Lukas Pfeifer
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::buffer::SharedBuffer;
+use crate::interpreter::{Interpreter, InterpreterError, InterpreterErrorType};
+use std::path::{Path, PathBuf};
+
+/// The number of unchanged lines shown around every change of a diff.
+const CONTEXT: usize = 3;
+
+/// The file extensions of programs the runner picks up.
+const EXTENSIONS: [&str; 3] = ["trng", "bf", "b"];
+
+/// A program together with its input and the expected result of running it.
+///
+/// Expectations are read from sibling files with the same name: `.in` holds the input,
+/// `.out` the expected output and `.err` the expected error kind, e.g. `Tape error`.
+/// TRNG files can state them in comments at the top of the file instead:
+///
+/// ```text
+/// ; input: 5
+/// ; expect: Hello\r\n
+/// ; expect-error: Tape error
+/// ```
+///
+/// Header lines may start with `//` instead of `;`, e.g. `// expect: Hello`. TRNG has no `//`
+/// comments, so these lines are removed before the program runs.
+///
+/// Header values support the escapes `\n`, `\r`, `\t`, `\0`, `\\` and `\xNN`. Several
+/// `expect:` lines are joined. Sibling files take precedence over headers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GoldenTest {
+    /// The path of the program.
+    pub path: PathBuf,
+    /// The bytes the program reads.
+    pub input: Vec<u8>,
+    /// The bytes the program is expected to write.
+    pub expected_output: Vec<u8>,
+    /// The expected error kind or `None` if the program is expected to succeed.
+    pub expected_error: Option<String>,
+}
+
+/// The result of running a `GoldenTest`.
+#[derive(Debug)]
+pub struct GoldenOutcome {
+    /// The path of the program.
+    pub path: PathBuf,
+    /// The bytes the program wrote.
+    pub output: Vec<u8>,
+    /// The error the run ended with, as `Display` of its `InterpreterErrorType`.
+    pub error: Option<String>,
+    /// A unified diff of the expected and the actual result or `None` if they are equal.
+    pub diff: Option<String>,
+}
+
+impl GoldenOutcome {
+    /// Returns whether the program behaved as expected.
+    pub fn passed(&self) -> bool {
+        self.diff.is_none()
+    }
+}
+
+/// Expectations read from the comment header of a TRNG file.
+#[derive(Default)]
+struct Header {
+    input: Option<Vec<u8>>,
+    output: Option<Vec<u8>>,
+    error: Option<String>,
+}
+
+impl GoldenTest {
+    /// Loads a program and its expectations.
+    /// A program without any expectations is expected to succeed without output.
+    /// # Arguments
+    /// * `path` - Path of the program.
+    pub fn from_file<P>(path: P) -> std::io::Result<GoldenTest>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let header = if is_trng(path) {
+            parse_header(&std::fs::read_to_string(path)?)
+        } else {
+            Header::default()
+        };
+
+        let input = read_sibling(path, "in")?.or(header.input);
+        let output = read_sibling(path, "out")?.or(header.output);
+        let error = read_sibling(path, "err")?
+            .map(|e| String::from_utf8_lossy(&e).trim().to_string())
+            .or(header.error);
+
+        Ok(GoldenTest {
+            path: path.to_path_buf(),
+            input: input.unwrap_or_default(),
+            expected_output: output.unwrap_or_default(),
+            expected_error: error,
+        })
+    }
+
+    /// Runs the program with its input and compares output and error kind with the expectations.
+    pub fn run(&self) -> GoldenOutcome {
+        let output = SharedBuffer::new();
+
+        let mut interpreter = Interpreter::default();
+        interpreter.set_output(output.clone());
+        interpreter.set_input(std::io::Cursor::new(self.input.clone()));
+
+        let result = if is_trng(&self.path) {
+            match std::fs::read_to_string(&self.path) {
+                Ok(source) => interpreter.run(strip_header(&source).as_bytes()),
+                Err(e) => Err(InterpreterError::new(
+                    InterpreterErrorType::Io,
+                    e.to_string(),
+                )),
+            }
+        } else {
+            interpreter.run_file(&self.path)
+        };
+        let error = result.err().map(|e| e._type.to_string());
+        let output = output.take();
+
+        let mut diff = String::new();
+        if output != self.expected_output {
+            diff.push_str(&unified_diff(&self.expected_output, &output));
+        }
+        if error != self.expected_error {
+            diff.push_str(&format!(
+                "-error: {}\n+error: {}\n",
+                self.expected_error.as_deref().unwrap_or("none"),
+                error.as_deref().unwrap_or("none")
+            ));
+        }
+
+        GoldenOutcome {
+            path: self.path.clone(),
+            output,
+            error,
+            diff: if diff.is_empty() { None } else { Some(diff) },
+        }
+    }
+}
+
+/// Finds all programs below a directory that have expectations, sorted by path.
+/// Programs with neither sibling `.out` or `.err` files nor an `expect` header are skipped.
+/// # Arguments
+/// * `dir` - The directory to search.
+pub fn discover<P>(dir: P) -> std::io::Result<Vec<GoldenTest>>
+where
+    P: AsRef<Path>,
+{
+    let mut paths = vec![];
+    collect_programs(dir.as_ref(), &mut paths)?;
+    paths.sort();
+
+    let mut tests = vec![];
+    for path in paths {
+        let has_files = ["out", "err"]
+            .iter()
+            .any(|e| path.with_extension(e).is_file());
+        let has_header = is_trng(&path) && {
+            let header = parse_header(&std::fs::read_to_string(&path)?);
+            header.output.is_some() || header.error.is_some()
+        };
+
+        if has_files || has_header {
+            tests.push(GoldenTest::from_file(&path)?);
+        }
+    }
+    Ok(tests)
+}
+
+/// Adds the paths of all programs below a directory.
+fn collect_programs(dir: &Path, paths: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_programs(&path, paths)?;
+        } else if path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| EXTENSIONS.contains(&e))
+        {
+            paths.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn is_trng(path: &Path) -> bool {
+    !matches!(
+        path.extension().and_then(|e| e.to_str()),
+        Some("bf") | Some("b")
+    )
+}
+
+/// Reads the file next to `path` with the given extension, if it exists.
+fn read_sibling(path: &Path, extension: &str) -> std::io::Result<Option<Vec<u8>>> {
+    match std::fs::read(path.with_extension(extension)) {
+        Ok(bytes) => Ok(Some(bytes)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Reads the expectations from the comments at the top of TRNG code.
+fn parse_header(source: &str) -> Header {
+    let mut header = Header::default();
+
+    for line in source.lines().map(str::trim) {
+        if line.is_empty() {
+            continue;
+        }
+        let Some(comment) = line.strip_prefix("//").or_else(|| line.strip_prefix(';')) else {
+            break;
+        };
+        let Some((key, value)) = comment.split_once(':') else {
+            continue;
+        };
+        let value = value.strip_prefix(' ').unwrap_or(value);
+
+        match key.trim() {
+            "input" => header
+                .input
+                .get_or_insert_with(Vec::new)
+                .extend(unescape(value)),
+            "expect" => header
+                .output
+                .get_or_insert_with(Vec::new)
+                .extend(unescape(value)),
+            "expect-error" => header.error = Some(value.trim().to_string()),
+            _ => {}
+        }
+    }
+
+    header
+}
+
+/// Blanks the header lines starting with `//`, keeping the line numbers of the code.
+fn strip_header(source: &str) -> String {
+    let mut in_header = true;
+    let mut stripped = String::with_capacity(source.len());
+
+    for line in source.split_inclusive('\n') {
+        let trimmed = line.trim();
+        in_header &= trimmed.is_empty() || trimmed.starts_with("//") || trimmed.starts_with(';');
+
+        if in_header && trimmed.starts_with("//") {
+            stripped.push('\n');
+        } else {
+            stripped.push_str(line);
+        }
+    }
+
+    stripped
+}
+
+/// Replaces the escapes of a header value with the bytes they stand for.
+fn unescape(value: &str) -> Vec<u8> {
+    let mut bytes = vec![];
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buf = [0; 4];
+            bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            continue;
+        }
+
+        match chars.next() {
+            Some('n') => bytes.push(b'\n'),
+            Some('r') => bytes.push(b'\r'),
+            Some('t') => bytes.push(b'\t'),
+            Some('0') => bytes.push(0),
+            Some('x') => {
+                let hex: String = chars.by_ref().take(2).collect();
+                match u8::from_str_radix(&hex, 16) {
+                    Ok(byte) => bytes.push(byte),
+                    Err(_) => bytes.extend_from_slice(format!("\\x{}", hex).as_bytes()),
+                }
+            }
+            Some(other) => {
+                let mut buf = [0; 4];
+                bytes.extend_from_slice(other.encode_utf8(&mut buf).as_bytes());
+            }
+            None => bytes.push(b'\\'),
+        }
+    }
+
+    bytes
+}
+
+/// A line of a diff.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Change {
+    Keep,
+    Remove,
+    Add,
+}
+
+/// A step of an edit script with the index of the expected and the actual line it is at.
+#[derive(Clone, Copy)]
+struct Edit {
+    change: Change,
+    old: usize,
+    new: usize,
+}
+
+/// Creates a unified diff of the lines of two outputs.
+/// Control characters are escaped so that the diff can be printed to a terminal.
+/// # Arguments
+/// * `expected` - The expected output.
+/// * `actual` - The actual output.
+pub fn unified_diff(expected: &[u8], actual: &[u8]) -> String {
+    let old: Vec<&[u8]> = expected.split_inclusive(|b| *b == b'\n').collect();
+    let new: Vec<&[u8]> = actual.split_inclusive(|b| *b == b'\n').collect();
+    let edits = edit_script(&old, &new);
+
+    let changes: Vec<usize> = edits
+        .iter()
+        .enumerate()
+        .filter(|(_, e)| e.change != Change::Keep)
+        .map(|(i, _)| i)
+        .collect();
+
+    let mut diff = String::from("--- expected\n+++ actual\n");
+    let mut k = 0;
+    while k < changes.len() {
+        let start = changes[k].saturating_sub(CONTEXT);
+        let mut last = changes[k];
+        while k + 1 < changes.len() && changes[k + 1] - last <= 2 * CONTEXT + 1 {
+            k += 1;
+            last = changes[k];
+        }
+        k += 1;
+        let hunk = &edits[start..(last + CONTEXT + 1).min(edits.len())];
+
+        let old_len = hunk.iter().filter(|e| e.change != Change::Add).count();
+        let new_len = hunk.iter().filter(|e| e.change != Change::Remove).count();
+        diff.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            range(hunk[0].old, old_len),
+            range(hunk[0].new, new_len)
+        ));
+
+        for edit in hunk {
+            let (prefix, line) = match edit.change {
+                Change::Keep => (' ', old[edit.old]),
+                Change::Remove => ('-', old[edit.old]),
+                Change::Add => ('+', new[edit.new]),
+            };
+            diff.push(prefix);
+            diff.push_str(&escape(line.strip_suffix(b"\n").unwrap_or(line)));
+            diff.push('\n');
+            if !line.ends_with(b"\n") {
+                diff.push_str("\\ No newline at end of file\n");
+            }
+        }
+    }
+
+    diff
+}
+
+/// Formats the line range of a hunk header.
+fn range(start: usize, len: usize) -> String {
+    match len {
+        0 => format!("{},0", start),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{}", start + 1, len),
+    }
+}
+
+/// Finds a shortest edit script turning `old` into `new` with the linear space variant of Myers'
+/// algorithm. Removed lines are listed before the added lines that replace them.
+fn edit_script(old: &[&[u8]], new: &[&[u8]]) -> Vec<Edit> {
+    let mut changes = Vec::with_capacity(old.len() + new.len());
+    let mut diagonals = Diagonals::new(old.len() + new.len());
+    diff(old, new, &mut diagonals, &mut changes);
+
+    let mut edits = Vec::with_capacity(changes.len());
+    let (mut i, mut j) = (0, 0);
+    let mut k = 0;
+    while k < changes.len() {
+        if changes[k] == Change::Keep {
+            edits.push(Edit {
+                change: Change::Keep,
+                old: i,
+                new: j,
+            });
+            i += 1;
+            j += 1;
+            k += 1;
+            continue;
+        }
+
+        let end = changes[k..]
+            .iter()
+            .position(|c| *c == Change::Keep)
+            .map_or(changes.len(), |p| k + p);
+        let removed = changes[k..end]
+            .iter()
+            .filter(|c| **c == Change::Remove)
+            .count();
+        for _ in 0..removed {
+            edits.push(Edit {
+                change: Change::Remove,
+                old: i,
+                new: j,
+            });
+            i += 1;
+        }
+        for _ in removed..end - k {
+            edits.push(Edit {
+                change: Change::Add,
+                old: i,
+                new: j,
+            });
+            j += 1;
+        }
+        k = end;
+    }
+
+    edits
+}
+
+/// The furthest reaching x of every diagonal, for the forward and the backward search.
+struct Diagonals {
+    forward: Vec<usize>,
+    backward: Vec<usize>,
+}
+
+impl Diagonals {
+    /// Creates diagonals for sequences with up to `len` lines in total.
+    fn new(len: usize) -> Diagonals {
+        let size = 2 * (len / 2 + 2) + 1;
+        Diagonals {
+            forward: vec![0; size],
+            backward: vec![0; size],
+        }
+    }
+}
+
+/// Appends the changes turning `old` into `new` by splitting both at the middle of a shortest
+/// edit script.
+fn diff(old: &[&[u8]], new: &[&[u8]], diagonals: &mut Diagonals, changes: &mut Vec<Change>) {
+    let prefix = old.iter().zip(new).take_while(|(o, n)| o == n).count();
+    let (old, new) = (&old[prefix..], &new[prefix..]);
+    let suffix = old
+        .iter()
+        .rev()
+        .zip(new.iter().rev())
+        .take_while(|(o, n)| o == n)
+        .count();
+    let (old, new) = (&old[..old.len() - suffix], &new[..new.len() - suffix]);
+
+    changes.extend(std::iter::repeat_n(Change::Keep, prefix));
+    if old.is_empty() || new.is_empty() {
+        changes.extend(std::iter::repeat_n(Change::Remove, old.len()));
+        changes.extend(std::iter::repeat_n(Change::Add, new.len()));
+    } else {
+        let (x, y) = middle_snake(old, new, diagonals);
+        diff(&old[..x], &new[..y], diagonals, changes);
+        diff(&old[x..], &new[y..], diagonals, changes);
+    }
+    changes.extend(std::iter::repeat_n(Change::Keep, suffix));
+}
+
+/// Finds the start of the snake in the middle of a shortest edit script by searching from both
+/// ends at once. `old` and `new` must not be empty and must differ in their first and last lines.
+fn middle_snake(old: &[&[u8]], new: &[&[u8]], diagonals: &mut Diagonals) -> (usize, usize) {
+    let (n, m) = (old.len(), new.len());
+    let delta = n as isize - m as isize;
+    let odd = delta % 2 != 0;
+    let Diagonals { forward, backward } = diagonals;
+    let offset = (forward.len() / 2) as isize;
+    let index = |k: isize| (k + offset) as usize;
+    forward[index(1)] = 0;
+    backward[index(1)] = 0;
+
+    for d in 0..=(n + m).div_ceil(2) as isize {
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && forward[index(k - 1)] < forward[index(k + 1)]) {
+                forward[index(k + 1)]
+            } else {
+                forward[index(k - 1)] + 1
+            };
+            let (x0, y0) = (x, (x as isize - k) as usize);
+            let mut y = y0;
+            while x < n && y < m && old[x] == new[y] {
+                x += 1;
+                y += 1;
+            }
+            forward[index(k)] = x;
+            if odd && (k - delta).abs() < d && x + backward[index(delta - k)] >= n {
+                return (x0, y0);
+            }
+        }
+
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && backward[index(k - 1)] < backward[index(k + 1)]) {
+                backward[index(k + 1)]
+            } else {
+                backward[index(k - 1)] + 1
+            };
+            let mut y = (x as isize - k) as usize;
+            while x < n && y < m && old[n - 1 - x] == new[m - 1 - y] {
+                x += 1;
+                y += 1;
+            }
+            backward[index(k)] = x;
+            if !odd && (k - delta).abs() <= d && x + forward[index(delta - k)] >= n {
+                return (n - x, m - y);
+            }
+        }
+    }
+
+    unreachable!("the searches meet after at most (n + m + 1) / 2 steps")
+}
+
+/// Escapes all control characters but tabs.
+fn escape(line: &[u8]) -> String {
+    String::from_utf8_lossy(line)
+        .chars()
+        .map(|c| {
+            if c.is_control() && c != '\t' {
+                c.escape_default().to_string()
+            } else {
+                c.to_string()
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    fn write_program(name: &str, code: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("trng-golden-{}-{}", std::process::id(), name));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(format!("{}.trng", name));
+        std::fs::write(&path, code).unwrap();
+        path
+    }
+
+    #[test]
+    fn header_expectations_test() {
+        let path = write_program(
+            "header",
+            "; input: A\n; expect: A\\r\n; expect: \\n\nrdi\nwrt\nclr\ninc 13\nwrt\ndec 3\nwrt\n",
+        );
+
+        let test = super::GoldenTest::from_file(&path).unwrap();
+        assert_eq!(test.input, b"A");
+        assert_eq!(test.expected_output, b"A\r\n");
+        assert_eq!(test.expected_error, None);
+        assert!(test.run().passed());
+
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn slash_header_expectations_test() {
+        let path = write_program(
+            "slash",
+            "// input: A\n; expect: A\n// expect-error: Tape error\nrdi\nwrt\npbw 1\n",
+        );
+
+        let test = super::GoldenTest::from_file(&path).unwrap();
+        assert_eq!(test.input, b"A");
+        assert_eq!(test.expected_output, b"A");
+        assert_eq!(test.expected_error.as_deref(), Some("Tape error"));
+        assert!(test.run().passed());
+
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn sibling_files_take_precedence_test() {
+        let path = write_program("sibling", "; expect: wrong\npbw 1\n");
+        std::fs::write(path.with_extension("err"), "Tape error\n").unwrap();
+
+        let tests = super::discover(path.parent().unwrap()).unwrap();
+        assert_eq!(tests.len(), 1);
+        assert_eq!(tests[0].expected_output, b"wrong");
+        assert_eq!(tests[0].expected_error.as_deref(), Some("Tape error"));
+
+        let outcome = tests[0].run();
+        assert!(!outcome.passed());
+        assert_eq!(outcome.error.as_deref(), Some("Tape error"));
+        assert_eq!(
+            outcome.diff.unwrap(),
+            "--- expected\n+++ actual\n@@ -1 +0,0 @@\n-wrong\n\\ No newline at end of file\n"
+        );
+
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn discover_skips_programs_without_expectations_test() {
+        let path = write_program("skip", "; a comment\ninc 1\n");
+
+        assert!(super::discover(path.parent().unwrap()).unwrap().is_empty());
+
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn error_kind_mismatch_test() {
+        let path = write_program("kind", "; expect-error: Stack error\npbw 1\n");
+
+        let outcome = super::GoldenTest::from_file(&path).unwrap().run();
+        assert_eq!(
+            outcome.diff.unwrap(),
+            "-error: Stack error\n+error: Tape error\n"
+        );
+
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn unified_diff_test() {
+        let expected = b"1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n11\n12\n";
+        let actual = b"1\n2\nthree\n4\n5\n6\n7\n8\n9\n10\n11\n12\r\n13\n";
+
+        assert_eq!(
+            super::unified_diff(expected, actual),
+            "--- expected\n+++ actual\n\
+             @@ -1,6 +1,6 @@\n 1\n 2\n-3\n+three\n 4\n 5\n 6\n\
+             @@ -9,4 +9,5 @@\n 9\n 10\n 11\n-12\n+12\\r\n+13\n"
+        );
+    }
+
+    #[test]
+    fn unified_diff_equal_test() {
+        assert_eq!(
+            super::unified_diff(b"a\n", b"a\n"),
+            "--- expected\n+++ actual\n"
+        );
+    }
+
+    #[test]
+    fn unified_diff_long_output_test() {
+        let expected = "line\n".repeat(200_000);
+        let actual = expected.replacen("line\n", "changed\n", 1);
+
+        assert_eq!(
+            super::unified_diff(expected.as_bytes(), actual.as_bytes()),
+            "--- expected\n+++ actual\n\
+             @@ -1,4 +1,4 @@\n-line\n+changed\n line\n line\n line\n"
+        );
+    }
+}
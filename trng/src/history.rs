@@ -0,0 +1,336 @@
+// TRNG - Brainfucks pretty sister.
+// Copyright (C) 2023 Lukas Pfeifer
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{program::Instruction, snapshot::Snapshot, stack::ValueStack, tape::Tape};
+use std::collections::VecDeque;
+use std::mem::size_of;
+
+/// The state that a single instruction changed, recorded before it was executed.
+pub(crate) struct Entry {
+    /// The index of the executed instruction.
+    pub(crate) instruction_index: usize,
+    /// The number of instructions executed before it.
+    pub(crate) instruction_count: u64,
+    ptr_index: usize,
+    bytes_read: u64,
+    bytes_written: u64,
+    /// The index and the previous value of every cell the instruction wrote.
+    cells: Vec<(usize, u8)>,
+    /// The length and the top of the bookmark stack.
+    bookmarks: (usize, Option<usize>),
+    /// The length and the top of the loop stack.
+    loop_stack: (usize, Option<usize>),
+    /// The length of the value stack.
+    stack_len: usize,
+    /// The values on top of the value stack if the instruction removes or reorders them.
+    stack_top: Vec<Vec<u8>>,
+}
+
+impl Entry {
+    /// Returns the number of bytes the entry occupies.
+    fn size(&self) -> usize {
+        size_of::<Entry>()
+            + self.cells.capacity() * size_of::<(usize, u8)>()
+            + self
+                .stack_top
+                .iter()
+                .map(|v| size_of::<Vec<u8>>() + v.len())
+                .sum::<usize>()
+    }
+
+    /// Reverts the changes of the instruction.
+    /// # Arguments
+    /// * `tape` - The tape the instruction was executed on.
+    /// * `loop_stack` - The loop stack of the interpreter.
+    /// * `stack` - The value stack the instruction was executed on.
+    pub(crate) fn undo(self, tape: &mut Tape, loop_stack: &mut Vec<usize>, stack: &mut ValueStack) {
+        for (index, value) in self.cells.into_iter().rev() {
+            tape.data[index] = value;
+        }
+        tape.ptr_index = self.ptr_index;
+        tape.bytes_read = self.bytes_read;
+        tape.bytes_written = self.bytes_written;
+        restore_top(&mut tape.bookmarks, self.bookmarks);
+        restore_top(loop_stack, self.loop_stack);
+
+        stack.values.truncate(self.stack_len - self.stack_top.len());
+        stack.values.extend(self.stack_top);
+    }
+}
+
+/// Restores a stack of which an instruction changed at most the top entry.
+/// # Arguments
+/// * `values` - The stack to restore.
+/// * `(len, top)` - The length and the top entry before the instruction.
+fn restore_top(values: &mut Vec<usize>, (len, top): (usize, Option<usize>)) {
+    values.truncate(len.saturating_sub(1));
+    values.extend(top);
+}
+
+/// Records the changes of every executed instruction so that the execution can be reversed.
+///
+/// Set it with `Interpreter::set_history` and move back with `Interpreter::step_back`,
+/// `Interpreter::reverse_continue` and `Interpreter::goto`.
+/// The oldest records are dropped once the memory budget is exceeded.
+/// Optional checkpoints of the complete state make jumps far into the past cheaper.
+pub struct History {
+    /// The maximum number of bytes the records may occupy.
+    budget: usize,
+    /// The number of instructions between two checkpoints. `None` disables checkpoints.
+    checkpoint_interval: Option<u64>,
+    entries: VecDeque<Entry>,
+    checkpoints: VecDeque<Snapshot>,
+    /// The entry of the instruction that is executed at the moment.
+    pending: Option<Entry>,
+    /// The number of bytes the entries and the checkpoints occupy.
+    size: usize,
+}
+
+impl History {
+    /// Creates an empty history.
+    /// # Arguments
+    /// * `budget` - The maximum number of bytes the records may occupy.
+    pub fn new(budget: usize) -> Self {
+        Self {
+            budget,
+            checkpoint_interval: None,
+            entries: VecDeque::new(),
+            checkpoints: VecDeque::new(),
+            pending: None,
+            size: 0,
+        }
+    }
+
+    /// Sets the number of instructions between two checkpoints of the complete state.
+    /// Each checkpoint holds a copy of the tape and counts towards the memory budget.
+    /// # Arguments
+    /// * `interval` - The number of instructions. `None` disables checkpoints.
+    pub fn set_checkpoint_interval(&mut self, interval: Option<u64>) {
+        self.checkpoint_interval = interval.filter(|i| *i > 0);
+    }
+
+    /// Returns the number of recorded instructions.
+    pub fn len(&self) -> usize {
+        self.entries.len() + usize::from(self.pending.is_some())
+    }
+
+    /// Returns whether no instruction was recorded.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of bytes the records occupy.
+    pub fn memory_usage(&self) -> usize {
+        self.size + self.pending.as_ref().map_or(0, |e| e.size())
+    }
+
+    /// Returns the lowest instruction count the execution can be moved back to.
+    /// `None` if no instruction was recorded.
+    pub fn earliest(&self) -> Option<u64> {
+        self.entries
+            .front()
+            .or(self.pending.as_ref())
+            .map(|e| e.instruction_count)
+    }
+
+    /// Drops all records.
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+        self.checkpoints.clear();
+        self.pending = None;
+        self.size = 0;
+    }
+
+    /// Starts the record of an instruction that is about to be executed.
+    /// # Arguments
+    /// * `tape` - The tape the instruction is executed on.
+    /// * `instruction` - The instruction.
+    /// * `instruction_index` - The index of the instruction.
+    /// * `instruction_count` - The number of instructions executed before it.
+    /// * `loop_stack` - The loop stack of the interpreter.
+    /// * `stack` - The value stack the instruction is executed on.
+    pub(crate) fn begin(
+        &mut self,
+        tape: &mut Tape,
+        instruction: &Instruction,
+        instruction_index: usize,
+        instruction_count: u64,
+        loop_stack: &[usize],
+        stack: &ValueStack,
+    ) {
+        self.finish(tape);
+
+        let stack_top = match instruction {
+            Instruction::Pop | Instruction::Swp => {
+                let start = stack.values.len().saturating_sub(2);
+                stack.values[start..].to_vec()
+            }
+            _ => vec![],
+        };
+
+        self.pending = Some(Entry {
+            instruction_index,
+            instruction_count,
+            ptr_index: tape.ptr_index,
+            bytes_read: tape.bytes_read,
+            bytes_written: tape.bytes_written,
+            cells: vec![],
+            bookmarks: (tape.bookmarks.len(), tape.bookmarks.last().copied()),
+            loop_stack: (loop_stack.len(), loop_stack.last().copied()),
+            stack_len: stack.values.len(),
+            stack_top,
+        });
+    }
+
+    /// Completes the record of the last executed instruction with the cells it wrote.
+    /// # Arguments
+    /// * `tape` - The tape the instruction was executed on.
+    pub(crate) fn finish(&mut self, tape: &mut Tape) {
+        let Some(mut entry) = self.pending.take() else {
+            return;
+        };
+
+        if let Some(journal) = &mut tape.journal {
+            entry.cells = std::mem::take(journal);
+        }
+
+        self.size += entry.size();
+        self.entries.push_back(entry);
+
+        while self.size > self.budget {
+            match self.entries.pop_front() {
+                Some(e) => self.size -= e.size(),
+                None => break,
+            }
+        }
+
+        let earliest = self
+            .entries
+            .front()
+            .map_or(u64::MAX, |e| e.instruction_count);
+        while self
+            .checkpoints
+            .front()
+            .is_some_and(|c| c.instruction_count < earliest)
+        {
+            if let Some(c) = self.checkpoints.pop_front() {
+                self.size -= snapshot_size(&c);
+            }
+        }
+    }
+
+    /// Returns whether a checkpoint should be taken before the given instruction.
+    /// # Arguments
+    /// * `instruction_count` - The number of instructions executed before it.
+    pub(crate) fn wants_checkpoint(&self, instruction_count: u64) -> bool {
+        self.checkpoint_interval
+            .is_some_and(|i| instruction_count.is_multiple_of(i))
+    }
+
+    /// Adds a checkpoint of the complete state.
+    /// # Arguments
+    /// * `snapshot` - The state before the instruction that is executed next.
+    pub(crate) fn push_checkpoint(&mut self, snapshot: Snapshot) {
+        self.size += snapshot_size(&snapshot);
+        self.checkpoints.push_back(snapshot);
+    }
+
+    /// Removes the record of the last executed instruction and returns it.
+    /// # Arguments
+    /// * `tape` - The tape the instruction was executed on.
+    pub(crate) fn pop(&mut self, tape: &mut Tape) -> Option<Entry> {
+        self.finish(tape);
+
+        let entry = self.entries.pop_back()?;
+        self.size -= entry.size();
+        self.drop_checkpoints_after(entry.instruction_count);
+        Some(entry)
+    }
+
+    /// Removes the earliest checkpoint that is not before the given instruction count and not after the latest record
+    /// together with all later records. Returns the checkpoint.
+    /// # Arguments
+    /// * `instruction_count` - The instruction count to move back to.
+    pub(crate) fn rewind_to_checkpoint(&mut self, instruction_count: u64) -> Option<Snapshot> {
+        let latest = self.entries.back()?.instruction_count;
+        let checkpoint = self
+            .checkpoints
+            .iter()
+            .find(|c| c.instruction_count >= instruction_count && c.instruction_count <= latest)?
+            .clone();
+
+        while self
+            .entries
+            .back()
+            .is_some_and(|e| e.instruction_count >= checkpoint.instruction_count)
+        {
+            if let Some(e) = self.entries.pop_back() {
+                self.size -= e.size();
+            }
+        }
+        self.drop_checkpoints_after(checkpoint.instruction_count);
+
+        Some(checkpoint)
+    }
+
+    /// Drops the checkpoints that were taken after the given instruction count.
+    fn drop_checkpoints_after(&mut self, instruction_count: u64) {
+        while self
+            .checkpoints
+            .back()
+            .is_some_and(|c| c.instruction_count > instruction_count)
+        {
+            if let Some(c) = self.checkpoints.pop_back() {
+                self.size -= snapshot_size(&c);
+            }
+        }
+    }
+}
+
+/// Returns the number of bytes a checkpoint occupies.
+fn snapshot_size(snapshot: &Snapshot) -> usize {
+    size_of::<Snapshot>()
+        + snapshot.data.len()
+        + (snapshot.bookmarks.len() + snapshot.loop_stack.len()) * size_of::<usize>()
+        + snapshot
+            .stack
+            .iter()
+            .map(|v| size_of::<Vec<u8>>() + v.len())
+            .sum::<usize>()
+}
+
+#[cfg(test)]
+mod tests {
+
+    #[test]
+    fn restore_top_test() {
+        let mut pushed = vec![1, 2, 3];
+        super::restore_top(&mut pushed, (2, Some(2)));
+        assert_eq!(pushed, vec![1, 2]);
+
+        let mut popped = vec![1];
+        super::restore_top(&mut popped, (2, Some(2)));
+        assert_eq!(popped, vec![1, 2]);
+
+        let mut emptied = vec![];
+        super::restore_top(&mut emptied, (1, Some(7)));
+        assert_eq!(emptied, vec![7]);
+
+        let mut unchanged: Vec<usize> = vec![];
+        super::restore_top(&mut unchanged, (0, None));
+        assert!(unchanged.is_empty());
+    }
+}
@@ -14,24 +14,92 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+#[cfg(feature = "jit")]
+use crate::jit;
 use crate::{
-    lexer,
-    tape::{self, TapeError},
+    assertion::{self, AssertionError},
+    cancel::CancelToken,
+    coverage::Coverage,
+    history::History,
+    profile::Profiler,
+    program::{Instruction, ParseError, Position, Program},
+    snapshot::{Snapshot, SnapshotError, SnapshotErrorType},
+    stack::{self, StackError},
+    tape::{self, Num, TapeError},
+    trace::Tracer,
 };
-use std::io::Read;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::ops::Range;
+use std::path::Path;
+use std::time::Instant;
 
 /// Type alias for a simple result with an InterpreterError.
 pub type InterpreterResult<T> = Result<T, InterpreterError>;
 
+/// The number of executed instructions between two checks of the deadline and the cancel token.
+const POLL_INTERVAL: u64 = 1024;
+
+/// Defines error types for interpreter errors.
+#[derive(Debug, PartialEq, Eq)]
+pub enum InterpreterErrorType {
+    Syntax,
+    Tape,
+    Stack,
+    Io,
+    BudgetExhausted,
+    Cancelled,
+    Snapshot,
+    Assertion,
+}
+
+impl std::fmt::Display for InterpreterErrorType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InterpreterErrorType::Syntax => {
+                write!(f, "Syntax error")
+            }
+            InterpreterErrorType::Tape => {
+                write!(f, "Tape error")
+            }
+            InterpreterErrorType::Stack => {
+                write!(f, "Stack error")
+            }
+            InterpreterErrorType::Io => {
+                write!(f, "IO error")
+            }
+            InterpreterErrorType::BudgetExhausted => {
+                write!(f, "Budget exhausted")
+            }
+            InterpreterErrorType::Cancelled => {
+                write!(f, "Cancelled")
+            }
+            InterpreterErrorType::Snapshot => {
+                write!(f, "Snapshot error")
+            }
+            InterpreterErrorType::Assertion => {
+                write!(f, "Assertion error")
+            }
+        }
+    }
+}
+
 /// Defines an error that occurs during runtime.
 #[derive(Debug)]
 pub struct InterpreterError {
     pub description: String,
+    pub _type: InterpreterErrorType,
+    /// The failed assertion with its expected and actual value if the type is `Assertion`.
+    pub assertion: Option<Box<AssertionError>>,
 }
 
 impl std::fmt::Display for InterpreterError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Interpreter Error: {}", self.description)
+        write!(
+            f,
+            "Interpreter Error [{}]: {}",
+            self._type, self.description
+        )
     }
 }
 
@@ -39,8 +107,44 @@ impl std::error::Error for InterpreterError {}
 
 impl From<TapeError> for InterpreterError {
     fn from(value: TapeError) -> Self {
-        InterpreterError {
+        InterpreterError::new(InterpreterErrorType::Tape, value.to_string())
+    }
+}
+
+impl From<StackError> for InterpreterError {
+    fn from(value: StackError) -> Self {
+        InterpreterError::new(InterpreterErrorType::Stack, value.to_string())
+    }
+}
+
+impl From<ParseError> for InterpreterError {
+    fn from(value: ParseError) -> Self {
+        InterpreterError::new(InterpreterErrorType::Syntax, value.to_string())
+    }
+}
+
+impl From<SnapshotError> for InterpreterError {
+    fn from(value: SnapshotError) -> Self {
+        InterpreterError::new(InterpreterErrorType::Snapshot, value.to_string())
+    }
+}
+
+impl From<AssertionError> for InterpreterError {
+    fn from(value: AssertionError) -> Self {
+        Self {
             description: value.to_string(),
+            _type: InterpreterErrorType::Assertion,
+            assertion: Some(Box::new(value)),
+        }
+    }
+}
+
+impl InterpreterError {
+    pub fn new(_type: InterpreterErrorType, description: String) -> InterpreterError {
+        Self {
+            _type,
+            description,
+            assertion: None,
         }
     }
 }
@@ -54,6 +158,39 @@ pub struct Interpreter {
     instruction_index: usize,
 
     loop_stack: Vec<usize>,
+
+    /// The auxiliary value stack.
+    stack: stack::ValueStack,
+
+    /// The program that is currently executed.
+    program: Program,
+
+    /// The number of instructions executed since the program was started.
+    instruction_count: u64,
+
+    /// The number of instructions that may still be executed. `None` means unlimited.
+    fuel: Option<u64>,
+
+    /// The point in time after which the execution is stopped. `None` means no deadline.
+    deadline: Option<Instant>,
+
+    /// Token that is checked for cancellation requests from other threads.
+    cancel_token: Option<CancelToken>,
+
+    /// Records every executed instruction. `None` disables tracing.
+    tracer: Option<Tracer>,
+
+    /// Counts executed instructions and measures loops. `None` disables profiling.
+    profiler: Option<Profiler>,
+
+    /// Records executed lines and branches. `None` disables the coverage collection.
+    coverage: Option<Coverage>,
+
+    /// Records the changes of executed instructions to reverse them. `None` disables the recording.
+    history: Option<History>,
+
+    /// Whether assertions are removed from programs before they are executed.
+    release: bool,
 }
 
 impl Default for Interpreter {
@@ -62,6 +199,17 @@ impl Default for Interpreter {
             tape: tape::Tape::new(30000),
             instruction_index: 0,
             loop_stack: vec![],
+            stack: stack::ValueStack::default(),
+            program: Program::default(),
+            instruction_count: 0,
+            fuel: None,
+            deadline: None,
+            cancel_token: None,
+            tracer: None,
+            profiler: None,
+            coverage: None,
+            history: None,
+            release: false,
         }
     }
 }
@@ -75,333 +223,1524 @@ impl Interpreter {
             tape: tape::Tape::new(data_tape_sz),
             instruction_index: 0,
             loop_stack: vec![],
+            stack: stack::ValueStack::default(),
+            program: Program::default(),
+            instruction_count: 0,
+            fuel: None,
+            deadline: None,
+            cancel_token: None,
+            tracer: None,
+            profiler: None,
+            coverage: None,
+            history: None,
+            release: false,
         }
     }
 
-    /// Resets the interpreter. This resets the tape and sets all indizes to 0.
+    /// Resets the interpreter. This resets the tape and the value stack and sets all indizes to 0.
     pub fn reset(&mut self) {
         self.tape.reset();
+        self.stack.reset();
         self.instruction_index = 0;
+        self.instruction_count = 0;
         self.loop_stack = vec![];
+        self.clear_history();
     }
 
-    /// Returns a reference to the data that is stored on the current tape.
-    pub fn get_data(&self) -> &Vec<u8> {
-        &self.tape.data
+    /// Sets the number of instructions the interpreter may execute before it stops with a
+    /// `BudgetExhausted` error.
+    /// # Arguments
+    /// * `fuel` - The instruction budget. `None` removes the limit.
+    pub fn set_fuel(&mut self, fuel: Option<u64>) {
+        self.fuel = fuel;
     }
 
-    /// Starts the interpreter.
+    /// Adds instructions to the current budget. Does nothing if the budget is unlimited.
     /// # Arguments
-    /// * `read_from` - Source for TRNG code. Can be anything that implements the `Read` trait.
-    pub fn run<T>(&mut self, read_from: T) -> InterpreterResult<()>
-    where
-        T: Read,
-    {
-        let mut lexer = lexer::Lexer::new();
+    /// * `fuel` - The number of instructions to add.
+    pub fn add_fuel(&mut self, fuel: u64) {
+        if let Some(f) = self.fuel {
+            self.fuel = Some(f.saturating_add(fuel));
+        }
+    }
 
-        let lex_result = lexer.tokenize(read_from);
+    /// Returns the number of instructions that may still be executed. `None` means unlimited.
+    pub fn get_fuel(&self) -> Option<u64> {
+        self.fuel
+    }
 
-        let tokens;
+    /// Sets the point in time after which the interpreter stops with a `BudgetExhausted` error.
+    /// # Arguments
+    /// * `deadline` - The deadline. `None` removes the deadline.
+    pub fn set_deadline(&mut self, deadline: Option<Instant>) {
+        self.deadline = deadline;
+    }
 
-        match lex_result {
-            Ok(t) => {
-                tokens = t;
-            }
-            Err(e) => {
-                return Err(InterpreterError {
-                    description: e.to_string(),
-                })
-            }
-        }
+    /// Sets the token that is checked for cancellation requests.
+    /// A cancelled interpreter stops with a `Cancelled` error and can be resumed after the token was reset.
+    /// # Arguments
+    /// * `token` - The cancel token. `None` removes the token.
+    pub fn set_cancel_token(&mut self, token: Option<CancelToken>) {
+        self.cancel_token = token;
+    }
 
-        let mut i = 0;
-        while let Some(token) = tokens.get(i) {
-            self.instruction_index = i;
-            match token._type {
-                lexer::TokenType::Pfw => {
-                    i += 1;
-                    let steps = expect_num::<usize>(tokens.get(i))?;
-                    self.tape.pfw(steps)?;
-                }
-                lexer::TokenType::Pbw => {
-                    i += 1;
-                    let steps = expect_num::<usize>(tokens.get(i))?;
-                    self.tape.pbw(steps)?;
-                }
-                lexer::TokenType::Inc => {
-                    i += 1;
-                    let by = expect_num::<u8>(tokens.get(i))?;
-                    self.tape.inc(by)?;
-                }
-                lexer::TokenType::Dec => {
-                    i += 1;
-                    let by = expect_num::<u8>(tokens.get(i))?;
-                    self.tape.dec(by)?;
-                }
-                lexer::TokenType::Lop => self.loop_stack.push(self.instruction_index),
-                lexer::TokenType::Pol => {
-                    let last = self.loop_stack.last();
-                    match last {
-                        Some(index) => {
-                            let cur = self.tape.get_current_value();
-                            match cur {
-                                Ok(val) => {
-                                    if val == 0 {
-                                        self.loop_stack.pop(); // Index was valid and we don't need it any longer.
-                                        i += 1;
-                                        continue;
-                                    }
-                                }
-                                Err(e) => return Err(InterpreterError::from(e)),
-                            }
+    /// Sets the tracer that records every executed instruction.
+    /// # Arguments
+    /// * `tracer` - The tracer. `None` disables tracing.
+    pub fn set_tracer(&mut self, tracer: Option<Tracer>) {
+        self.tracer = tracer;
+    }
 
-                            i = *index;
-                        }
-                        None => {
-                            return Err(InterpreterError {
-                                description: "Expected an index on the loop stack. Found nothing."
-                                    .to_string(),
-                            })
-                        }
-                    }
-                }
-                lexer::TokenType::Wrt => self.tape.wrt()?,
-                lexer::TokenType::Wrti8 => self.tape.wrti8()?,
-                lexer::TokenType::Wrti16 => self.tape.wrti16()?,
-                lexer::TokenType::Wrti32 => self.tape.wrti32()?,
-                lexer::TokenType::Wrti64 => self.tape.wrti64()?,
-                lexer::TokenType::Wrtu8 => self.tape.wrtu8()?,
-                lexer::TokenType::Wrtu16 => self.tape.wrtu16()?,
-                lexer::TokenType::Wrtu32 => self.tape.wrtu32()?,
-                lexer::TokenType::Wrtu64 => self.tape.wrtu64()?,
-                lexer::TokenType::Wrtf32 => self.tape.wrtf32()?,
-                lexer::TokenType::Wrtf64 => self.tape.wrtf64()?,
-                lexer::TokenType::Rdi => self.tape.rdi()?,
-                lexer::TokenType::Set => {
-                    i += 1;
-                    let value = tokens.get(i);
-                    match value {
-                        Some(t) => {
-                            let set_res = self.tape.set(&t.value);
-                            match set_res {
-                                Ok(_) => (),
-                                Err(e) => return Err(InterpreterError::from(e)),
-                            }
-                        }
-                        None => {
-                            return Err(InterpreterError {
-                                description: "Expected a value. Found nothing.".to_string(),
-                            })
-                        }
-                    }
-                }
-                lexer::TokenType::Seti8 => {
-                    i += 1;
-                    let v = expect_num::<i8>(tokens.get(i))?;
-                    self.tape.seti8(v)?;
-                }
-                lexer::TokenType::Seti16 => {
-                    i += 1;
-                    let v = expect_num::<i16>(tokens.get(i))?;
-                    self.tape.seti16(v)?;
-                }
-                lexer::TokenType::Seti32 => {
-                    i += 1;
-                    let v = expect_num::<i32>(tokens.get(i))?;
-                    self.tape.seti32(v)?;
-                }
-                lexer::TokenType::Seti64 => {
-                    i += 1;
-                    let v = expect_num::<i64>(tokens.get(i))?;
-                    self.tape.seti64(v)?;
-                }
-                lexer::TokenType::Setu8 => {
-                    i += 1;
-                    let v = expect_num::<u8>(tokens.get(i))?;
-                    self.tape.setu8(v)?;
-                }
-                lexer::TokenType::Setu16 => {
-                    i += 1;
-                    let v = expect_num::<u16>(tokens.get(i))?;
-                    self.tape.setu16(v)?;
-                }
-                lexer::TokenType::Setu32 => {
-                    i += 1;
-                    let v = expect_num::<u32>(tokens.get(i))?;
-                    self.tape.setu32(v)?;
-                }
-                lexer::TokenType::Setu64 => {
-                    i += 1;
-                    let v = expect_num::<u64>(tokens.get(i))?;
-                    self.tape.setu64(v)?;
-                }
-                lexer::TokenType::Setf32 => {
-                    i += 1;
-                    let v = expect_num::<f32>(tokens.get(i))?;
-                    self.tape.setf32(v)?;
-                }
-                lexer::TokenType::Setf64 => {
-                    i += 1;
-                    let v = expect_num::<f64>(tokens.get(i))?;
-                    self.tape.setf64(v)?;
-                }
-                lexer::TokenType::Wra => self.tape.wra()?,
-                lexer::TokenType::Rda => self.tape.rda()?,
-                lexer::TokenType::Clr => self.tape.clr()?,
-                lexer::TokenType::Unknown => {
-                    return Err(InterpreterError {
-                        description: format!(
-                            "Found unknown token '{}'. - ln: {}, col: {}",
-                            token.value, token.line, token.column
-                        ),
-                    })
-                }
-                _ => {
-                    return Err(InterpreterError {
-                        description: "Unexpected error.".to_string(),
-                    })
-                }
-            }
+    /// Removes the tracer and returns it.
+    pub fn take_tracer(&mut self) -> Option<Tracer> {
+        self.tracer.take()
+    }
 
-            i += 1;
-        }
+    /// Sets the profiler that counts executed instructions and measures loops.
+    /// # Arguments
+    /// * `profiler` - The profiler. `None` disables profiling.
+    pub fn set_profiler(&mut self, profiler: Option<Profiler>) {
+        self.profiler = profiler;
+    }
 
-        Ok(())
+    /// Removes the profiler and returns it with its measurements.
+    pub fn take_profiler(&mut self) -> Option<Profiler> {
+        self.profiler.take()
     }
-}
 
-/// Expects the given `Token` to be a `Num` token.
-/// Returns an error if the token is different from what was expected.
-/// # Arguments
-/// * `token` - Option of a token.
-fn expect_num<T>(token: Option<&lexer::Token>) -> InterpreterResult<T>
-where
-    T: std::str::FromStr,
-    T::Err: std::fmt::Display,
-{
-    match token {
-        Some(tok) => match tok._type {
-            lexer::TokenType::Num => {
-                let num = tok.value.parse::<T>();
-                match num {
-                    Ok(n) => Ok(n),
-                    Err(e) => Err(InterpreterError {
-                        description: e.to_string(),
-                    }),
-                }
-            }
-            _ => Err(InterpreterError {
-                description: format!(
-                    "Expected 'num'. Found '{}' - ln: {}, col: {}",
-                    tok.value, tok.line, tok.column
-                ),
-            }),
-        },
-        None => Err(InterpreterError {
-            description: "Expected 'num'. Found nothing instead.".to_string(),
-        }),
+    /// Sets the collector that records executed source lines and branches.
+    /// # Arguments
+    /// * `coverage` - The collector. `None` disables the coverage collection.
+    pub fn set_coverage(&mut self, coverage: Option<Coverage>) {
+        self.coverage = coverage;
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use std::io::BufReader;
+    /// Removes the coverage collector and returns it with its records.
+    pub fn take_coverage(&mut self) -> Option<Coverage> {
+        self.coverage.take()
+    }
 
-    #[test]
-    fn wrt_is_successful_test() {
-        let code = r#"
-            set Hello
-            pbw 5
-            wrt
-            pfw 1
-            wrt
-            pfw 1
-            wrt
-            pfw 1
-            wrt
-            pfw 1
-            wrt
-            "#;
+    /// Sets the history that records executed instructions so that the execution can be reversed
+    /// with `step_back`, `reverse_continue` and `goto`. Records the history already holds are dropped.
+    /// # Arguments
+    /// * `history` - The history. `None` disables the recording.
+    pub fn set_history(&mut self, history: Option<History>) {
+        self.tape.journal = history.as_ref().map(|_| vec![]);
+        self.history = history;
+        self.clear_history();
+    }
 
-        let reader = BufReader::new(code.as_bytes());
-        let mut interpreter = super::Interpreter::default();
-        let run_result = interpreter.run(reader);
+    /// Removes the history and returns it with its records.
+    pub fn take_history(&mut self) -> Option<History> {
+        let mut history = self.history.take();
+        if let Some(h) = &mut history {
+            h.finish(&mut self.tape);
+        }
+        self.tape.journal = None;
+        history
+    }
 
-        assert!(!run_result.is_err());
+    /// Sets whether assertions like `ast` and `asp` are removed from programs before they are executed.
+    /// Applies to programs loaded afterwards.
+    /// # Arguments
+    /// * `release` - `true` to remove assertions.
+    pub fn set_release(&mut self, release: bool) {
+        self.release = release;
     }
 
-    #[test]
-    fn wra_is_successful_test() {
-        let code = "
-            set Hello
-            pbw 5
-            wra";
+    /// Sets the destination of all output instructions. Defaults to stdout.
+    /// # Arguments
+    /// * `output` - The `Write` instance to write to.
+    pub fn set_output<W>(&mut self, output: W)
+    where
+        W: Write + Send + 'static,
+    {
+        self.tape.set_output(Box::new(output));
+    }
 
-        let reader = BufReader::new(code.as_bytes());
-        let mut interpreter = super::Interpreter::default();
-        let run_result = interpreter.run(reader);
+    /// Sets the source of all input instructions. Defaults to stdin.
+    /// # Arguments
+    /// * `input` - The `Read` instance to read from.
+    pub fn set_input<R>(&mut self, input: R)
+    where
+        R: Read + Send + 'static,
+    {
+        self.tape.set_input(Box::new(input));
+    }
 
-        assert!(!run_result.is_err());
+    /// Sets the maximum number of values the value stack can hold.
+    /// # Arguments
+    /// * `max_depth` - The maximum stack depth.
+    pub fn set_max_stack_depth(&mut self, max_depth: usize) {
+        self.stack.max_depth = max_depth;
     }
 
-    #[test]
-    fn run_file_is_successful_test() {
-        let f = std::fs::File::open("../examples/example.trng");
+    /// Returns a reference to the data that is stored on the current tape.
+    pub fn get_data(&self) -> &[u8] {
+        &self.tape.data
+    }
 
-        assert!(!f.is_err());
+    /// Returns the index of the instruction that is executed next.
+    pub fn instruction_index(&self) -> usize {
+        self.instruction_index
+    }
 
-        let mut interpreter = super::Interpreter::default();
-        let run_result = interpreter.run(f.unwrap());
+    /// Returns the number of instructions executed since the program was started.
+    pub fn instruction_count(&self) -> u64 {
+        self.instruction_count
+    }
 
-        assert!(!run_result.is_err());
+    /// Returns the index of the cell the pointer is at.
+    pub fn pointer(&self) -> usize {
+        self.tape.ptr_index
     }
 
-    #[test]
-    fn run_loop_example_successful_test() {
-        let f = std::fs::File::open("../examples/loop.trng");
+    /// Returns the value of a cell or `None` if the index is outside of the tape.
+    /// # Arguments
+    /// * `index` - The index of the cell.
+    pub fn cell(&self, index: usize) -> Option<u8> {
+        self.tape.data.get(index).copied()
+    }
 
-        assert!(!f.is_err());
+    /// Returns the values of a range of cells or `None` if the range is not inside of the tape.
+    /// # Arguments
+    /// * `range` - The indices of the cells.
+    pub fn slice(&self, range: Range<usize>) -> Option<&[u8]> {
+        self.tape.data.get(range)
+    }
 
-        let mut interpreter = super::Interpreter::default();
-        let run_result = interpreter.run(f.unwrap());
+    /// Interprets the cells from an index on as a number in BE byte order, the same way `wrti32` and
+    /// the other typed instructions do, e.g. `read_typed::<i32>(0)`.
+    /// Returns `None` if the number does not fit on the tape.
+    /// # Arguments
+    /// * `index` - The index of the first cell.
+    pub fn read_typed<T: Num>(&self, index: usize) -> Option<T> {
+        let end = index.checked_add(T::number_of_bytes())?;
+        self.slice(index..end).map(|bytes| T::from(bytes.to_vec()))
+    }
 
-        assert!(!run_result.is_err());
+    /// Formats the cells around the pointer as a hex and ASCII dump with the current cell in brackets.
+    /// # Arguments
+    /// * `rows` - The number of rows of 16 cells to show before and after the row of the pointer.
+    pub fn dump(&self, rows: usize) -> String {
+        tape::dump(&self.tape.data, self.tape.ptr_index, rows)
     }
 
-    #[test]
-    fn run_seti_example_successful_test() {
-        let f = std::fs::File::open("../examples/seti.trng");
+    /// Returns a reference to the values on the value stack. The last value is the top of the stack.
+    pub fn get_stack(&self) -> &Vec<Vec<u8>> {
+        &self.stack.values
+    }
 
-        assert!(!f.is_err());
+    /// Copies all bytes of the given reader onto the tape starting at the given cell, e.g. before a run.
+    /// Returns the number of copied bytes. Fails if the bytes do not fit on the tape.
+    /// # Arguments
+    /// * `read_from` - The `Read` instance to read the bytes from.
+    /// * `offset` - The index of the first cell to write.
+    pub fn preload_tape<T>(&mut self, read_from: T, offset: usize) -> InterpreterResult<usize>
+    where
+        T: Read,
+    {
+        Ok(self.tape.preload(read_from, offset)?)
+    }
 
-        let mut interpreter = super::Interpreter::default();
-        let run_result = interpreter.run(f.unwrap());
+    /// Copies the content of the given file onto the tape starting at the given cell.
+    /// Returns the number of copied bytes. Fails if the bytes do not fit on the tape.
+    /// # Arguments
+    /// * `path` - The path of the file.
+    /// * `offset` - The index of the first cell to write.
+    pub fn preload_tape_file<P>(&mut self, path: P, offset: usize) -> InterpreterResult<usize>
+    where
+        P: AsRef<Path>,
+    {
+        let file = File::open(path).map_err(io_error)?;
+        self.preload_tape(std::io::BufReader::new(file), offset)
+    }
 
-        assert!(!run_result.is_err());
+    /// Writes the raw cells of the tape to the given writer, e.g. after a run.
+    /// # Arguments
+    /// * `write_to` - The `Write` instance to write the cells to.
+    /// * `range` - The indizes of the cells to write. `None` writes the whole tape.
+    pub fn dump_tape<T>(&self, write_to: T, range: Option<Range<usize>>) -> InterpreterResult<()>
+    where
+        T: Write,
+    {
+        let range = range.unwrap_or(0..self.tape.data.len());
+        Ok(self.tape.dump_to(write_to, range)?)
     }
 
-    #[test]
-    fn run_setu_example_successful_test() {
-        let f = std::fs::File::open("../examples/setu.trng");
+    /// Writes the raw cells of the tape to the given file. The file is created or truncated.
+    /// # Arguments
+    /// * `path` - The path of the file.
+    /// * `range` - The indizes of the cells to write. `None` writes the whole tape.
+    pub fn dump_tape_file<P>(&self, path: P, range: Option<Range<usize>>) -> InterpreterResult<()>
+    where
+        P: AsRef<Path>,
+    {
+        let file = File::create(path).map_err(io_error)?;
+        self.dump_tape(std::io::BufWriter::new(file), range)
+    }
 
-        assert!(!f.is_err());
+    /// Uses the given file as the tape, so programs change the file in place.
+    /// The tape has as many cells as the file has bytes, so writing beyond the end of the file fails with an index error.
+    /// The pointer is moved to the first cell.
+    /// # Arguments
+    /// * `path` - The path of the file. It has to be readable and writable.
+    #[cfg(feature = "mmap")]
+    pub fn map_tape_file<P>(&mut self, path: P) -> InterpreterResult<()>
+    where
+        P: AsRef<Path>,
+    {
+        let file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(path)
+            .map_err(io_error)?;
 
-        let mut interpreter = super::Interpreter::default();
-        let run_result = interpreter.run(f.unwrap());
+        self.tape.map(&file)?;
+        self.clear_history();
+        Ok(())
+    }
 
-        assert!(!run_result.is_err());
+    /// Starts the interpreter.
+    /// # Arguments
+    /// * `read_from` - Source for TRNG code. Can be anything that implements the `Read` trait.
+    pub fn run<T>(&mut self, read_from: T) -> InterpreterResult<()>
+    where
+        T: Read,
+    {
+        self.load(read_from)?;
+        self.execute()
     }
 
-    #[test]
-    fn run_setf_example_successful_test() {
-        let f = std::fs::File::open("../examples/setf.trng");
+    /// Starts the interpreter with Brainfuck code.
+    /// # Arguments
+    /// * `read_from` - Source for Brainfuck code. Can be anything that implements the `Read` trait.
+    pub fn run_brainfuck<T>(&mut self, read_from: T) -> InterpreterResult<()>
+    where
+        T: Read,
+    {
+        self.run_program(Program::parse_brainfuck(read_from)?)
+    }
 
-        assert!(!f.is_err());
+    /// Starts the interpreter with the code of the given file.
+    /// Files ending in `.bf` or `.b` are treated as Brainfuck, all other files as TRNG.
+    /// Coverage records are attributed to the path of the file.
+    /// # Arguments
+    /// * `path` - Path of the source file.
+    pub fn run_file<P>(&mut self, path: P) -> InterpreterResult<()>
+    where
+        P: AsRef<Path>,
+    {
+        if let Some(coverage) = &mut self.coverage {
+            coverage.set_source(&path.as_ref().to_string_lossy());
+        }
 
-        let mut interpreter = super::Interpreter::default();
-        let run_result = interpreter.run(f.unwrap());
+        self.run_program(Program::from_file(path)?)
+    }
 
-        assert!(!run_result.is_err());
+    /// Starts the interpreter with an already parsed program.
+    /// # Arguments
+    /// * `program` - The program to execute.
+    pub fn run_program(&mut self, program: Program) -> InterpreterResult<()> {
+        self.load_program(program);
+        self.execute()
+    }
+
+    /// Starts the interpreter with the given program compiled to native code.
+    ///
+    /// Falls back to interpreting the program if the host is not supported or if fuel,
+    /// a deadline or a cancel token is set or the execution is observed, since compiled code does not count instructions.
+    /// A compiled program that failed cannot be resumed.
+    /// # Arguments
+    /// * `program` - The program to execute.
+    #[cfg(feature = "jit")]
+    pub fn run_jit(&mut self, mut program: Program) -> InterpreterResult<()> {
+        if self.fuel.is_some()
+            || self.deadline.is_some()
+            || self.cancel_token.is_some()
+            || self.is_observed()
+        {
+            return self.run_program(program);
+        }
+
+        if self.release {
+            program.strip_assertions();
+        }
+
+        let compiled = match jit::compile(&program) {
+            Ok(compiled) => compiled,
+            Err(_) => return self.run_program(program),
+        };
+
+        self.load_program(program);
+
+        let result = compiled.run(&mut self.tape, &mut self.stack, &self.program);
+        let flushed = self.tape.flush();
+
+        match result {
+            Ok(()) => self.instruction_index = self.program.len(),
+            Err((i, e)) => {
+                self.instruction_index = i;
+                return Err(e);
+            }
+        }
+
+        Ok(flushed?)
+    }
+
+    /// Loads a program without executing it. The execution starts with `resume`.
+    /// # Arguments
+    /// * `read_from` - Source for TRNG code. Can be anything that implements the `Read` trait.
+    pub fn load<T>(&mut self, read_from: T) -> InterpreterResult<()>
+    where
+        T: Read,
+    {
+        self.load_program(Program::parse(read_from)?);
+        Ok(())
+    }
+
+    /// Loads an already parsed program without executing it. The execution starts with `resume`.
+    /// Assertions are removed if the interpreter is set to release.
+    /// # Arguments
+    /// * `program` - The program to load.
+    pub fn load_program(&mut self, mut program: Program) {
+        if self.release {
+            program.strip_assertions();
+        }
+
+        self.tape.wrapping = program.wrapping;
+        self.program = program;
+        self.instruction_index = 0;
+        self.instruction_count = 0;
+        self.loop_stack = vec![];
+        self.clear_history();
+    }
+
+    /// Captures the complete execution state of the interpreter.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            program_hash: self.program.hash(),
+            data: self.tape.data.to_vec(),
+            ptr_index: self.tape.ptr_index,
+            bookmarks: self.tape.bookmarks.clone(),
+            instruction_index: self.instruction_index,
+            instruction_count: self.instruction_count,
+            loop_stack: self.loop_stack.clone(),
+            stack: self.stack.values.clone(),
+        }
+    }
+
+    /// Restores the execution state from a snapshot. Continue the execution with `resume`.
+    ///
+    /// The snapshot is refused if it was taken from a different program or with a different tape size.
+    /// # Arguments
+    /// * `snapshot` - The snapshot to restore.
+    pub fn restore(&mut self, snapshot: &Snapshot) -> InterpreterResult<()> {
+        if snapshot.program_hash != self.program.hash() {
+            return Err(InterpreterError::from(SnapshotError::new(
+                SnapshotErrorType::Mismatch,
+                "The snapshot was taken from a different program.".to_string(),
+            )));
+        }
+
+        if snapshot.data.len() != self.tape.data.len() {
+            return Err(InterpreterError::from(SnapshotError::new(
+                SnapshotErrorType::Mismatch,
+                format!(
+                    "The snapshot has a tape size of {}. Expected {}.",
+                    snapshot.data.len(),
+                    self.tape.data.len()
+                ),
+            )));
+        }
+
+        let valid = snapshot.ptr_index < snapshot.data.len()
+            && snapshot.bookmarks.iter().all(|b| *b < snapshot.data.len())
+            && snapshot.instruction_index <= self.program.len()
+            && snapshot.loop_stack.iter().all(|l| *l < self.program.len());
+
+        if !valid {
+            return Err(InterpreterError::from(SnapshotError::new(
+                SnapshotErrorType::Format,
+                "The snapshot contains indizes outside of the tape or the program.".to_string(),
+            )));
+        }
+
+        self.apply(snapshot);
+        self.clear_history();
+
+        Ok(())
+    }
+
+    /// Moves the execution back by one instruction using the history.
+    /// Returns `false` if there is no history or it holds no earlier instruction.
+    ///
+    /// Continuing with `resume` executes the following instructions again,
+    /// so their input is read and their output is written once more.
+    pub fn step_back(&mut self) -> bool {
+        let Some(history) = &mut self.history else {
+            return false;
+        };
+
+        match history.pop(&mut self.tape) {
+            Some(entry) => {
+                self.instruction_index = entry.instruction_index;
+                self.instruction_count = entry.instruction_count;
+                entry.undo(&mut self.tape, &mut self.loop_stack, &mut self.stack);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Moves the execution back until the next instruction is one of the breakpoints.
+    /// Moves back at least one instruction. Returns `false` if the history holds no earlier execution of a breakpoint;
+    /// the execution is then at the earliest recorded instruction.
+    /// # Arguments
+    /// * `breakpoints` - The instruction indizes to stop at.
+    pub fn reverse_continue(&mut self, breakpoints: &[usize]) -> bool {
+        while self.step_back() {
+            if breakpoints.contains(&self.instruction_index) {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Moves the execution back to the point at which the given number of instructions had been executed.
+    /// Returns `false` without changing the state if the history does not reach back that far
+    /// or the instruction count lies in the future.
+    /// # Arguments
+    /// * `instruction_count` - The number of executed instructions to move back to.
+    pub fn goto(&mut self, instruction_count: u64) -> bool {
+        let Some(history) = &mut self.history else {
+            return false;
+        };
+
+        history.finish(&mut self.tape);
+        let earliest = history.earliest().unwrap_or(self.instruction_count);
+        if instruction_count < earliest || instruction_count > self.instruction_count {
+            return false;
+        }
+
+        if let Some(checkpoint) = history.rewind_to_checkpoint(instruction_count) {
+            self.apply(&checkpoint);
+        }
+
+        while self.instruction_count > instruction_count && self.step_back() {}
+        true
+    }
+
+    /// Overwrites the execution state with the given snapshot without validating it.
+    fn apply(&mut self, snapshot: &Snapshot) {
+        self.tape.data.copy_from_slice(&snapshot.data);
+        self.tape.ptr_index = snapshot.ptr_index;
+        self.tape.bookmarks = snapshot.bookmarks.clone();
+        self.instruction_index = snapshot.instruction_index;
+        self.instruction_count = snapshot.instruction_count;
+        self.loop_stack = snapshot.loop_stack.clone();
+        self.stack.values = snapshot.stack.clone();
+    }
+
+    /// Drops the records of the history and the journal of the tape.
+    fn clear_history(&mut self) {
+        if let Some(history) = &mut self.history {
+            history.clear();
+        }
+        if let Some(journal) = &mut self.tape.journal {
+            journal.clear();
+        }
+    }
+
+    /// Continues the execution of the current program at the current instruction index,
+    /// e.g. after more fuel was added following a `BudgetExhausted` error.
+    pub fn resume(&mut self) -> InterpreterResult<()> {
+        self.execute()
+    }
+
+    /// Executes the current program starting at the current instruction index
+    /// and flushes the output afterwards.
+    fn execute(&mut self) -> InterpreterResult<()> {
+        let result = if self.is_observed() {
+            if let Some(tracer) = &mut self.tracer {
+                tracer.begin(&self.tape.data);
+            }
+            if let Some(profiler) = &mut self.profiler {
+                profiler.begin(
+                    &self.program,
+                    self.instruction_index,
+                    (self.tape.bytes_read, self.tape.bytes_written),
+                );
+            }
+            if let Some(coverage) = &mut self.coverage {
+                coverage.begin(&self.program);
+            }
+            self.dispatch::<true>()
+        } else {
+            self.dispatch::<false>()
+        };
+        let flushed = self.tape.flush();
+        let traced = self.tracer.as_mut().map_or(Ok(()), |t| t.flush());
+
+        result?;
+        flushed?;
+        traced.map_err(|e| InterpreterError::new(InterpreterErrorType::Io, e.to_string()))
+    }
+
+    /// Runs the dispatch loop starting at the current instruction index.
+    /// Tracing, profiling and the coverage collection are only compiled into the loop if `OBSERVED` is set.
+    fn dispatch<const OBSERVED: bool>(&mut self) -> InterpreterResult<()> {
+        let mut i = self.instruction_index;
+        while i < self.program.len() {
+            self.instruction_index = i;
+            self.consume_budget()?;
+
+            let ptr_before = self.tape.ptr_index;
+            if OBSERVED {
+                self.record(i);
+            }
+
+            match &self.program.instructions[i] {
+                Instruction::Lop(_) => self.loop_stack.push(i),
+                Instruction::Whl(end) => {
+                    if self.tape.get_current_value()? == 0 {
+                        i = *end; // Skip the loop including its 'pol'.
+                    } else {
+                        self.loop_stack.push(i);
+                    }
+                }
+                Instruction::Pol(_) => match self.loop_stack.last() {
+                    Some(index) => {
+                        if self.tape.get_current_value()? == 0 {
+                            self.loop_stack.pop(); // Index was valid and we don't need it any longer.
+                        } else {
+                            i = *index;
+                        }
+                    }
+                    None => {
+                        return Err(InterpreterError::new(
+                            InterpreterErrorType::Syntax,
+                            "Expected an index on the loop stack. Found nothing.".to_string(),
+                        ))
+                    }
+                },
+                Instruction::Rdi | Instruction::Rda => {
+                    check_cancelled(&self.cancel_token, i, self.tape.ptr_index)?;
+                    step(
+                        &mut self.tape,
+                        &mut self.stack,
+                        &self.program.instructions[i],
+                        self.program.positions[i],
+                    )?
+                }
+                instruction => step(
+                    &mut self.tape,
+                    &mut self.stack,
+                    instruction,
+                    self.program.positions[i],
+                )?,
+            }
+
+            if OBSERVED {
+                self.observe(ptr_before, i + 1)?;
+            }
+
+            i += 1;
+        }
+
+        self.instruction_index = i;
+        Ok(())
+    }
+
+    /// Returns whether a tracer, a profiler, a coverage collector or a history is set.
+    fn is_observed(&self) -> bool {
+        self.tracer.is_some()
+            || self.profiler.is_some()
+            || self.coverage.is_some()
+            || self.history.is_some()
+    }
+
+    /// Passes the state before the instruction at the given index to the history.
+    /// # Arguments
+    /// * `i` - The index of the instruction that is about to be executed.
+    fn record(&mut self, i: usize) {
+        let count = self.instruction_count - 1;
+        let Some(history) = &mut self.history else {
+            return;
+        };
+
+        history.begin(
+            &mut self.tape,
+            &self.program.instructions[i],
+            i,
+            count,
+            &self.loop_stack,
+            &self.stack,
+        );
+
+        if history.wants_checkpoint(count) {
+            let snapshot = Snapshot {
+                instruction_count: count,
+                ..self.snapshot()
+            };
+            if let Some(history) = &mut self.history {
+                history.push_checkpoint(snapshot);
+            }
+        }
+    }
+
+    /// Passes the instruction at the current instruction index to the tracer, the profiler and the coverage collector.
+    /// # Arguments
+    /// * `ptr_before` - The pointer index before the instruction was executed.
+    /// * `next` - The index of the instruction that is executed next.
+    fn observe(&mut self, ptr_before: usize, next: usize) -> InterpreterResult<()> {
+        if let Some(profiler) = &mut self.profiler {
+            profiler.record(
+                &self.program,
+                self.instruction_index,
+                next,
+                self.tape.ptr_index,
+                (self.tape.bytes_read, self.tape.bytes_written),
+            );
+        }
+
+        if let Some(coverage) = &mut self.coverage {
+            coverage.record(self.instruction_index, next);
+        }
+
+        if let Some(tracer) = &mut self.tracer {
+            tracer
+                .record(
+                    &self.program,
+                    self.instruction_index,
+                    ptr_before,
+                    self.tape.ptr_index,
+                    &self.tape.data,
+                )
+                .map_err(|e| InterpreterError::new(InterpreterErrorType::Io, e.to_string()))?;
+        }
+
+        Ok(())
+    }
+
+    /// Consumes one instruction of the budget.
+    /// Returns an error if the fuel is exhausted or the deadline has passed.
+    fn consume_budget(&mut self) -> InterpreterResult<()> {
+        if self.instruction_count.is_multiple_of(POLL_INTERVAL) {
+            check_cancelled(
+                &self.cancel_token,
+                self.instruction_index,
+                self.tape.ptr_index,
+            )?;
+
+            if let Some(deadline) = self.deadline {
+                if Instant::now() >= deadline {
+                    return Err(halt_error(
+                        InterpreterErrorType::BudgetExhausted,
+                        "Deadline exceeded",
+                        self.instruction_index,
+                        self.tape.ptr_index,
+                    ));
+                }
+            }
+        }
+
+        if let Some(fuel) = self.fuel {
+            if fuel == 0 {
+                return Err(halt_error(
+                    InterpreterErrorType::BudgetExhausted,
+                    "Instruction budget exhausted",
+                    self.instruction_index,
+                    self.tape.ptr_index,
+                ));
+            }
+            self.fuel = Some(fuel - 1);
+        }
+
+        self.instruction_count += 1;
+        Ok(())
+    }
+}
+
+/// Returns a `Cancelled` error if a cancellation was requested on the given token.
+/// # Arguments
+/// * `token` - The optional cancel token.
+/// * `instruction_index` - The index of the instruction that is about to be executed.
+/// * `ptr_index` - The current pointer index.
+fn check_cancelled(
+    token: &Option<CancelToken>,
+    instruction_index: usize,
+    ptr_index: usize,
+) -> InterpreterResult<()> {
+    match token {
+        Some(t) if t.is_cancelled() => Err(halt_error(
+            InterpreterErrorType::Cancelled,
+            "Execution cancelled",
+            instruction_index,
+            ptr_index,
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Creates an `Io` error from the given error of a file operation.
+fn io_error(e: std::io::Error) -> InterpreterError {
+    InterpreterError::new(InterpreterErrorType::Io, e.to_string())
+}
+
+/// Creates an error for an execution that was stopped before the given instruction.
+fn halt_error(
+    _type: InterpreterErrorType,
+    reason: &str,
+    instruction_index: usize,
+    ptr_index: usize,
+) -> InterpreterError {
+    InterpreterError::new(
+        _type,
+        format!(
+            "{} at instruction index {} with pointer index {}.",
+            reason, instruction_index, ptr_index
+        ),
+    )
+}
+
+/// Executes a single instruction that does not change the control flow.
+/// Loop instructions only read the current cell, which fails if the pointer is outside of the tape.
+/// # Arguments
+/// * `tape` - The tape to operate on.
+/// * `stack` - The value stack to operate on.
+/// * `instruction` - The instruction to execute.
+/// * `position` - The source position of the instruction, reported by failed assertions.
+pub(crate) fn step(
+    tape: &mut tape::Tape,
+    stack: &mut stack::ValueStack,
+    instruction: &Instruction,
+    position: Position,
+) -> InterpreterResult<()> {
+    match instruction {
+        Instruction::Pfw(steps) => tape.pfw(*steps)?,
+        Instruction::Pbw(steps) => tape.pbw(*steps)?,
+        Instruction::Ptr(index) => tape.ptr(*index)?,
+        Instruction::Mrk => tape.mrk()?,
+        Instruction::Ret => tape.ret()?,
+        Instruction::Inc(by) => tape.inc(*by)?,
+        Instruction::Dec(by) => tape.dec(*by)?,
+        Instruction::Lop(_) | Instruction::Whl(_) | Instruction::Pol(_) => {
+            tape.get_current_value()?;
+        }
+        Instruction::Wrt => tape.wrt()?,
+        Instruction::Wrti8 => tape.wrti8()?,
+        Instruction::Wrti16 => tape.wrti16()?,
+        Instruction::Wrti32 => tape.wrti32()?,
+        Instruction::Wrti64 => tape.wrti64()?,
+        Instruction::Wrtu8 => tape.wrtu8()?,
+        Instruction::Wrtu16 => tape.wrtu16()?,
+        Instruction::Wrtu32 => tape.wrtu32()?,
+        Instruction::Wrtu64 => tape.wrtu64()?,
+        Instruction::Wrtf32 => tape.wrtf32()?,
+        Instruction::Wrtf64 => tape.wrtf64()?,
+        Instruction::Rdi => tape.rdi()?,
+        Instruction::Set(v) => tape.set(v)?,
+        Instruction::Seti8(v) => tape.seti8(*v)?,
+        Instruction::Seti16(v) => tape.seti16(*v)?,
+        Instruction::Seti32(v) => tape.seti32(*v)?,
+        Instruction::Seti64(v) => tape.seti64(*v)?,
+        Instruction::Setu8(v) => tape.setu8(*v)?,
+        Instruction::Setu16(v) => tape.setu16(*v)?,
+        Instruction::Setu32(v) => tape.setu32(*v)?,
+        Instruction::Setu64(v) => tape.setu64(*v)?,
+        Instruction::Setf32(v) => tape.setf32(*v)?,
+        Instruction::Setf64(v) => tape.setf64(*v)?,
+        Instruction::Wra => tape.wra()?,
+        Instruction::Rda => tape.rda()?,
+        Instruction::Clr => tape.clr()?,
+        Instruction::Psh => stack.push(tape.get_bytes(1)?)?,
+        Instruction::Psh16 => stack.push(tape.get_bytes(2)?)?,
+        Instruction::Psh32 => stack.push(tape.get_bytes(4)?)?,
+        Instruction::Psh64 => stack.push(tape.get_bytes(8)?)?,
+        Instruction::Pop => {
+            let value = stack.pop()?;
+            tape.put_bytes(&value)?;
+        }
+        Instruction::Pek => tape.put_bytes(stack.peek()?)?,
+        Instruction::Dup => stack.dup()?,
+        Instruction::Swp => stack.swp()?,
+        Instruction::Ast(_)
+        | Instruction::Asti8(_)
+        | Instruction::Asti16(_)
+        | Instruction::Asti32(_)
+        | Instruction::Asti64(_)
+        | Instruction::Astu8(_)
+        | Instruction::Astu16(_)
+        | Instruction::Astu32(_)
+        | Instruction::Astu64(_)
+        | Instruction::Astf32(..)
+        | Instruction::Astf64(..)
+        | Instruction::Asp(_) => assertion::check(tape, instruction, position)?,
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+#[allow(clippy::nonminimal_bool)]
+mod tests {
+    use std::io::BufReader;
+
+    #[test]
+    fn wrt_is_successful_test() {
+        let code = r#"
+            set Hello
+            pbw 5
+            wrt
+            pfw 1
+            wrt
+            pfw 1
+            wrt
+            pfw 1
+            wrt
+            pfw 1
+            wrt
+            "#;
+
+        let reader = BufReader::new(code.as_bytes());
+        let mut interpreter = super::Interpreter::default();
+        let run_result = interpreter.run(reader);
+
+        assert!(!run_result.is_err());
+    }
+
+    #[test]
+    fn wra_is_successful_test() {
+        let code = "
+            set Hello
+            pbw 5
+            wra";
+
+        let reader = BufReader::new(code.as_bytes());
+        let mut interpreter = super::Interpreter::default();
+        let run_result = interpreter.run(reader);
+
+        assert!(!run_result.is_err());
+    }
+
+    #[test]
+    fn ptr_mrk_ret_is_successful_test() {
+        let code = "
+            pfw 2
+            mrk
+            set Hello
+            ptr 20
+            inc 1
+            ret
+            wra";
+
+        let reader = BufReader::new(code.as_bytes());
+        let mut interpreter = super::Interpreter::default();
+        let run_result = interpreter.run(reader);
+
+        assert!(run_result.is_ok());
+        assert_eq!(interpreter.get_data()[20], 1);
+        assert_eq!(&interpreter.get_data()[2..7], b"Hello");
+    }
+
+    #[test]
+    fn ptr_out_of_bounds_is_err_test() {
+        let code = "ptr 100";
+
+        let reader = BufReader::new(code.as_bytes());
+        let mut interpreter = super::Interpreter::new(100);
+        let err = interpreter.run(reader).unwrap_err();
+
+        assert_eq!(err._type, super::InterpreterErrorType::Tape);
+        assert!(err.description.contains("Index error"));
+    }
+
+    #[test]
+    fn stack_swaps_values_test() {
+        let code = "
+            inc 1
+            psh
+            pfw 1
+            seti32 1000
+            pbw 4
+            psh32
+            swp
+            pop
+            pfw 4
+            pop
+            pbw 4
+            wrti8";
+
+        let reader = BufReader::new(code.as_bytes());
+        let mut interpreter = super::Interpreter::default();
+        let run_result = interpreter.run(reader);
+
+        assert!(run_result.is_ok());
+        assert_eq!(interpreter.get_data()[1], 1);
+        assert_eq!(&interpreter.get_data()[5..9], &[0, 0, 3, 232]);
+        assert!(interpreter.get_stack().is_empty());
+    }
+
+    #[test]
+    fn stack_dup_pek_test() {
+        let code = "
+            inc 42
+            psh
+            dup
+            pfw 1
+            pek
+            pfw 1
+            pop";
+
+        let reader = BufReader::new(code.as_bytes());
+        let mut interpreter = super::Interpreter::default();
+        let run_result = interpreter.run(reader);
+
+        assert!(run_result.is_ok());
+        assert_eq!(&interpreter.get_data()[0..3], &[42, 42, 42]);
+        assert_eq!(interpreter.get_stack(), &vec![vec![42]]);
+    }
+
+    #[test]
+    fn stack_underflow_is_err_test() {
+        let code = "pop";
+
+        let reader = BufReader::new(code.as_bytes());
+        let mut interpreter = super::Interpreter::default();
+        let run_result = interpreter.run(reader);
+
+        assert!(run_result.unwrap_err().description.contains("Underflow"));
+    }
+
+    #[test]
+    fn stack_max_depth_is_err_test() {
+        let code = "
+            psh
+            psh";
+
+        let reader = BufReader::new(code.as_bytes());
+        let mut interpreter = super::Interpreter::default();
+        interpreter.set_max_stack_depth(1);
+        let err = interpreter.run(reader).unwrap_err();
+
+        assert_eq!(err._type, super::InterpreterErrorType::Stack);
+    }
+
+    #[test]
+    fn passing_assertions_test() {
+        let code = "
+            inc 3
+            ast 3
+            pfw 1
+            seti32 -7
+            pbw 4
+            asti32 -7
+            setf64 1.5
+            pbw 8
+            astf64 1.5001 0.001
+            asp 1";
+
+        let mut interpreter = super::Interpreter::default();
+        let run_result = interpreter.run(code.as_bytes());
+
+        assert!(run_result.is_ok());
+    }
+
+    #[test]
+    fn failed_assertion_is_err_test() {
+        let code = "inc 3\n  ast 4";
+
+        let mut interpreter = super::Interpreter::default();
+        let err = interpreter.run(code.as_bytes()).unwrap_err();
+
+        assert_eq!(err._type, super::InterpreterErrorType::Assertion);
+        let assertion = err.assertion.unwrap();
+        assert_eq!(assertion._type, crate::AssertionErrorType::Value);
+        assert_eq!(assertion.expected, "4");
+        assert_eq!(assertion.actual, "3");
+        assert_eq!((assertion.line, assertion.column), (2, 3));
+        assert_eq!(
+            err.description,
+            "Assertion Error [Value error]: Expected 4 but found 3. - ln: 2, col: 3"
+        );
+    }
+
+    #[test]
+    fn failed_pointer_assertion_is_err_test() {
+        let code = "pfw 2 astf32 0 0.5 asp 1";
+
+        let mut interpreter = super::Interpreter::default();
+        let err = interpreter.run(code.as_bytes()).unwrap_err();
+
+        let assertion = err.assertion.unwrap();
+        assert_eq!(assertion._type, crate::AssertionErrorType::Pointer);
+        assert_eq!(assertion.expected, "pointer index 1");
+        assert_eq!(assertion.actual, "pointer index 2");
+    }
+
+    #[test]
+    fn release_strips_assertions_test() {
+        let code = "inc 3 ast 4 asp 1";
+
+        let mut interpreter = super::Interpreter::default();
+        interpreter.set_release(true);
+
+        assert!(interpreter.run(code.as_bytes()).is_ok());
+    }
+
+    #[test]
+    fn inspect_tape_test() {
+        let code = "set Hi seti32 -7 setf64 1.5 ptr 1";
+
+        let mut interpreter = super::Interpreter::new(20);
+        interpreter.set_output(std::io::sink());
+        interpreter.run(code.as_bytes()).unwrap();
+
+        assert_eq!(interpreter.pointer(), 1);
+        assert_eq!(interpreter.cell(0), Some(b'H'));
+        assert_eq!(interpreter.cell(20), None);
+        assert_eq!(interpreter.slice(0..2), Some(&b"Hi"[..]));
+        assert_eq!(interpreter.slice(18..21), None);
+        assert_eq!(interpreter.read_typed::<i32>(2), Some(-7));
+        assert_eq!(interpreter.read_typed::<f64>(6), Some(1.5));
+        assert_eq!(interpreter.read_typed::<u64>(16), None);
+        assert_eq!(
+            interpreter.dump(0),
+            "00000000  48[69]ff ff ff f9 3f f8  00 00 00 00 00 00 00 00  |Hi....?.........|\n"
+        );
+    }
+
+    #[test]
+    fn preload_and_dump_tape_test() {
+        let mut interpreter = super::Interpreter::new(8);
+        interpreter.preload_tape(&[1u8, 2, 3][..], 2).unwrap();
+        interpreter.run("ptr 3 inc 10".as_bytes()).unwrap();
+
+        let mut out = vec![];
+        interpreter.dump_tape(&mut out, Some(2..5)).unwrap();
+        assert_eq!(out, vec![1, 12, 3]);
+
+        out.clear();
+        interpreter.dump_tape(&mut out, None).unwrap();
+        assert_eq!(out, vec![0, 0, 1, 12, 3, 0, 0, 0]);
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn map_tape_file_changes_file_in_place_test() {
+        let path = std::env::temp_dir().join(format!("trng-mmap-{}.bin", std::process::id()));
+        std::fs::write(&path, b"abcd").unwrap();
+
+        let mut interpreter = super::Interpreter::default();
+        interpreter.map_tape_file(&path).unwrap();
+        interpreter.run("ptr 1 set XY".as_bytes()).unwrap();
+        let err = interpreter.run("ptr 3 set yz".as_bytes()).unwrap_err();
+        drop(interpreter);
+
+        let content = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(err._type, super::InterpreterErrorType::Tape);
+        assert!(err.description.contains("Index error"));
+        assert_eq!(content, b"aXYy");
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn reset_unmaps_tape_file_test() {
+        let path = std::env::temp_dir().join(format!("trng-reset-{}.bin", std::process::id()));
+        std::fs::write(&path, b"abcd").unwrap();
+
+        let mut interpreter = super::Interpreter::default();
+        interpreter.map_tape_file(&path).unwrap();
+        interpreter.run("inc 1".as_bytes()).unwrap();
+        interpreter.reset();
+        interpreter.run("pfw 1 inc 1".as_bytes()).unwrap();
+        let data = interpreter.get_data().to_vec();
+        drop(interpreter);
+
+        let content = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(content, b"bbcd");
+        assert_eq!(data, vec![0, 1, 0, 0]);
+    }
+
+    #[test]
+    fn fuel_exhausted_in_endless_loop_test() {
+        let code = "
+            inc 1
+            lop
+            pfw 1
+            pbw 1
+            pol";
+
+        let reader = BufReader::new(code.as_bytes());
+        let mut interpreter = super::Interpreter::default();
+        interpreter.set_fuel(Some(100));
+        let run_result = interpreter.run(reader);
+
+        let err = run_result.unwrap_err();
+        assert_eq!(err._type, super::InterpreterErrorType::BudgetExhausted);
+        assert!(err.description.contains("pointer index"));
+        assert_eq!(interpreter.get_fuel(), Some(0));
+    }
+
+    #[test]
+    fn resume_after_fuel_exhausted_test() {
+        let code = "
+            inc 10
+            lop
+            dec 1
+            pol
+            pfw 1
+            inc 7";
+
+        let reader = BufReader::new(code.as_bytes());
+        let mut interpreter = super::Interpreter::default();
+        interpreter.set_fuel(Some(5));
+        let err = interpreter.run(reader).unwrap_err();
+
+        assert_eq!(err._type, super::InterpreterErrorType::BudgetExhausted);
+
+        interpreter.add_fuel(100);
+        let resume_result = interpreter.resume();
+
+        assert!(resume_result.is_ok());
+        assert_eq!(interpreter.get_data()[0], 0);
+        assert_eq!(interpreter.get_data()[1], 7);
+    }
+
+    #[test]
+    fn deadline_exceeded_test() {
+        let code = "
+            inc 1
+            lop
+            pol";
+
+        let reader = BufReader::new(code.as_bytes());
+        let mut interpreter = super::Interpreter::default();
+        interpreter.set_deadline(Some(
+            std::time::Instant::now() + std::time::Duration::from_millis(20),
+        ));
+        let run_result = interpreter.run(reader);
+
+        assert_eq!(
+            run_result.unwrap_err()._type,
+            super::InterpreterErrorType::BudgetExhausted
+        );
+    }
+
+    #[test]
+    fn cancel_endless_loop_test() {
+        let code = "
+            inc 1
+            lop
+            pfw 1
+            pbw 1
+            pol";
+
+        let token = crate::CancelToken::new();
+        let handle = {
+            let token = token.clone();
+            std::thread::spawn(move || {
+                std::thread::sleep(std::time::Duration::from_millis(20));
+                token.cancel();
+            })
+        };
+
+        let reader = BufReader::new(code.as_bytes());
+        let mut interpreter = super::Interpreter::default();
+        interpreter.set_cancel_token(Some(token));
+        let run_result = interpreter.run(reader);
+        handle.join().unwrap();
+
+        assert_eq!(
+            run_result.unwrap_err()._type,
+            super::InterpreterErrorType::Cancelled
+        );
+        assert_eq!(interpreter.get_data()[0], 1);
+    }
+
+    #[test]
+    fn cancel_before_rdi_test() {
+        let code = "
+            inc 3
+            rdi";
+
+        let token = crate::CancelToken::new();
+
+        let reader = BufReader::new(code.as_bytes());
+        let mut interpreter = super::Interpreter::default();
+        interpreter.set_cancel_token(Some(token.clone()));
+        interpreter.set_fuel(Some(1));
+        let run_result = interpreter.run(reader);
+
+        assert_eq!(
+            run_result.unwrap_err()._type,
+            super::InterpreterErrorType::BudgetExhausted
+        );
+
+        token.cancel();
+        interpreter.add_fuel(1);
+        let resume_result = interpreter.resume();
+
+        assert_eq!(
+            resume_result.unwrap_err()._type,
+            super::InterpreterErrorType::Cancelled
+        );
+        assert_eq!(interpreter.get_data()[0], 3);
+    }
+
+    #[test]
+    fn snapshot_restore_continues_execution_test() {
+        let code = "
+            inc 10
+            lop
+            pfw 1
+            inc 2
+            psh
+            pbw 1
+            dec 1
+            pol
+            pfw 1
+            pop";
+
+        let mut interpreter = super::Interpreter::default();
+        interpreter.set_fuel(Some(20));
+        assert_eq!(
+            interpreter.run(code.as_bytes()).unwrap_err()._type,
+            super::InterpreterErrorType::BudgetExhausted
+        );
+
+        let bytes = interpreter.snapshot().to_bytes();
+        let snapshot = crate::Snapshot::from_bytes(&bytes).unwrap();
+
+        let mut restored = super::Interpreter::default();
+        restored.load(code.as_bytes()).unwrap();
+        restored.restore(&snapshot).unwrap();
+        assert!(restored.resume().is_ok());
+
+        interpreter.set_fuel(None);
+        assert!(interpreter.resume().is_ok());
+
+        assert_eq!(restored.get_data(), interpreter.get_data());
+        assert_eq!(restored.get_data()[1], 20);
+        assert_eq!(restored.get_stack(), interpreter.get_stack());
+    }
+
+    /// Runs the program one instruction at a time with a history and returns the snapshot before every instruction.
+    fn run_with_history(
+        interpreter: &mut super::Interpreter,
+        code: &str,
+        history: crate::History,
+    ) -> Vec<crate::Snapshot> {
+        interpreter.set_history(Some(history));
+        interpreter.set_input("xyz\n".as_bytes());
+        interpreter.set_output(std::io::sink());
+        interpreter.set_fuel(Some(0));
+        interpreter.load(code.as_bytes()).unwrap();
+
+        let mut snapshots = vec![];
+        loop {
+            snapshots.push(interpreter.snapshot());
+            interpreter.add_fuel(1);
+            match interpreter.resume() {
+                Ok(()) => break,
+                Err(e) => assert_eq!(e._type, super::InterpreterErrorType::BudgetExhausted),
+            }
+        }
+        snapshots.push(interpreter.snapshot());
+        snapshots
+    }
+
+    const HISTORY_CODE: &str = "
+        set ab
+        mrk
+        rda
+        ret
+        ptr 10
+        inc 3
+        lop
+        pfw 1
+        psh
+        psh16
+        swp
+        pop
+        pbw 1
+        dec 1
+        pol
+        ptr 2
+        clr
+        wra";
+
+    #[test]
+    fn goto_reconstructs_every_state_test() {
+        let mut interpreter = super::Interpreter::new(100);
+        let snapshots = run_with_history(
+            &mut interpreter,
+            HISTORY_CODE,
+            crate::History::new(usize::MAX),
+        );
+
+        for snapshot in snapshots.iter().rev() {
+            assert!(interpreter.goto(snapshot.instruction_count));
+            assert_eq!(&interpreter.snapshot(), snapshot);
+        }
+        assert!(!interpreter.step_back());
+    }
+
+    #[test]
+    fn goto_with_checkpoints_test() {
+        let mut history = crate::History::new(usize::MAX);
+        history.set_checkpoint_interval(Some(4));
+
+        let mut interpreter = super::Interpreter::new(100);
+        let snapshots = run_with_history(&mut interpreter, HISTORY_CODE, history);
+
+        for target in [30, 29, 17, 3, 0] {
+            assert!(interpreter.goto(target));
+            assert_eq!(interpreter.snapshot(), snapshots[target as usize]);
+        }
+        assert!(!interpreter.goto(1));
+    }
+
+    #[test]
+    fn step_back_after_tape_error_test() {
+        let code = "
+            inc 3
+            lop
+            pfw 1
+            inc 1
+            pol";
+
+        let mut interpreter = super::Interpreter::new(3);
+        interpreter.set_history(Some(crate::History::new(usize::MAX)));
+
+        let err = interpreter.run(code.as_bytes()).unwrap_err();
+        assert_eq!(err._type, super::InterpreterErrorType::Tape);
+        assert_eq!(interpreter.instruction_count(), 9);
+
+        assert!(interpreter.step_back());
+        assert_eq!(interpreter.instruction_index(), 2);
+        assert_eq!(interpreter.pointer(), 2);
+        assert_eq!(interpreter.get_data(), &vec![3, 1, 1]);
+
+        assert!(interpreter.reverse_continue(&[2]));
+        assert_eq!(interpreter.instruction_count(), 5);
+        assert_eq!(interpreter.pointer(), 1);
+        assert_eq!(interpreter.get_data(), &vec![3, 1, 0]);
+
+        assert!(!interpreter.reverse_continue(&[5]));
+        assert_eq!(interpreter.instruction_count(), 0);
+        assert_eq!(interpreter.get_data(), &vec![0, 0, 0]);
+    }
+
+    #[test]
+    fn history_budget_drops_oldest_records_test() {
+        let mut history = crate::History::new(1024);
+        history.set_checkpoint_interval(Some(8));
+
+        let mut interpreter = super::Interpreter::new(100);
+        interpreter.set_history(Some(history));
+        interpreter.run("inc 200 lop dec 1 pol".as_bytes()).unwrap();
+
+        let history = interpreter.take_history().unwrap();
+        assert!(history.memory_usage() <= 1024);
+        assert!(history.earliest().unwrap() > 0);
+        assert_eq!(
+            history.len() as u64,
+            interpreter.instruction_count() - history.earliest().unwrap()
+        );
+    }
+
+    #[test]
+    fn restore_different_program_is_err_test() {
+        let mut interpreter = super::Interpreter::default();
+        interpreter.run("inc 1".as_bytes()).unwrap();
+        let snapshot = interpreter.snapshot();
+
+        let mut other = super::Interpreter::default();
+        other.load("inc 2".as_bytes()).unwrap();
+
+        assert_eq!(
+            other.restore(&snapshot).unwrap_err()._type,
+            super::InterpreterErrorType::Snapshot
+        );
+    }
+
+    #[test]
+    fn restore_different_tape_size_is_err_test() {
+        let mut interpreter = super::Interpreter::default();
+        interpreter.run("inc 1".as_bytes()).unwrap();
+        let snapshot = interpreter.snapshot();
+
+        let mut other = super::Interpreter::new(100);
+        other.load("inc 1".as_bytes()).unwrap();
+
+        assert_eq!(
+            other.restore(&snapshot).unwrap_err()._type,
+            super::InterpreterErrorType::Snapshot
+        );
+    }
+
+    #[test]
+    fn whl_skips_loop_on_zero_test() {
+        let code = "
+            whl
+            dec 1
+            pol
+            inc 3
+            whl
+            dec 1
+            pol";
+
+        let mut interpreter = super::Interpreter::default();
+        let run_result = interpreter.run(code.as_bytes());
+
+        assert!(run_result.is_ok());
+        assert_eq!(interpreter.get_data()[0], 0);
+    }
+
+    #[test]
+    fn run_brainfuck_test() {
+        let code = "++++++[>++++++++<-]>+++ copy: [>+>+<<-]";
+
+        let mut interpreter = super::Interpreter::default();
+        let run_result = interpreter.run_brainfuck(code.as_bytes());
+
+        assert!(run_result.is_ok());
+        assert_eq!(&interpreter.get_data()[0..4], &[0, 0, 51, 51]);
+    }
+
+    #[test]
+    fn run_brainfuck_wraps_around_test() {
+        let out = crate::SharedBuffer::new();
+        let mut interpreter = super::Interpreter::default();
+        interpreter.set_output(out.clone());
+
+        let run_result = interpreter.run_brainfuck("-.>+[+]".as_bytes());
+
+        assert!(run_result.is_ok());
+        assert_eq!(out.contents(), vec![255]);
+        assert_eq!(&interpreter.get_data()[0..2], &[255, 0]);
+    }
+
+    #[test]
+    fn examples_suite_test() {
+        let tests = crate::discover("../examples").unwrap();
+
+        assert_eq!(tests.len(), 6);
+        for test in tests {
+            let outcome = test.run();
+            assert!(
+                outcome.passed(),
+                "{}\n{}",
+                outcome.path.display(),
+                outcome.diff.unwrap()
+            );
+        }
     }
 
     #[test]
@@ -0,0 +1,527 @@
+// TRNG - Brainfucks pretty sister.
+// Copyright (C) 2023 Lukas Pfeifer
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::interpreter::{step, InterpreterError};
+use crate::program::{Instruction, Program};
+use crate::stack::ValueStack;
+use crate::tape::Tape;
+use cranelift_codegen::ir::condcodes::IntCC;
+use cranelift_codegen::ir::{types, AbiParam, Block, FuncRef, InstBuilder, MemFlags, Value};
+use cranelift_codegen::settings::{self, Configurable};
+use cranelift_frontend::{FunctionBuilder, FunctionBuilderContext, Variable};
+use cranelift_jit::{JITBuilder, JITModule};
+use cranelift_module::{default_libcall_names, Linkage, Module};
+use std::any::Any;
+use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
+
+/// Returned by the callback and the compiled function if the execution failed.
+const FAILED: u64 = u64::MAX;
+
+/// Signature of the compiled function. Takes the context, the tape data, the tape length and
+/// the pointer index. Returns the final pointer index or `FAILED`.
+type CompiledFn = unsafe extern "C" fn(*mut Context, *mut u8, u64, u64) -> u64;
+
+/// The state the callback operates on.
+struct Context<'a> {
+    tape: &'a mut Tape,
+    stack: &'a mut ValueStack,
+    program: &'a Program,
+    /// The index of the failed instruction and its error.
+    error: Option<(usize, InterpreterError)>,
+    /// The payload of a panic inside the callback. The panic is resumed once the compiled code returned.
+    panic: Option<Box<dyn Any + Send>>,
+}
+
+/// A program compiled to native code.
+pub(crate) struct JitFunction {
+    module: Option<JITModule>,
+    function: CompiledFn,
+}
+
+impl Drop for JitFunction {
+    fn drop(&mut self) {
+        if let Some(module) = self.module.take() {
+            // SAFETY: The compiled function is not used after this point.
+            unsafe { module.free_memory() };
+        }
+    }
+}
+
+impl JitFunction {
+    /// Runs the compiled program on the given tape and stack.
+    /// Returns the index of the failed instruction and its error if the execution failed.
+    /// # Arguments
+    /// * `tape` - The tape to operate on.
+    /// * `stack` - The value stack to operate on.
+    /// * `program` - The program the function was compiled from.
+    pub(crate) fn run(
+        &self,
+        tape: &mut Tape,
+        stack: &mut ValueStack,
+        program: &Program,
+    ) -> Result<(), (usize, InterpreterError)> {
+        let data = tape.data.as_mut_ptr();
+        let len = tape.data.len() as u64;
+        let ptr_index = tape.ptr_index as u64;
+
+        let mut context = Context {
+            tape,
+            stack,
+            program,
+            error: None,
+            panic: None,
+        };
+
+        // SAFETY: The function was compiled from `program` and only accesses `data` within `len`.
+        // The tape data is never reallocated while the function runs.
+        let result = unsafe { (self.function)(&mut context, data, len, ptr_index) };
+
+        if let Some(payload) = context.panic {
+            resume_unwind(payload);
+        }
+
+        match context.error {
+            Some(error) => Err(error),
+            None => {
+                context.tape.ptr_index = result as usize;
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Executes the instruction at index `i` with the pointer at `p` through the interpreter.
+/// Returns the new pointer index or `FAILED`.
+extern "C" fn callback(context: *mut Context, i: u64, p: u64) -> u64 {
+    // SAFETY: The compiled code passes on the pointer it got from `JitFunction::run`.
+    let context = unsafe { &mut *context };
+    let i = i as usize;
+    let instruction = &context.program.instructions[i];
+    let position = context.program.positions[i];
+
+    context.tape.ptr_index = p as usize;
+
+    let tape = &mut *context.tape;
+    let stack = &mut *context.stack;
+    match catch_unwind(AssertUnwindSafe(|| {
+        step(tape, stack, instruction, position)
+    })) {
+        Ok(Ok(())) => context.tape.ptr_index as u64,
+        Ok(Err(e)) => {
+            context.error = Some((i, e));
+            FAILED
+        }
+        Err(payload) => {
+            context.panic = Some(payload);
+            FAILED
+        }
+    }
+}
+
+/// Compiles the given program for the host with Cranelift.
+/// Returns a description of the problem if the host is not supported.
+///
+/// Pointer movement, `inc`, `dec`, loops and the `set*` instructions run natively.
+/// All other instructions and every failed bounds or overflow check call back into `interpreter::step`,
+/// so output and errors are the same as in the interpreter.
+/// # Arguments
+/// * `program` - The program to compile.
+pub(crate) fn compile(program: &Program) -> Result<JitFunction, String> {
+    let mut flags = settings::builder();
+    flags.set("opt_level", "speed").map_err(|e| e.to_string())?;
+    flags.set("is_pic", "false").map_err(|e| e.to_string())?;
+
+    let isa = cranelift_native::builder()?
+        .finish(settings::Flags::new(flags))
+        .map_err(|e| e.to_string())?;
+
+    let mut builder = JITBuilder::with_isa(isa, default_libcall_names());
+    builder.symbol("trng_callback", callback as *const u8);
+    let mut module = JITModule::new(builder);
+
+    let ptr_type = module.target_config().pointer_type();
+
+    let mut callback_sig = module.make_signature();
+    callback_sig.params.push(AbiParam::new(ptr_type));
+    callback_sig.params.push(AbiParam::new(types::I64));
+    callback_sig.params.push(AbiParam::new(types::I64));
+    callback_sig.returns.push(AbiParam::new(types::I64));
+    let callback_id = module
+        .declare_function("trng_callback", Linkage::Import, &callback_sig)
+        .map_err(|e| e.to_string())?;
+
+    let mut ctx = module.make_context();
+    ctx.func.signature.params.push(AbiParam::new(ptr_type));
+    ctx.func.signature.params.push(AbiParam::new(ptr_type));
+    ctx.func.signature.params.push(AbiParam::new(types::I64));
+    ctx.func.signature.params.push(AbiParam::new(types::I64));
+    ctx.func.signature.returns.push(AbiParam::new(types::I64));
+    let main_id = module
+        .declare_function("trng_main", Linkage::Local, &ctx.func.signature)
+        .map_err(|e| e.to_string())?;
+
+    let mut builder_ctx = FunctionBuilderContext::new();
+    {
+        let mut b = FunctionBuilder::new(&mut ctx.func, &mut builder_ctx);
+        let callback = module.declare_func_in_func(callback_id, b.func);
+
+        let entry = b.create_block();
+        b.append_block_params_for_function_params(entry);
+        b.switch_to_block(entry);
+        b.seal_block(entry);
+
+        let params = b.block_params(entry).to_vec();
+        let p = Variable::from_u32(0);
+        b.declare_var(p, types::I64);
+        b.def_var(p, params[3]);
+
+        let fail = b.create_block();
+
+        let mut emitter = Emitter {
+            b,
+            program,
+            callback,
+            context: params[0],
+            data: params[1],
+            len: params[2],
+            p,
+            fail,
+            ptr_type,
+        };
+        emitter.emit();
+
+        let Emitter { mut b, .. } = emitter;
+        b.switch_to_block(fail);
+        b.seal_block(fail);
+        let failed = b.ins().iconst(types::I64, FAILED as i64);
+        b.ins().return_(&[failed]);
+
+        b.finalize();
+    }
+
+    module
+        .define_function(main_id, &mut ctx)
+        .map_err(|e| e.to_string())?;
+    module.clear_context(&mut ctx);
+    module.finalize_definitions().map_err(|e| e.to_string())?;
+
+    let code = module.get_finalized_function(main_id);
+
+    Ok(JitFunction {
+        module: Some(module),
+        // SAFETY: The function was defined with exactly this signature.
+        function: unsafe { std::mem::transmute::<*const u8, CompiledFn>(code) },
+    })
+}
+
+struct Emitter<'a> {
+    b: FunctionBuilder<'a>,
+    program: &'a Program,
+    callback: FuncRef,
+    context: Value,
+    data: Value,
+    len: Value,
+    p: Variable,
+    fail: Block,
+    ptr_type: types::Type,
+}
+
+impl Emitter<'_> {
+    fn emit(&mut self) {
+        // The header and exit blocks of the open loops.
+        let mut loops: Vec<(Block, Block)> = vec![];
+
+        for (i, instruction) in self.program.instructions.iter().enumerate() {
+            match instruction {
+                Instruction::Pfw(n) if *n <= u32::MAX as usize => {
+                    let p = self.b.use_var(self.p);
+                    let moved = self.b.ins().iadd_imm(p, *n as i64);
+                    let ok = self.b.ins().icmp(IntCC::UnsignedLessThan, moved, self.len);
+                    self.guarded(ok, i, |e| e.b.def_var(e.p, moved));
+                }
+                Instruction::Pbw(n) if *n <= u32::MAX as usize => {
+                    let p = self.b.use_var(self.p);
+                    let ok = self
+                        .b
+                        .ins()
+                        .icmp_imm(IntCC::UnsignedGreaterThanOrEqual, p, *n as i64);
+                    self.guarded(ok, i, |e| {
+                        let moved = e.b.ins().iadd_imm(p, -(*n as i64));
+                        e.b.def_var(e.p, moved);
+                    });
+                }
+                Instruction::Inc(by) => {
+                    let by = *by as i64;
+                    let addr = self.cell_addr(i);
+                    let v = self
+                        .b
+                        .ins()
+                        .uload8(types::I32, MemFlags::trusted(), addr, 0);
+                    let added = self.b.ins().iadd_imm(v, by);
+                    if self.program.wrapping {
+                        // The store truncates the sum to the low byte.
+                        self.b.ins().istore8(MemFlags::trusted(), added, addr, 0);
+                    } else {
+                        let ok = self
+                            .b
+                            .ins()
+                            .icmp_imm(IntCC::UnsignedLessThanOrEqual, added, 255);
+                        self.guarded(ok, i, |e| {
+                            e.b.ins().istore8(MemFlags::trusted(), added, addr, 0);
+                        });
+                    }
+                }
+                Instruction::Dec(by) => {
+                    let by = *by as i64;
+                    let addr = self.cell_addr(i);
+                    let v = self
+                        .b
+                        .ins()
+                        .uload8(types::I32, MemFlags::trusted(), addr, 0);
+                    if self.program.wrapping {
+                        let subbed = self.b.ins().iadd_imm(v, -by);
+                        self.b.ins().istore8(MemFlags::trusted(), subbed, addr, 0);
+                    } else {
+                        let ok = self
+                            .b
+                            .ins()
+                            .icmp_imm(IntCC::UnsignedGreaterThanOrEqual, v, by);
+                        self.guarded(ok, i, |e| {
+                            let subbed = e.b.ins().iadd_imm(v, -by);
+                            e.b.ins().istore8(MemFlags::trusted(), subbed, addr, 0);
+                        });
+                    }
+                }
+                Instruction::Lop(_) => {
+                    let header = self.b.create_block();
+                    let exit = self.b.create_block();
+                    self.b.ins().jump(header, &[]);
+                    self.b.switch_to_block(header);
+                    loops.push((header, exit));
+                }
+                Instruction::Whl(_) => {
+                    let header = self.b.create_block();
+                    let exit = self.b.create_block();
+                    let v = self.cell(i);
+                    self.b.ins().brif(v, header, &[], exit, &[]);
+                    self.b.switch_to_block(header);
+                    loops.push((header, exit));
+                }
+                Instruction::Pol(_) => {
+                    let (header, exit) = loops.pop().expect("Loops are balanced after parsing.");
+                    let v = self.cell(i);
+                    self.b.ins().brif(v, header, &[], exit, &[]);
+                    self.b.seal_block(header);
+                    self.b.switch_to_block(exit);
+                    self.b.seal_block(exit);
+                }
+                Instruction::Set(v) => self.set(i, v.as_bytes()),
+                Instruction::Seti8(v) => self.set(i, &v.to_be_bytes()),
+                Instruction::Seti16(v) => self.set(i, &v.to_be_bytes()),
+                Instruction::Seti32(v) => self.set(i, &v.to_be_bytes()),
+                Instruction::Seti64(v) => self.set(i, &v.to_be_bytes()),
+                Instruction::Setu8(v) => self.set(i, &v.to_be_bytes()),
+                Instruction::Setu16(v) => self.set(i, &v.to_be_bytes()),
+                Instruction::Setu32(v) => self.set(i, &v.to_be_bytes()),
+                Instruction::Setu64(v) => self.set(i, &v.to_be_bytes()),
+                Instruction::Setf32(v) => self.set(i, &v.to_be_bytes()),
+                Instruction::Setf64(v) => self.set(i, &v.to_be_bytes()),
+                _ => self.call(i),
+            }
+        }
+
+        let p = self.b.use_var(self.p);
+        self.b.ins().return_(&[p]);
+    }
+
+    /// Executes the instruction at index `i` through the interpreter.
+    fn call(&mut self, i: usize) {
+        let p = self.b.use_var(self.p);
+        let index = self.b.ins().iconst(types::I64, i as i64);
+        let inst = self.b.ins().call(self.callback, &[self.context, index, p]);
+        let result = self.b.inst_results(inst)[0];
+
+        let failed = self.b.ins().icmp_imm(IntCC::Equal, result, FAILED as i64);
+        let next = self.b.create_block();
+        self.b.ins().brif(failed, self.fail, &[], next, &[]);
+        self.b.switch_to_block(next);
+        self.b.seal_block(next);
+
+        self.b.def_var(self.p, result);
+    }
+
+    /// Runs `fast` if `ok` is set and the instruction at index `i` through the interpreter otherwise.
+    fn guarded<F>(&mut self, ok: Value, i: usize, fast: F)
+    where
+        F: FnOnce(&mut Self),
+    {
+        let fast_block = self.b.create_block();
+        let slow_block = self.b.create_block();
+        let done = self.b.create_block();
+
+        self.b.ins().brif(ok, fast_block, &[], slow_block, &[]);
+
+        self.b.switch_to_block(fast_block);
+        self.b.seal_block(fast_block);
+        fast(self);
+        self.b.ins().jump(done, &[]);
+
+        self.b.switch_to_block(slow_block);
+        self.b.seal_block(slow_block);
+        self.call(i);
+        self.b.ins().jump(done, &[]);
+
+        self.b.switch_to_block(done);
+        self.b.seal_block(done);
+    }
+
+    /// Returns the address of the current cell.
+    /// If the pointer is outside of the tape the instruction at index `i` fails through the interpreter.
+    fn cell_addr(&mut self, i: usize) -> Value {
+        let p = self.b.use_var(self.p);
+        let ok = self.b.ins().icmp(IntCC::UnsignedLessThan, p, self.len);
+
+        let inside = self.b.create_block();
+        let outside = self.b.create_block();
+        self.b.ins().brif(ok, inside, &[], outside, &[]);
+
+        self.b.switch_to_block(outside);
+        self.b.seal_block(outside);
+        self.call(i);
+        self.b.ins().jump(self.fail, &[]);
+
+        self.b.switch_to_block(inside);
+        self.b.seal_block(inside);
+        let offset = self.offset(p);
+        self.b.ins().iadd(self.data, offset)
+    }
+
+    /// Returns the value of the current cell.
+    fn cell(&mut self, i: usize) -> Value {
+        let addr = self.cell_addr(i);
+        self.b
+            .ins()
+            .uload8(types::I32, MemFlags::trusted(), addr, 0)
+    }
+
+    /// Stores the bytes in the cells starting at the pointer and moves the pointer behind them.
+    fn set(&mut self, i: usize, bytes: &[u8]) {
+        let p = self.b.use_var(self.p);
+        let end = self.b.ins().iadd_imm(p, bytes.len() as i64);
+        let ok = self
+            .b
+            .ins()
+            .icmp(IntCC::UnsignedLessThanOrEqual, end, self.len);
+
+        self.guarded(ok, i, |e| {
+            let offset = e.offset(p);
+            let addr = e.b.ins().iadd(e.data, offset);
+            for (k, byte) in bytes.iter().enumerate() {
+                let v = e.b.ins().iconst(types::I32, *byte as i64);
+                e.b.ins().istore8(MemFlags::trusted(), v, addr, k as i32);
+            }
+            e.b.def_var(e.p, end);
+        });
+    }
+
+    /// Converts the 64-bit pointer index to a pointer-sized offset.
+    fn offset(&mut self, p: Value) -> Value {
+        if self.ptr_type == types::I64 {
+            p
+        } else {
+            self.b.ins().ireduce(self.ptr_type, p)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Interpreter, InterpreterErrorType, Program, SharedBuffer};
+
+    /// Runs the program and returns its output, the error message if any and the tape.
+    fn run(program: Program, input: &[u8], jit: bool) -> (Vec<u8>, Option<String>, Vec<u8>) {
+        let out = SharedBuffer::new();
+        let mut interpreter = Interpreter::default();
+        interpreter.set_output(out.clone());
+        interpreter.set_input(std::io::Cursor::new(input.to_vec()));
+
+        let result = if jit {
+            interpreter.run_jit(program)
+        } else {
+            interpreter.run_program(program)
+        };
+
+        (
+            out.contents(),
+            result.err().map(|e| e.to_string()),
+            interpreter.get_data().to_vec(),
+        )
+    }
+
+    #[test]
+    fn conformance_corpus_matches_interpreter_test() {
+        let mut paths: Vec<_> = std::fs::read_dir("../conformance")
+            .unwrap()
+            .map(|e| e.unwrap().path())
+            .filter(|p| p.extension().and_then(|e| e.to_str()) == Some("trng"))
+            .collect();
+        paths.sort();
+
+        assert!(!paths.is_empty());
+
+        for path in paths {
+            let program = Program::from_file(&path).unwrap();
+            let input = std::fs::read(path.with_extension("in")).unwrap_or_default();
+
+            let expected = run(program.clone(), &input, false);
+            let actual = run(program, &input, true);
+
+            assert_eq!(actual, expected, "{}", path.display());
+        }
+    }
+
+    #[test]
+    fn compiled_program_runs_test() {
+        let program = Program::parse("inc 3 lop pfw 1 inc 2 pbw 1 dec 1 pol".as_bytes()).unwrap();
+
+        let mut interpreter = Interpreter::default();
+        interpreter.run_jit(program).unwrap();
+
+        assert_eq!(interpreter.get_data()[..2], [0, 6]);
+    }
+
+    #[test]
+    fn brainfuck_program_wraps_around_test() {
+        let program = Program::parse_brainfuck("-.>+[+]".as_bytes()).unwrap();
+
+        let (out, err, data) = run(program, &[], true);
+
+        assert_eq!(out, vec![255]);
+        assert_eq!(err, None);
+        assert_eq!(data[..2], [255, 0]);
+    }
+
+    #[test]
+    fn fuel_falls_back_to_interpreter_test() {
+        let program = Program::parse("inc 1 lop pol".as_bytes()).unwrap();
+
+        let mut interpreter = Interpreter::default();
+        interpreter.set_fuel(Some(100));
+        let err = interpreter.run_jit(program).unwrap_err();
+
+        assert_eq!(err._type, InterpreterErrorType::BudgetExhausted);
+    }
+}
@@ -0,0 +1,57 @@
+// TRNG - Brainfucks pretty sister.
+// Copyright (C) 2023 Lukas Pfeifer
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+/// Defines error types for lexer errors.
+#[derive(Debug, PartialEq, Eq)]
+pub enum LexErrorType {
+    Io,
+}
+
+impl std::fmt::Display for LexErrorType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LexErrorType::Io => {
+                write!(f, "IO error")
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct LexError {
+    pub description: String,
+    pub _type: LexErrorType,
+    /// Line number at which reading the code failed.
+    pub line: u32,
+}
+
+impl std::fmt::Display for LexError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Lex Error [{}]: {}", self._type, self.description)
+    }
+}
+
+impl std::error::Error for LexError {}
+
+impl LexError {
+    pub fn new(_type: LexErrorType, description: String, line: u32) -> LexError {
+        Self {
+            _type,
+            description,
+            line,
+        }
+    }
+}
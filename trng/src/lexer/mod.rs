@@ -0,0 +1,625 @@
+// TRNG - Brainfucks pretty sister.
+// Copyright (C) 2023 Lukas Pfeifer
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+mod error;
+
+pub use error::{LexError, LexErrorType};
+
+use std::borrow::Cow;
+use std::io::{ErrorKind, Read};
+
+/// Defines token types.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TokenType {
+    Pfw,
+    Pbw,
+    Ptr,
+    Mrk,
+    Ret,
+    Inc,
+    Dec,
+    Num,
+    Lop,
+    Whl,
+    Pol,
+    Rdi,
+    Rda,
+    Wrt,
+    Wrti8,
+    Wrti16,
+    Wrti32,
+    Wrti64,
+    Wrtu8,
+    Wrtu16,
+    Wrtu32,
+    Wrtu64,
+    Wrtf32,
+    Wrtf64,
+    Set,
+    Seti8,
+    Seti16,
+    Seti32,
+    Seti64,
+    Setu8,
+    Setu16,
+    Setu32,
+    Setu64,
+    Setf32,
+    Setf64,
+    Wra,
+    Clr,
+    Psh,
+    Psh16,
+    Psh32,
+    Psh64,
+    Pop,
+    Pek,
+    Dup,
+    Swp,
+    Ast,
+    Asti8,
+    Asti16,
+    Asti32,
+    Asti64,
+    Astu8,
+    Astu16,
+    Astu32,
+    Astu64,
+    Astf32,
+    Astf64,
+    Asp,
+    Comment,
+    Unknown,
+}
+
+impl TokenType {
+    /// All token types of instructions.
+    pub const INSTRUCTIONS: [TokenType; 56] = [
+        TokenType::Pfw,
+        TokenType::Pbw,
+        TokenType::Ptr,
+        TokenType::Mrk,
+        TokenType::Ret,
+        TokenType::Inc,
+        TokenType::Dec,
+        TokenType::Lop,
+        TokenType::Whl,
+        TokenType::Pol,
+        TokenType::Rdi,
+        TokenType::Rda,
+        TokenType::Wrt,
+        TokenType::Wrti8,
+        TokenType::Wrti16,
+        TokenType::Wrti32,
+        TokenType::Wrti64,
+        TokenType::Wrtu8,
+        TokenType::Wrtu16,
+        TokenType::Wrtu32,
+        TokenType::Wrtu64,
+        TokenType::Wrtf32,
+        TokenType::Wrtf64,
+        TokenType::Set,
+        TokenType::Seti8,
+        TokenType::Seti16,
+        TokenType::Seti32,
+        TokenType::Seti64,
+        TokenType::Setu8,
+        TokenType::Setu16,
+        TokenType::Setu32,
+        TokenType::Setu64,
+        TokenType::Setf32,
+        TokenType::Setf64,
+        TokenType::Wra,
+        TokenType::Clr,
+        TokenType::Psh,
+        TokenType::Psh16,
+        TokenType::Psh32,
+        TokenType::Psh64,
+        TokenType::Pop,
+        TokenType::Pek,
+        TokenType::Dup,
+        TokenType::Swp,
+        TokenType::Ast,
+        TokenType::Asti8,
+        TokenType::Asti16,
+        TokenType::Asti32,
+        TokenType::Asti64,
+        TokenType::Astu8,
+        TokenType::Astu16,
+        TokenType::Astu32,
+        TokenType::Astu64,
+        TokenType::Astf32,
+        TokenType::Astf64,
+        TokenType::Asp,
+    ];
+
+    /// Returns the op code of an instruction token type as written in TRNG source.
+    pub fn op_code(&self) -> Option<&'static str> {
+        match self {
+            TokenType::Pfw => Some("pfw"),
+            TokenType::Pbw => Some("pbw"),
+            TokenType::Ptr => Some("ptr"),
+            TokenType::Mrk => Some("mrk"),
+            TokenType::Ret => Some("ret"),
+            TokenType::Inc => Some("inc"),
+            TokenType::Dec => Some("dec"),
+            TokenType::Lop => Some("lop"),
+            TokenType::Whl => Some("whl"),
+            TokenType::Pol => Some("pol"),
+            TokenType::Rdi => Some("rdi"),
+            TokenType::Rda => Some("rda"),
+            TokenType::Wrt => Some("wrt"),
+            TokenType::Wrti8 => Some("wrti8"),
+            TokenType::Wrti16 => Some("wrti16"),
+            TokenType::Wrti32 => Some("wrti32"),
+            TokenType::Wrti64 => Some("wrti64"),
+            TokenType::Wrtu8 => Some("wrtu8"),
+            TokenType::Wrtu16 => Some("wrtu16"),
+            TokenType::Wrtu32 => Some("wrtu32"),
+            TokenType::Wrtu64 => Some("wrtu64"),
+            TokenType::Wrtf32 => Some("wrtf32"),
+            TokenType::Wrtf64 => Some("wrtf64"),
+            TokenType::Set => Some("set"),
+            TokenType::Seti8 => Some("seti8"),
+            TokenType::Seti16 => Some("seti16"),
+            TokenType::Seti32 => Some("seti32"),
+            TokenType::Seti64 => Some("seti64"),
+            TokenType::Setu8 => Some("setu8"),
+            TokenType::Setu16 => Some("setu16"),
+            TokenType::Setu32 => Some("setu32"),
+            TokenType::Setu64 => Some("setu64"),
+            TokenType::Setf32 => Some("setf32"),
+            TokenType::Setf64 => Some("setf64"),
+            TokenType::Wra => Some("wra"),
+            TokenType::Clr => Some("clr"),
+            TokenType::Psh => Some("psh"),
+            TokenType::Psh16 => Some("psh16"),
+            TokenType::Psh32 => Some("psh32"),
+            TokenType::Psh64 => Some("psh64"),
+            TokenType::Pop => Some("pop"),
+            TokenType::Pek => Some("pek"),
+            TokenType::Dup => Some("dup"),
+            TokenType::Swp => Some("swp"),
+            TokenType::Ast => Some("ast"),
+            TokenType::Asti8 => Some("asti8"),
+            TokenType::Asti16 => Some("asti16"),
+            TokenType::Asti32 => Some("asti32"),
+            TokenType::Asti64 => Some("asti64"),
+            TokenType::Astu8 => Some("astu8"),
+            TokenType::Astu16 => Some("astu16"),
+            TokenType::Astu32 => Some("astu32"),
+            TokenType::Astu64 => Some("astu64"),
+            TokenType::Astf32 => Some("astf32"),
+            TokenType::Astf64 => Some("astf64"),
+            TokenType::Asp => Some("asp"),
+            TokenType::Num | TokenType::Comment | TokenType::Unknown => None,
+        }
+    }
+
+    /// Returns whether the instruction is followed by an operand.
+    pub fn has_operand(&self) -> bool {
+        self.operand_count() > 0
+    }
+
+    /// Returns the number of operands following the instruction.
+    pub fn operand_count(&self) -> usize {
+        match self {
+            TokenType::Pfw
+            | TokenType::Pbw
+            | TokenType::Ptr
+            | TokenType::Inc
+            | TokenType::Dec
+            | TokenType::Set
+            | TokenType::Seti8
+            | TokenType::Seti16
+            | TokenType::Seti32
+            | TokenType::Seti64
+            | TokenType::Setu8
+            | TokenType::Setu16
+            | TokenType::Setu32
+            | TokenType::Setu64
+            | TokenType::Setf32
+            | TokenType::Setf64
+            | TokenType::Ast
+            | TokenType::Asti8
+            | TokenType::Asti16
+            | TokenType::Asti32
+            | TokenType::Asti64
+            | TokenType::Astu8
+            | TokenType::Astu16
+            | TokenType::Astu32
+            | TokenType::Astu64
+            | TokenType::Asp => 1,
+            TokenType::Astf32 | TokenType::Astf64 => 2,
+            _ => 0,
+        }
+    }
+}
+
+/// Defines a token.
+#[derive(Debug, Clone)]
+pub struct Token<'a> {
+    /// Token value. Borrowed from the code if it was lexed from a `&str`.
+    pub value: Cow<'a, str>,
+    /// Line number of the token.
+    pub line: u32,
+    /// Column number of the token.
+    pub column: u32,
+    /// Column type.
+    pub _type: TokenType,
+}
+
+impl<'a> Token<'a> {
+    pub fn new(value: Cow<'a, str>, line: u32, column: u32, _type: TokenType) -> Self {
+        Self {
+            value,
+            line,
+            column,
+            _type,
+        }
+    }
+
+    /// Returns the token with an owned value.
+    pub fn into_owned(self) -> Token<'static> {
+        Token::new(
+            Cow::Owned(self.value.into_owned()),
+            self.line,
+            self.column,
+            self._type,
+        )
+    }
+}
+
+/// The number of bytes `ReadLexer` reads at once.
+const CHUNK_SIZE: usize = 8192;
+
+/// The position of the lexer in the code.
+#[derive(Clone, Copy)]
+struct Cursor {
+    /// Byte index of the next character in the current text.
+    pos: usize,
+    /// Current line number.
+    line: u32,
+    /// Current column number.
+    column: u32,
+}
+
+impl Default for Cursor {
+    fn default() -> Self {
+        Self {
+            pos: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+}
+
+/// Splits TRNG code held in memory into tokens. The values of the tokens borrow from the code.
+pub struct Lexer<'a> {
+    /// The code to tokenize.
+    source: &'a str,
+    cursor: Cursor,
+}
+
+impl<'a> Lexer<'a> {
+    /// Constructor creates a lexer for the given code.
+    /// # Arguments
+    /// * `source` - The code to tokenize.
+    pub fn new(source: &'a str) -> Lexer<'a> {
+        Lexer {
+            source,
+            cursor: Cursor::default(),
+        }
+    }
+}
+
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Result<Token<'a>, LexError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        next_token(self.source, &mut self.cursor).map(Ok)
+    }
+}
+
+/// Splits TRNG code from a reader into tokens.
+///
+/// The code is read in chunks of `CHUNK_SIZE` bytes. A token cut off at the end of a chunk is
+/// carried over to the next one, so only the current chunk and the current token are held in memory.
+/// Bytes that are not valid UTF-8 are read as Latin-1 characters.
+pub struct ReadLexer<R> {
+    reader: R,
+    /// The decoded code that was not tokenized yet.
+    text: String,
+    /// The bytes of a UTF-8 sequence cut off at the end of the last chunk.
+    partial: Vec<u8>,
+    cursor: Cursor,
+    /// Whether the end of the code was reached.
+    eof: bool,
+    /// Whether reading failed.
+    failed: bool,
+}
+
+impl<R: Read> ReadLexer<R> {
+    /// Constructor creates a lexer that reads the code from the given reader.
+    /// # Arguments
+    /// * `read_from` - The `Read` instance to read the code from.
+    pub fn new(read_from: R) -> ReadLexer<R> {
+        ReadLexer {
+            reader: read_from,
+            text: String::new(),
+            partial: vec![],
+            cursor: Cursor::default(),
+            eof: false,
+            failed: false,
+        }
+    }
+
+    /// Drops the tokenized text and appends the next chunk of the code.
+    fn read_chunk(&mut self) -> Result<(), LexError> {
+        self.text.drain(..self.cursor.pos);
+        self.cursor.pos = 0;
+
+        let mut chunk = [0; CHUNK_SIZE];
+        let n = loop {
+            match self.reader.read(&mut chunk) {
+                Ok(n) => break n,
+                Err(e) if e.kind() == ErrorKind::Interrupted => (),
+                Err(e) => {
+                    return Err(LexError::new(
+                        LexErrorType::Io,
+                        e.to_string(),
+                        self.cursor.line,
+                    ))
+                }
+            }
+        };
+
+        self.eof = n == 0;
+        self.partial.extend_from_slice(&chunk[..n]);
+        let rest = decode(&self.partial, &mut self.text, self.eof);
+        self.partial.drain(..self.partial.len() - rest);
+
+        Ok(())
+    }
+}
+
+impl<R: Read> Iterator for ReadLexer<R> {
+    type Item = Result<Token<'static>, LexError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.failed {
+            let cursor = self.cursor;
+            let token = next_token(&self.text, &mut self.cursor);
+
+            // A token is complete if the text goes on behind it.
+            if self.eof || self.cursor.pos < self.text.len() {
+                if let Some(token) = token {
+                    return Some(Ok(token.into_owned()));
+                }
+                if self.eof {
+                    return None;
+                }
+            } else if token.is_some() {
+                self.cursor = cursor;
+            }
+
+            if let Err(e) = self.read_chunk() {
+                self.failed = true;
+                return Some(Err(e));
+            }
+        }
+
+        None
+    }
+}
+
+/// Appends the given bytes to the text and returns the number of bytes at the end that belong to
+/// an incomplete UTF-8 sequence. Invalid bytes are appended as Latin-1 characters.
+/// # Arguments
+/// * `bytes` - The bytes to decode.
+/// * `text` - The text to append to.
+/// * `eof` - Whether no more bytes follow. Incomplete sequences are appended as invalid bytes then.
+fn decode(mut bytes: &[u8], text: &mut String, eof: bool) -> usize {
+    loop {
+        match std::str::from_utf8(bytes) {
+            Ok(valid) => {
+                text.push_str(valid);
+                return 0;
+            }
+            Err(e) => {
+                let (valid, rest) = bytes.split_at(e.valid_up_to());
+                // SAFETY: `from_utf8` checked that the bytes up to `valid_up_to` are valid UTF-8.
+                text.push_str(unsafe { std::str::from_utf8_unchecked(valid) });
+
+                let invalid = match e.error_len() {
+                    Some(len) => len,
+                    None if !eof => return rest.len(),
+                    None => rest.len(),
+                };
+                text.extend(rest[..invalid].iter().map(|b| *b as char));
+                bytes = &rest[invalid..];
+            }
+        }
+    }
+}
+
+/// Returns the next token of the given text and moves the cursor behind it.
+/// A comment starts with ';' at the beginning of a token and runs until the end of the line.
+/// # Arguments
+/// * `text` - The text to tokenize.
+/// * `cursor` - The position in the text.
+fn next_token<'a>(text: &'a str, cursor: &mut Cursor) -> Option<Token<'a>> {
+    let rest = &text[cursor.pos..];
+    let start = rest
+        .find(|c: char| !c.is_whitespace())
+        .unwrap_or(rest.len());
+
+    for c in rest[..start].chars() {
+        if c == '\n' {
+            cursor.line += 1;
+            cursor.column = 1;
+        } else {
+            cursor.column += 1;
+        }
+    }
+    cursor.pos += start;
+
+    let rest = &rest[start..];
+    if rest.is_empty() {
+        return None;
+    }
+
+    let (len, _type) = if rest.starts_with(';') {
+        (rest.find('\n').unwrap_or(rest.len()), TokenType::Comment)
+    } else {
+        let len = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        (len, token_type(&rest[..len]))
+    };
+
+    let value = &rest[..len];
+    let token = Token::new(
+        Cow::Borrowed(match _type {
+            TokenType::Comment => value.trim_end(),
+            _ => value,
+        }),
+        cursor.line,
+        cursor.column,
+        _type,
+    );
+
+    cursor.pos += len;
+    cursor.column += value.chars().count() as u32;
+
+    Some(token)
+}
+
+/// Returns the type of a token that is not a comment.
+fn token_type(value: &str) -> TokenType {
+    match value.chars().next() {
+        Some(c) if c.is_alphabetic() => TokenType::INSTRUCTIONS
+            .iter()
+            .find(|t| t.op_code() == Some(value))
+            .cloned()
+            .unwrap_or(TokenType::Unknown),
+        Some(c) if c.is_numeric() || c.is_ascii_punctuation() => TokenType::Num,
+        _ => TokenType::Unknown,
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::nonminimal_bool)]
+mod tests {
+    use std::borrow::Cow;
+
+    #[test]
+    pub fn tokenize_file_no_error_test() {
+        let f = std::fs::File::open("../examples/example.trng").unwrap();
+
+        let tokens: Result<Vec<_>, _> = super::ReadLexer::new(f).collect();
+
+        assert!(!tokens.is_err());
+    }
+
+    #[test]
+    pub fn comment_runs_until_end_of_line_test() {
+        let tokens: Vec<_> = super::Lexer::new("inc 3 ; add three\r\nset a;b")
+            .map(Result::unwrap)
+            .collect();
+
+        assert_eq!(tokens.len(), 5);
+        assert!(tokens[2]._type == super::TokenType::Comment);
+        assert_eq!(tokens[2].value, "; add three");
+        assert_eq!(tokens[4].value, "a;b");
+    }
+
+    #[test]
+    pub fn token_column_is_first_character_test() {
+        let tokens: Vec<_> = super::ReadLexer::new("inc 3\n  wrt\n\n\tset äb wrt".as_bytes())
+            .map(Result::unwrap)
+            .collect();
+
+        assert_eq!((tokens[0].line, tokens[0].column), (1, 1));
+        assert_eq!((tokens[1].line, tokens[1].column), (1, 5));
+        assert_eq!((tokens[2].line, tokens[2].column), (2, 3));
+        assert_eq!((tokens[3].line, tokens[3].column), (4, 2));
+        assert_eq!(tokens[4].value, "äb");
+        assert_eq!((tokens[5].line, tokens[5].column), (4, 9));
+    }
+
+    #[test]
+    pub fn str_tokens_are_borrowed_test() {
+        let tokens: Vec<_> = super::Lexer::new("set abc ; note")
+            .map(Result::unwrap)
+            .collect();
+
+        assert!(tokens.iter().all(|t| matches!(t.value, Cow::Borrowed(_))));
+    }
+
+    #[test]
+    pub fn read_lexer_matches_str_lexer_test() {
+        for path in ["../examples/example.trng", "../examples/loop.trng"] {
+            let code = std::fs::read_to_string(path).unwrap();
+
+            let from_str: Vec<_> = super::Lexer::new(&code).map(Result::unwrap).collect();
+            let from_reader: Vec<_> = super::ReadLexer::new(code.as_bytes())
+                .map(Result::unwrap)
+                .collect();
+
+            assert_eq!(from_str.len(), from_reader.len());
+            for (a, b) in from_str.iter().zip(&from_reader) {
+                assert_eq!(
+                    (&a.value, a.line, a.column, &a._type),
+                    (&b.value, b.line, b.column, &b._type)
+                );
+            }
+        }
+    }
+
+    #[test]
+    pub fn invalid_utf8_is_latin1_test() {
+        let tokens: Vec<_> = super::ReadLexer::new(&b"inc 3\nset \xffa"[..])
+            .map(Result::unwrap)
+            .collect();
+
+        assert_eq!(tokens.len(), 4);
+        assert_eq!(tokens[3].value, "\u{ff}a");
+        assert_eq!((tokens[3].line, tokens[3].column), (2, 5));
+    }
+
+    #[test]
+    pub fn tokens_across_chunks_test() {
+        // Puts a multi-byte character, a token and a comment across chunk boundaries.
+        let code = format!(
+            "{}ä {}b ; {}\nwrt",
+            " ".repeat(super::CHUNK_SIZE - 1),
+            "a".repeat(super::CHUNK_SIZE),
+            "c".repeat(super::CHUNK_SIZE)
+        );
+
+        let from_str: Vec<_> = super::Lexer::new(&code).map(Result::unwrap).collect();
+        let from_reader: Vec<_> = super::ReadLexer::new(code.as_bytes())
+            .map(Result::unwrap)
+            .collect();
+
+        assert_eq!(from_reader.len(), 4);
+        for (a, b) in from_str.iter().zip(&from_reader) {
+            assert_eq!(
+                (&a.value, a.line, a.column, &a._type),
+                (&b.value, b.line, b.column, &b._type)
+            );
+        }
+    }
+}
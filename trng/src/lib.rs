@@ -14,9 +14,48 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+mod assertion;
+mod brainfuck;
+mod buffer;
+mod cancel;
+mod codegen;
+mod coverage;
+mod formatter;
+mod fuzz;
+mod golden;
+mod history;
 mod interpreter;
+#[cfg(feature = "jit")]
+mod jit;
 mod lexer;
+mod lint;
+mod listing;
+mod profile;
+mod program;
+mod snapshot;
+mod stack;
 mod tape;
+mod trace;
 
+pub use assertion::{AssertionError, AssertionErrorType};
+pub use buffer::SharedBuffer;
+pub use cancel::CancelToken;
+pub use codegen::{
+    to_brainfuck, to_c, to_wat, COptions, CodegenError, CodegenErrorType, WatOptions,
+};
+pub use coverage::Coverage;
+pub use formatter::format_source;
+pub use fuzz::{differential, shrink, Divergence, Engine, Generator, Outcome};
+pub use golden::{discover, unified_diff, GoldenOutcome, GoldenTest};
+pub use history::History;
 pub use interpreter::Interpreter;
 pub use interpreter::InterpreterError;
+pub use interpreter::InterpreterErrorType;
+pub use lexer::{LexError, LexErrorType, Lexer, ReadLexer, Token, TokenType};
+pub use lint::{lint, Diagnostic, LintCode, Severity};
+pub use listing::listing;
+pub use profile::{LoopProfile, Profiler};
+pub use program::{Instruction, ParseError, ParseErrorType, Position, Program};
+pub use snapshot::{Snapshot, SnapshotError, SnapshotErrorType};
+pub use tape::Num;
+pub use trace::{TraceFormat, Tracer};
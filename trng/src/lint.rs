@@ -0,0 +1,660 @@
+// TRNG - Brainfucks pretty sister.
+// Copyright (C) 2023 Lukas Pfeifer
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::program::{Instruction, Program};
+use std::collections::HashMap;
+
+/// Defines how severe a diagnostic is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The instruction fails whenever it is executed.
+    Error,
+    /// The code is most likely not what was intended.
+    Warning,
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Error => {
+                write!(f, "error")
+            }
+            Severity::Warning => {
+                write!(f, "warning")
+            }
+        }
+    }
+}
+
+/// Defines the kinds of diagnostics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LintCode {
+    /// `inc` or `dec` always overflows the current cell.
+    Overflow,
+    /// `pbw` always moves the pointer below 0.
+    PointerUnderflow,
+    /// An instruction that does nothing, e.g. `inc 0`.
+    NoOp,
+    /// Code that can never be reached.
+    Unreachable,
+    /// A loop without a body.
+    EmptyLoop,
+    /// A typed write reads cells that were never written.
+    UninitializedRead,
+}
+
+impl LintCode {
+    /// Returns the identifier of the code, e.g. `L001`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            LintCode::Overflow => "L001",
+            LintCode::PointerUnderflow => "L002",
+            LintCode::NoOp => "L003",
+            LintCode::Unreachable => "L004",
+            LintCode::EmptyLoop => "L005",
+            LintCode::UninitializedRead => "L006",
+        }
+    }
+
+    /// Returns the severity of diagnostics with this code.
+    pub fn severity(&self) -> Severity {
+        match self {
+            LintCode::Overflow | LintCode::PointerUnderflow => Severity::Error,
+            _ => Severity::Warning,
+        }
+    }
+}
+
+/// Defines a finding of the linter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub code: LintCode,
+    pub severity: Severity,
+    pub message: String,
+    /// Index of the offending instruction.
+    pub instruction_index: usize,
+    /// Line number of the offending instruction.
+    pub line: u32,
+    /// Column number of the offending instruction.
+    pub column: u32,
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}[{}]: {} - ln: {}, col: {}",
+            self.severity,
+            self.code.code(),
+            self.message,
+            self.line,
+            self.column
+        )
+    }
+}
+
+/// Checks the given program without running it and returns all diagnostics ordered by instruction index.
+///
+/// The linter tracks the pointer position and the cell values as long as they are known statically.
+/// Loops are analyzed once with every cell they may change set to an unknown value,
+/// so diagnostics are only reported where the problem occurs on every execution reaching the instruction.
+/// # Arguments
+/// * `program` - The program to check.
+pub fn lint(program: &Program) -> Vec<Diagnostic> {
+    let mut linter = Linter {
+        program,
+        diagnostics: vec![],
+        reported_unreachable: false,
+    };
+
+    linter.analyze(0, program.len(), State::default());
+
+    linter.diagnostics.sort_by_key(|d| d.instruction_index);
+    linter.diagnostics
+}
+
+/// The statically known content of a cell.
+#[derive(Clone, Copy)]
+struct Cell {
+    /// The value if it is known.
+    value: Option<u8>,
+    /// Whether the cell may have been written.
+    written: bool,
+}
+
+/// The statically known state of the tape.
+#[derive(Clone)]
+struct State {
+    /// The pointer index if it is known.
+    ptr: Option<usize>,
+    /// Cells that differ from the initial tape.
+    cells: HashMap<usize, Cell>,
+    /// Whether cells may have been written at unknown positions.
+    clobbered: bool,
+    /// The bookmark stack if it is known.
+    bookmarks: Option<Vec<Option<usize>>>,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self {
+            ptr: Some(0),
+            cells: HashMap::new(),
+            clobbered: false,
+            bookmarks: Some(vec![]),
+        }
+    }
+}
+
+impl State {
+    fn cell(&self, index: usize) -> Cell {
+        match self.cells.get(&index) {
+            Some(cell) => *cell,
+            None if self.clobbered => Cell {
+                value: None,
+                written: true,
+            },
+            None => Cell {
+                value: Some(0),
+                written: false,
+            },
+        }
+    }
+
+    /// Returns the value of the current cell if it is known.
+    fn current(&self) -> Option<u8> {
+        self.ptr.and_then(|p| self.cell(p).value)
+    }
+
+    /// Stores a value in the cell at the given offset from the pointer.
+    fn write(&mut self, offset: usize, value: Option<u8>) {
+        match self.ptr {
+            Some(p) => {
+                self.cells.insert(
+                    p + offset,
+                    Cell {
+                        value,
+                        written: true,
+                    },
+                );
+            }
+            None => self.clobber(),
+        }
+    }
+
+    /// Forgets the values of all cells.
+    fn clobber(&mut self) {
+        self.cells.clear();
+        self.clobbered = true;
+    }
+
+    fn advance(&mut self, steps: usize) {
+        self.ptr = self.ptr.and_then(|p| p.checked_add(steps));
+    }
+}
+
+/// Summarizes what a loop body may change.
+struct Effect {
+    /// The offsets from the pointer at the start of the body that may be written.
+    /// `None` if the body moves the pointer by an unknown amount.
+    writes: Option<Vec<usize>>,
+    /// Whether the body uses the bookmark stack.
+    bookmarks: bool,
+}
+
+struct Linter<'a> {
+    program: &'a Program,
+    diagnostics: Vec<Diagnostic>,
+    /// Whether unreachable code was already reported. Only the first unreachable instruction is reported.
+    reported_unreachable: bool,
+}
+
+impl Linter<'_> {
+    /// Analyzes the instructions in `start..end`.
+    /// Returns the state at `end` or `None` if `end` can never be reached.
+    fn analyze(&mut self, start: usize, end: usize, mut state: State) -> Option<State> {
+        let mut i = start;
+        while i < end {
+            match &self.program.instructions[i] {
+                Instruction::Lop(pol) | Instruction::Whl(pol) => {
+                    let pol = *pol;
+                    match self.analyze_loop(i, pol, state) {
+                        Some(after) => state = after,
+                        None => {
+                            if pol + 1 < end {
+                                self.unreachable(pol + 1);
+                            }
+                            return None;
+                        }
+                    }
+                    i = pol + 1;
+                    continue;
+                }
+                instruction => self.step(i, instruction, &mut state),
+            }
+
+            i += 1;
+        }
+
+        Some(state)
+    }
+
+    /// Analyzes the loop starting at `start` and ending at `end`.
+    /// Returns the state after the loop or `None` if the loop never terminates.
+    fn analyze_loop(&mut self, start: usize, end: usize, state: State) -> Option<State> {
+        if start + 1 == end {
+            self.report(
+                start,
+                LintCode::EmptyLoop,
+                format!(
+                    "'{}' is immediately followed by 'pol'. The loop never terminates if the current cell is not 0.",
+                    self.program.instructions[start].op_code()
+                ),
+            );
+        }
+
+        let entry = state.current();
+        if matches!(self.program.instructions[start], Instruction::Whl(_)) && entry == Some(0) {
+            return Some(state);
+        }
+
+        let effect = self.effect(start + 1, end);
+
+        let mut havoc = state;
+        match &effect.writes {
+            Some(writes) => {
+                for offset in writes {
+                    havoc.write(*offset, None);
+                }
+            }
+            None => {
+                havoc.ptr = None;
+                havoc.clobber();
+            }
+        }
+        if effect.bookmarks {
+            havoc.bookmarks = None;
+        }
+
+        let after = self.analyze(start + 1, end, havoc.clone())?;
+
+        // A body that neither moves the pointer nor changes the current cell never ends the loop.
+        let keeps_cell = matches!(&effect.writes, Some(writes) if !writes.contains(&0));
+        if keeps_cell && matches!(entry, Some(v) if v != 0) {
+            return None;
+        }
+
+        // The pointer is unchanged after a balanced body, so the state before the body covers all iterations.
+        let mut after = match effect.writes {
+            Some(_) => havoc,
+            None => after,
+        };
+        after.write(0, Some(0));
+
+        Some(after)
+    }
+
+    /// Returns what the instructions in `start..end` may change, relative to the pointer at `start`.
+    fn effect(&self, start: usize, end: usize) -> Effect {
+        let mut writes = vec![];
+        let mut bookmarks = false;
+        let mut offset: Option<isize> = Some(0);
+
+        let mut i = start;
+        while i < end {
+            let instruction = &self.program.instructions[i];
+
+            if let Instruction::Lop(pol) | Instruction::Whl(pol) = instruction {
+                let inner = self.effect(i + 1, *pol);
+                bookmarks |= inner.bookmarks;
+                match (inner.writes, offset) {
+                    (Some(inner), Some(o)) => {
+                        writes.extend(inner.into_iter().map(|w| o + w as isize))
+                    }
+                    _ => offset = None,
+                }
+                i = pol + 1;
+                continue;
+            }
+
+            let width = match instruction {
+                Instruction::Inc(_) | Instruction::Dec(_) | Instruction::Rdi => 1,
+                Instruction::Set(v) => v.len(),
+                Instruction::Seti8(_) | Instruction::Setu8(_) => 1,
+                Instruction::Seti16(_) | Instruction::Setu16(_) => 2,
+                Instruction::Seti32(_) | Instruction::Setu32(_) | Instruction::Setf32(_) => 4,
+                Instruction::Seti64(_) | Instruction::Setu64(_) | Instruction::Setf64(_) => 8,
+                Instruction::Pop | Instruction::Pek => 8,
+                _ => 0,
+            };
+
+            if let Some(o) = offset {
+                writes.extend((0..width).map(|k| o + k as isize));
+            }
+
+            offset = match instruction {
+                Instruction::Pfw(n) => offset.map(|o| o + *n as isize),
+                Instruction::Pbw(n) => offset.map(|o| o - *n as isize),
+                Instruction::Inc(_)
+                | Instruction::Dec(_)
+                | Instruction::Rdi
+                | Instruction::Pop
+                | Instruction::Pek => offset,
+                Instruction::Ptr(_)
+                | Instruction::Ret
+                | Instruction::Wra
+                | Instruction::Rda
+                | Instruction::Clr => None,
+                // The remaining instructions that write move the pointer behind the written cells.
+                _ => offset.map(|o| o + width as isize),
+            };
+
+            if let Instruction::Mrk | Instruction::Ret = instruction {
+                bookmarks = true;
+            }
+
+            i += 1;
+        }
+
+        let writes = match offset {
+            Some(0) if writes.iter().all(|w| *w >= 0) => {
+                Some(writes.into_iter().map(|w| w as usize).collect())
+            }
+            _ => None,
+        };
+
+        Effect { writes, bookmarks }
+    }
+
+    /// Analyzes a single instruction that is not a loop instruction.
+    fn step(&mut self, i: usize, instruction: &Instruction, state: &mut State) {
+        match instruction {
+            Instruction::Pfw(0)
+            | Instruction::Pbw(0)
+            | Instruction::Inc(0)
+            | Instruction::Dec(0) => {
+                self.report(
+                    i,
+                    LintCode::NoOp,
+                    format!("'{}' has no effect.", instruction),
+                );
+            }
+            _ => (),
+        }
+
+        match instruction {
+            Instruction::Pfw(n) => state.advance(*n),
+            Instruction::Pbw(n) => match state.ptr {
+                Some(p) if p < *n => {
+                    self.report(
+                        i,
+                        LintCode::PointerUnderflow,
+                        format!(
+                            "'{}' moves the pointer below 0. The pointer is at index {}.",
+                            instruction, p
+                        ),
+                    );
+                    state.ptr = None;
+                }
+                Some(p) => state.ptr = Some(p - n),
+                None => (),
+            },
+            Instruction::Ptr(n) => state.ptr = Some(*n),
+            Instruction::Mrk => {
+                if let Some(bookmarks) = &mut state.bookmarks {
+                    bookmarks.push(state.ptr);
+                }
+            }
+            Instruction::Ret => {
+                state.ptr = match &mut state.bookmarks {
+                    Some(bookmarks) => bookmarks.pop().flatten(),
+                    None => None,
+                };
+            }
+            Instruction::Inc(n) if self.program.wrapping => {
+                let current = state.current();
+                state.write(0, current.map(|v| v.wrapping_add(*n)));
+            }
+            Instruction::Dec(n) if self.program.wrapping => {
+                let current = state.current();
+                state.write(0, current.map(|v| v.wrapping_sub(*n)));
+            }
+            Instruction::Inc(n) => {
+                let current = state.current();
+                if let Some(v) = current {
+                    if v.checked_add(*n).is_none() {
+                        self.overflow(i, instruction, v);
+                    }
+                }
+                state.write(0, current.and_then(|v| v.checked_add(*n)));
+            }
+            Instruction::Dec(n) => {
+                let current = state.current();
+                if let Some(v) = current {
+                    if v.checked_sub(*n).is_none() {
+                        self.overflow(i, instruction, v);
+                    }
+                }
+                state.write(0, current.and_then(|v| v.checked_sub(*n)));
+            }
+            Instruction::Rdi => state.write(0, None),
+            Instruction::Set(v) => set(state, v.as_bytes()),
+            Instruction::Seti8(v) => set(state, &v.to_be_bytes()),
+            Instruction::Seti16(v) => set(state, &v.to_be_bytes()),
+            Instruction::Seti32(v) => set(state, &v.to_be_bytes()),
+            Instruction::Seti64(v) => set(state, &v.to_be_bytes()),
+            Instruction::Setu8(v) => set(state, &v.to_be_bytes()),
+            Instruction::Setu16(v) => set(state, &v.to_be_bytes()),
+            Instruction::Setu32(v) => set(state, &v.to_be_bytes()),
+            Instruction::Setu64(v) => set(state, &v.to_be_bytes()),
+            Instruction::Setf32(v) => set(state, &v.to_be_bytes()),
+            Instruction::Setf64(v) => set(state, &v.to_be_bytes()),
+            Instruction::Wrti16 | Instruction::Wrtu16 => self.typed_read(i, instruction, state, 2),
+            Instruction::Wrti32 | Instruction::Wrtu32 | Instruction::Wrtf32 => {
+                self.typed_read(i, instruction, state, 4)
+            }
+            Instruction::Wrti64 | Instruction::Wrtu64 | Instruction::Wrtf64 => {
+                self.typed_read(i, instruction, state, 8)
+            }
+            Instruction::Wra => state.ptr = None,
+            Instruction::Rda | Instruction::Clr => {
+                state.ptr = None;
+                state.clobber();
+            }
+            Instruction::Pop | Instruction::Pek => {
+                // Values have up to 8 bytes.
+                for offset in 0..8 {
+                    state.write(offset, None);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Reports typed writes of multiple cells if one of the cells was never written.
+    fn typed_read(&mut self, i: usize, instruction: &Instruction, state: &State, width: usize) {
+        let Some(p) = state.ptr else {
+            return;
+        };
+
+        let unwritten = (0..width).filter(|k| !state.cell(p + k).written).count();
+        if unwritten > 0 {
+            self.report(
+                i,
+                LintCode::UninitializedRead,
+                format!(
+                    "'{}' reads {} cell(s) that were never written.",
+                    instruction, unwritten
+                ),
+            );
+        }
+    }
+
+    fn overflow(&mut self, i: usize, instruction: &Instruction, value: u8) {
+        self.report(
+            i,
+            LintCode::Overflow,
+            format!(
+                "'{}' always overflows. The current cell holds {}.",
+                instruction, value
+            ),
+        );
+    }
+
+    fn unreachable(&mut self, i: usize) {
+        if self.reported_unreachable {
+            return;
+        }
+        self.reported_unreachable = true;
+
+        self.report(
+            i,
+            LintCode::Unreachable,
+            "Unreachable code after a loop that never terminates.".to_string(),
+        );
+    }
+
+    fn report(&mut self, i: usize, code: LintCode, message: String) {
+        let pos = self.program.positions.get(i).copied().unwrap_or_default();
+        self.diagnostics.push(Diagnostic {
+            code,
+            severity: code.severity(),
+            message,
+            instruction_index: i,
+            line: pos.line,
+            column: pos.column,
+        });
+    }
+}
+
+/// Stores the given bytes from the pointer on and moves the pointer behind them.
+fn set(state: &mut State, bytes: &[u8]) {
+    for (offset, b) in bytes.iter().enumerate() {
+        state.write(offset, Some(*b));
+    }
+    state.advance(bytes.len());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{lint, LintCode, Severity};
+    use crate::Program;
+
+    fn codes(code: &str) -> Vec<(LintCode, u32)> {
+        let program = Program::parse(code.as_bytes()).unwrap();
+        lint(&program).iter().map(|d| (d.code, d.line)).collect()
+    }
+
+    #[test]
+    fn clean_program_has_no_diagnostics_test() {
+        let program = Program::from_file("../examples/example.trng").unwrap();
+
+        assert!(lint(&program).is_empty());
+    }
+
+    #[test]
+    fn inc_overflow_is_error_test() {
+        let program = Program::parse("inc 200\ninc 100".as_bytes()).unwrap();
+        let diagnostics = lint(&program);
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, LintCode::Overflow);
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+        assert_eq!((diagnostics[0].line, diagnostics[0].column), (2, 1));
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "error[L001]: 'inc 100' always overflows. The current cell holds 200. - ln: 2, col: 1"
+        );
+    }
+
+    #[test]
+    fn dec_on_empty_cell_is_error_test() {
+        assert_eq!(codes("pfw 3\ndec 1"), vec![(LintCode::Overflow, 2)]);
+    }
+
+    #[test]
+    fn overflow_after_unknown_value_is_not_reported_test() {
+        assert!(codes("rdi\ninc 255").is_empty());
+    }
+
+    #[test]
+    fn pbw_below_zero_is_error_test() {
+        assert_eq!(
+            codes("pfw 2\nset ab\npbw 5"),
+            vec![(LintCode::PointerUnderflow, 3)]
+        );
+    }
+
+    #[test]
+    fn pbw_after_wra_is_not_reported_test() {
+        assert!(codes("set Hi\npbw 2\nwra\npbw 1").is_empty());
+    }
+
+    #[test]
+    fn no_op_is_warning_test() {
+        assert_eq!(
+            codes("pfw 0\ninc 0\ninc 1"),
+            vec![(LintCode::NoOp, 1), (LintCode::NoOp, 2)]
+        );
+    }
+
+    #[test]
+    fn empty_loop_is_warning_test() {
+        assert_eq!(codes("lop\npol"), vec![(LintCode::EmptyLoop, 1)]);
+    }
+
+    #[test]
+    fn code_after_infinite_loop_is_unreachable_test() {
+        assert_eq!(
+            codes("inc 1\nwhl\n    pfw 1\n    inc 1\n    pbw 1\npol\nwrt\nwrt"),
+            vec![(LintCode::Unreachable, 7)]
+        );
+    }
+
+    #[test]
+    fn terminating_loop_is_not_reported_test() {
+        assert!(codes("inc 5\nwhl\n    dec 1\npol\ninc 1").is_empty());
+    }
+
+    #[test]
+    fn cell_is_zero_after_loop_test() {
+        assert_eq!(
+            codes("inc 5\nwhl\n    dec 1\npol\ndec 1"),
+            vec![(LintCode::Overflow, 5)]
+        );
+    }
+
+    #[test]
+    fn unbalanced_loop_forgets_pointer_test() {
+        assert!(codes("inc 1\nwhl\n    pfw 1\npol\npbw 5\ndec 1").is_empty());
+    }
+
+    #[test]
+    fn typed_write_of_unwritten_cells_is_warning_test() {
+        assert_eq!(
+            codes("setu16 500\npbw 2\nwrtu32\npbw 0"),
+            vec![(LintCode::UninitializedRead, 3), (LintCode::NoOp, 4)]
+        );
+        assert!(codes("setu32 500\npbw 4\nwrtu32").is_empty());
+    }
+
+    #[test]
+    fn wrapping_program_does_not_overflow_test() {
+        let program = Program::parse_brainfuck("-.".as_bytes()).unwrap();
+
+        assert!(lint(&program).is_empty());
+    }
+}
@@ -0,0 +1,101 @@
+// TRNG - Brainfucks pretty sister.
+// Copyright (C) 2023 Lukas Pfeifer
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::program::{Instruction, Program};
+
+/// Returns a listing of the program with one instruction per line.
+///
+/// Every line holds the instruction index as tracked by the interpreter, the source position,
+/// the op code, the decoded operand and the target of loop instructions.
+/// Instructions merged from several source instructions list the positions they came from.
+/// # Arguments
+/// * `program` - The program to list.
+pub fn listing(program: &Program) -> String {
+    let width = program.len().saturating_sub(1).to_string().len();
+    let mut listing = String::new();
+
+    for (i, instruction) in program.instructions.iter().enumerate() {
+        let position = program.positions.get(i).copied().unwrap_or_default();
+        let location = format!("{}:{}", position.line, position.column);
+
+        let mut line = format!(
+            "{:>width$}  {:<9} {:<7} {:<20}",
+            i,
+            location,
+            instruction.op_code(),
+            operand(instruction),
+            width = width
+        );
+
+        match instruction {
+            Instruction::Lop(target) | Instruction::Whl(target) | Instruction::Pol(target) => {
+                line += &format!(" -> {}", target);
+            }
+            _ => (),
+        }
+
+        if let Some(origins) = program.origins.get(i).filter(|o| !o.is_empty()) {
+            let from: Vec<String> = origins
+                .iter()
+                .map(|p| format!("{}:{}", p.line, p.column))
+                .collect();
+            line += &format!(" ; from {}", from.join(" "));
+        }
+
+        listing += line.trim_end();
+        listing.push('\n');
+    }
+
+    listing
+}
+
+/// Returns the operand as it is used by the interpreter. Text is quoted and escaped.
+fn operand(instruction: &Instruction) -> String {
+    match instruction {
+        Instruction::Set(v) => format!("{:?}", v),
+        _ => instruction.operand().unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Program;
+
+    #[test]
+    fn listing_shows_positions_and_targets_test() {
+        let program = Program::parse("set Hi\nlop\n    dec 1\npol".as_bytes()).unwrap();
+
+        assert_eq!(
+            super::listing(&program),
+            "0  1:1       set     \"Hi\"\n\
+             1  2:1       lop                          -> 3\n\
+             2  3:5       dec     1\n\
+             3  4:1       pol                          -> 1\n"
+        );
+    }
+
+    #[test]
+    fn listing_shows_folded_origins_test() {
+        let program = Program::parse_brainfuck("+\n+>".as_bytes()).unwrap();
+
+        let listing = super::listing(&program);
+        let lines: Vec<&str> = listing.lines().collect();
+
+        assert!(lines[0].ends_with("; from 1:1 2:1"));
+        assert!(lines[0].contains("inc     2"));
+        assert!(!lines[1].contains("from"));
+    }
+}
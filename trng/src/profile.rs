@@ -0,0 +1,446 @@
+// TRNG - Brainfucks pretty sister.
+// Copyright (C) 2023 Lukas Pfeifer
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::program::{Instruction, Program};
+use crate::trace::json_string;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Defines the measurements of a single loop.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoopProfile {
+    /// Index of the `lop` or `whl` instruction.
+    pub start: usize,
+    /// Index of the matching `pol` instruction.
+    pub end: usize,
+    /// How often the loop was reached, including skipped `whl` loops.
+    pub entries: u64,
+    /// How often the loop body was started.
+    pub iterations: u64,
+    /// Time spent in the loop including nested loops.
+    pub time: Duration,
+}
+
+/// A node of the tree of nested loops. Used for the folded stack export.
+struct Frame {
+    parent: usize,
+    /// Index of the loop instruction. `usize::MAX` for the root.
+    start: usize,
+    /// Instructions executed in this loop but not in a nested loop.
+    count: u64,
+}
+
+/// Counts executed instructions and measures loops.
+///
+/// Set it with `Interpreter::set_profiler` and take it back with `Interpreter::take_profiler` after the run.
+/// Running the same program again adds to the existing counts.
+pub struct Profiler {
+    counts: Vec<u64>,
+    loops: Vec<LoopProfile>,
+    /// Maps the index of a loop instruction to its entry in `loops`.
+    loop_ids: HashMap<usize, usize>,
+    max_pointer: usize,
+    bytes_read: u64,
+    bytes_written: u64,
+    /// I/O counters of the tape when the profiling started.
+    io_base: (u64, u64),
+    frames: Vec<Frame>,
+    children: HashMap<(usize, usize), usize>,
+    current: usize,
+    /// The loops that are currently running and when they were entered.
+    active: Vec<(usize, Instant)>,
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Profiler {
+    /// Creates a profiler without any measurements.
+    pub fn new() -> Self {
+        Self {
+            counts: vec![],
+            loops: vec![],
+            loop_ids: HashMap::new(),
+            max_pointer: 0,
+            bytes_read: 0,
+            bytes_written: 0,
+            io_base: (0, 0),
+            frames: vec![Frame {
+                parent: 0,
+                start: usize::MAX,
+                count: 0,
+            }],
+            children: HashMap::new(),
+            current: 0,
+            active: vec![],
+        }
+    }
+
+    /// Returns how often each instruction was executed, indexed by instruction index.
+    pub fn instruction_counts(&self) -> &[u64] {
+        &self.counts
+    }
+
+    /// Returns the measurements of all loops ordered by their position in the program.
+    pub fn loops(&self) -> &[LoopProfile] {
+        &self.loops
+    }
+
+    /// Returns the highest pointer index reached.
+    pub fn max_pointer(&self) -> usize {
+        self.max_pointer
+    }
+
+    /// Returns the number of bytes read from the input.
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes_read
+    }
+
+    /// Returns the number of bytes written to the output.
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written
+    }
+
+    /// Prepares the profiler before the execution starts or resumes.
+    /// # Arguments
+    /// * `program` - The executed program.
+    /// * `instruction_index` - The index the execution starts at.
+    /// * `io` - The number of bytes the tape has read and written so far.
+    pub(crate) fn begin(&mut self, program: &Program, instruction_index: usize, io: (u64, u64)) {
+        if self.counts.len() != program.len() {
+            *self = Self::new();
+            self.counts = vec![0; program.len()];
+
+            for (i, instruction) in program.instructions.iter().enumerate() {
+                if let Instruction::Lop(end) | Instruction::Whl(end) = instruction {
+                    self.loop_ids.insert(i, self.loops.len());
+                    self.loops.push(LoopProfile {
+                        start: i,
+                        end: *end,
+                        entries: 0,
+                        iterations: 0,
+                        time: Duration::ZERO,
+                    });
+                }
+            }
+        }
+
+        if instruction_index == 0 {
+            self.current = 0;
+            self.active.clear();
+        }
+
+        self.io_base = (
+            io.0.saturating_sub(self.bytes_read),
+            io.1.saturating_sub(self.bytes_written),
+        );
+    }
+
+    /// Records an executed instruction.
+    /// # Arguments
+    /// * `program` - The executed program.
+    /// * `index` - The index of the executed instruction.
+    /// * `next` - The index of the instruction executed next.
+    /// * `ptr_index` - The pointer index after the instruction was executed.
+    /// * `io` - The number of bytes the tape has read and written so far.
+    pub(crate) fn record(
+        &mut self,
+        program: &Program,
+        index: usize,
+        next: usize,
+        ptr_index: usize,
+        io: (u64, u64),
+    ) {
+        self.counts[index] += 1;
+        self.frames[self.current].count += 1;
+        self.max_pointer = self.max_pointer.max(ptr_index);
+        self.bytes_read = io.0 - self.io_base.0;
+        self.bytes_written = io.1 - self.io_base.1;
+
+        match program.instructions[index] {
+            Instruction::Lop(_) => self.enter(index),
+            Instruction::Whl(end) => {
+                if next == end + 1 {
+                    self.loops[self.loop_ids[&index]].entries += 1;
+                } else {
+                    self.enter(index);
+                }
+            }
+            Instruction::Pol(start) => {
+                if next == index + 1 {
+                    self.exit();
+                } else {
+                    self.loops[self.loop_ids[&start]].iterations += 1;
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn enter(&mut self, start: usize) {
+        let id = self.loop_ids[&start];
+        self.loops[id].entries += 1;
+        self.loops[id].iterations += 1;
+        self.active.push((id, Instant::now()));
+
+        let next_frame = self.frames.len();
+        let frame = *self
+            .children
+            .entry((self.current, start))
+            .or_insert(next_frame);
+        if frame == next_frame {
+            self.frames.push(Frame {
+                parent: self.current,
+                start,
+                count: 0,
+            });
+        }
+        self.current = frame;
+    }
+
+    fn exit(&mut self) {
+        if let Some((id, entered)) = self.active.pop() {
+            self.loops[id].time += entered.elapsed();
+        }
+        self.current = self.frames[self.current].parent;
+    }
+
+    /// Returns the number of instructions executed inside the given loop including nested loops.
+    fn loop_instructions(&self, profile: &LoopProfile) -> u64 {
+        self.counts[profile.start..=profile.end].iter().sum()
+    }
+
+    /// Returns the source annotated with the number of executed instructions on each line.
+    /// # Arguments
+    /// * `program` - The profiled program.
+    /// * `source` - The source code of the program.
+    pub fn annotate(&self, program: &Program, source: &str) -> String {
+        let mut per_line: HashMap<u32, u64> = HashMap::new();
+        for (i, count) in self.counts.iter().enumerate() {
+            if let Some(position) = program.positions.get(i) {
+                *per_line.entry(position.line).or_default() += count;
+            }
+        }
+
+        let mut annotated = String::new();
+        for (i, line) in source.lines().enumerate() {
+            let margin = match per_line.get(&(i as u32 + 1)) {
+                Some(count) => count.to_string(),
+                None => String::new(),
+            };
+            annotated += format!("{:>12} | {}", margin, line).trim_end();
+            annotated.push('\n');
+        }
+
+        annotated
+    }
+
+    /// Returns a summary of the run and all loops ordered by the time spent in them.
+    /// # Arguments
+    /// * `program` - The profiled program.
+    pub fn report(&self, program: &Program) -> String {
+        let mut report = format!(
+            "{} instructions executed, max pointer index {}, {} bytes read, {} bytes written\n",
+            self.counts.iter().sum::<u64>(),
+            self.max_pointer,
+            self.bytes_read,
+            self.bytes_written
+        );
+
+        let mut loops: Vec<&LoopProfile> = self.loops.iter().collect();
+        loops.sort_by(|a, b| b.time.cmp(&a.time).then(a.start.cmp(&b.start)));
+
+        for l in loops {
+            let position = program.positions.get(l.start).copied().unwrap_or_default();
+            report += &format!(
+                "{} at {}:{}: {:?}, {} entries, {} iterations, {} instructions\n",
+                program.instructions[l.start].op_code(),
+                position.line,
+                position.column,
+                l.time,
+                l.entries,
+                l.iterations,
+                self.loop_instructions(l)
+            );
+        }
+
+        report
+    }
+
+    /// Returns all measurements as a JSON object.
+    /// # Arguments
+    /// * `program` - The profiled program.
+    pub fn to_json(&self, program: &Program) -> String {
+        let instructions: Vec<String> = self
+            .counts
+            .iter()
+            .enumerate()
+            .map(|(i, count)| {
+                let position = program.positions.get(i).copied().unwrap_or_default();
+                format!(
+                    "{{\"index\":{},\"line\":{},\"column\":{},\"instruction\":{},\"count\":{}}}",
+                    i,
+                    position.line,
+                    position.column,
+                    json_string(&program.instructions[i].to_string()),
+                    count
+                )
+            })
+            .collect();
+
+        let loops: Vec<String> = self
+            .loops
+            .iter()
+            .map(|l| {
+                let position = program.positions.get(l.start).copied().unwrap_or_default();
+                format!(
+                    "{{\"start\":{},\"end\":{},\"line\":{},\"column\":{},\"entries\":{},\"iterations\":{},\"instructions\":{},\"time_ns\":{}}}",
+                    l.start,
+                    l.end,
+                    position.line,
+                    position.column,
+                    l.entries,
+                    l.iterations,
+                    self.loop_instructions(l),
+                    l.time.as_nanos()
+                )
+            })
+            .collect();
+
+        format!(
+            "{{\"instructions\":[{}],\"loops\":[{}],\"max_pointer\":{},\"bytes_read\":{},\"bytes_written\":{}}}",
+            instructions.join(","),
+            loops.join(","),
+            self.max_pointer,
+            self.bytes_read,
+            self.bytes_written
+        )
+    }
+
+    /// Returns the executed instructions per loop nesting in the folded stack format of flame graph tools.
+    ///
+    /// Every line holds the nested loops separated by `;` followed by the number of instructions
+    /// executed in the innermost loop, e.g. `program;lop@2:1;whl@4:5 120`.
+    /// # Arguments
+    /// * `program` - The profiled program.
+    pub fn to_folded(&self, program: &Program) -> String {
+        let mut lines = vec![];
+
+        for (i, frame) in self.frames.iter().enumerate() {
+            if frame.count == 0 {
+                continue;
+            }
+
+            let mut names = vec![];
+            let mut f = i;
+            while f != 0 {
+                let start = self.frames[f].start;
+                let position = program.positions.get(start).copied().unwrap_or_default();
+                names.push(format!(
+                    "{}@{}:{}",
+                    program.instructions[start].op_code(),
+                    position.line,
+                    position.column
+                ));
+                f = self.frames[f].parent;
+            }
+            names.push("program".to_string());
+            names.reverse();
+
+            lines.push(format!("{} {}", names.join(";"), frame.count));
+        }
+
+        lines.sort();
+        lines.iter().map(|l| format!("{}\n", l)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Profiler;
+    use crate::{Interpreter, Program, SharedBuffer};
+
+    const CODE: &str = "inc 3\nlop\n    pfw 1\n    inc 2\n    whl\n        dec 1\n        wrt\n    pol\n    pbw 1\n    dec 1\npol";
+
+    fn profile(code: &str) -> (Program, Profiler) {
+        let program = Program::parse(code.as_bytes()).unwrap();
+
+        let mut interpreter = Interpreter::default();
+        interpreter.set_output(SharedBuffer::new());
+        interpreter.set_profiler(Some(Profiler::new()));
+        interpreter.run_program(program.clone()).unwrap();
+
+        (program, interpreter.take_profiler().unwrap())
+    }
+
+    #[test]
+    fn counts_instructions_and_loops_test() {
+        let (_, profiler) = profile(CODE);
+
+        assert_eq!(profiler.instruction_counts()[0], 1);
+        assert_eq!(profiler.instruction_counts()[5], 6);
+        assert_eq!(profiler.loops()[0].entries, 1);
+        assert_eq!(profiler.loops()[0].iterations, 3);
+        assert_eq!(profiler.loops()[1].entries, 3);
+        assert_eq!(profiler.loops()[1].iterations, 6);
+        assert_eq!(profiler.max_pointer(), 1);
+        assert_eq!(profiler.bytes_written(), 6);
+    }
+
+    #[test]
+    fn skipped_whl_is_entry_without_iteration_test() {
+        let (_, profiler) = profile("whl\n    inc 1\npol");
+
+        assert_eq!(profiler.loops()[0].entries, 1);
+        assert_eq!(profiler.loops()[0].iterations, 0);
+        assert_eq!(profiler.instruction_counts(), &[1, 0, 0]);
+    }
+
+    #[test]
+    fn annotate_shows_counts_per_line_test() {
+        let (program, profiler) = profile("inc 2\nlop\n    dec 1\npol");
+
+        assert_eq!(
+            profiler.annotate(&program, "inc 2\nlop\n    dec 1\npol"),
+            "           1 | inc 2\n           1 | lop\n           2 |     dec 1\n           2 | pol\n"
+        );
+    }
+
+    #[test]
+    fn folded_stacks_follow_nesting_test() {
+        let (program, profiler) = profile(CODE);
+
+        assert_eq!(
+            profiler.to_folded(&program),
+            "program 2\nprogram;lop@2:1 18\nprogram;lop@2:1;whl@5:5 18\n"
+        );
+    }
+
+    #[test]
+    fn json_contains_loops_test() {
+        let (program, profiler) = profile("inc 1\nlop\n    dec 1\npol");
+        let json = profiler.to_json(&program);
+
+        assert!(json.starts_with(
+            r#"{"instructions":[{"index":0,"line":1,"column":1,"instruction":"inc 1","count":1}"#
+        ));
+        assert!(json.contains(r#""entries":1,"iterations":1,"instructions":3"#));
+        assert!(json.ends_with(r#""max_pointer":0,"bytes_read":0,"bytes_written":0}"#));
+    }
+}
@@ -0,0 +1,96 @@
+// TRNG - Brainfucks pretty sister.
+// Copyright (C) 2023 Lukas Pfeifer
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::lexer::LexError;
+
+/// Defines error types for parse errors.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ParseErrorType {
+    UnknownToken,
+    Operand,
+    Loop,
+    Io,
+}
+
+impl std::fmt::Display for ParseErrorType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseErrorType::UnknownToken => {
+                write!(f, "Unknown token error")
+            }
+            ParseErrorType::Operand => {
+                write!(f, "Operand error")
+            }
+            ParseErrorType::Loop => {
+                write!(f, "Loop error")
+            }
+            ParseErrorType::Io => {
+                write!(f, "IO error")
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ParseError {
+    pub description: String,
+    pub _type: ParseErrorType,
+    /// Line number of the offending token. 0 if unknown.
+    pub line: u32,
+    /// Column number of the offending token. 0 if unknown.
+    pub column: u32,
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Parse Error [{}]: {}", self._type, self.description)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl From<std::io::Error> for ParseError {
+    fn from(value: std::io::Error) -> Self {
+        Self {
+            description: value.to_string(),
+            _type: ParseErrorType::Io,
+            line: 0,
+            column: 0,
+        }
+    }
+}
+
+impl From<LexError> for ParseError {
+    fn from(value: LexError) -> Self {
+        Self {
+            description: value.to_string(),
+            _type: ParseErrorType::Io,
+            line: value.line,
+            column: 0,
+        }
+    }
+}
+
+impl ParseError {
+    pub fn new(_type: ParseErrorType, description: String, line: u32, column: u32) -> ParseError {
+        Self {
+            _type,
+            description,
+            line,
+            column,
+        }
+    }
+}
@@ -0,0 +1,209 @@
+// TRNG - Brainfucks pretty sister.
+// Copyright (C) 2023 Lukas Pfeifer
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+/// Defines an instruction of a parsed program.
+///
+/// Loop instructions carry the index of their matching counterpart.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Instruction {
+    Pfw(usize),
+    Pbw(usize),
+    Ptr(usize),
+    Mrk,
+    Ret,
+    Inc(u8),
+    Dec(u8),
+    /// Starts a loop. Holds the index of the matching `Pol`.
+    Lop(usize),
+    /// Starts a loop that is skipped if the current cell is 0. Holds the index of the matching `Pol`.
+    Whl(usize),
+    /// Ends a loop. Holds the index of the matching `Lop` or `Whl`.
+    Pol(usize),
+    Rdi,
+    Rda,
+    Wrt,
+    Wrti8,
+    Wrti16,
+    Wrti32,
+    Wrti64,
+    Wrtu8,
+    Wrtu16,
+    Wrtu32,
+    Wrtu64,
+    Wrtf32,
+    Wrtf64,
+    Set(String),
+    Seti8(i8),
+    Seti16(i16),
+    Seti32(i32),
+    Seti64(i64),
+    Setu8(u8),
+    Setu16(u16),
+    Setu32(u32),
+    Setu64(u64),
+    Setf32(f32),
+    Setf64(f64),
+    Wra,
+    Clr,
+    Psh,
+    Psh16,
+    Psh32,
+    Psh64,
+    Pop,
+    Pek,
+    Dup,
+    Swp,
+    /// Asserts that the current cell holds the value.
+    Ast(u8),
+    Asti8(i8),
+    Asti16(i16),
+    Asti32(i32),
+    Asti64(i64),
+    Astu8(u8),
+    Astu16(u16),
+    Astu32(u32),
+    Astu64(u64),
+    /// Asserts that the float at the pointer differs from the first value by at most the second.
+    Astf32(f32, f32),
+    /// Asserts that the float at the pointer differs from the first value by at most the second.
+    Astf64(f64, f64),
+    /// Asserts that the pointer is at the index.
+    Asp(usize),
+}
+
+impl Instruction {
+    /// Returns the op code of the instruction as written in TRNG source.
+    pub fn op_code(&self) -> &'static str {
+        match self {
+            Instruction::Pfw(_) => "pfw",
+            Instruction::Pbw(_) => "pbw",
+            Instruction::Ptr(_) => "ptr",
+            Instruction::Mrk => "mrk",
+            Instruction::Ret => "ret",
+            Instruction::Inc(_) => "inc",
+            Instruction::Dec(_) => "dec",
+            Instruction::Lop(_) => "lop",
+            Instruction::Whl(_) => "whl",
+            Instruction::Pol(_) => "pol",
+            Instruction::Rdi => "rdi",
+            Instruction::Rda => "rda",
+            Instruction::Wrt => "wrt",
+            Instruction::Wrti8 => "wrti8",
+            Instruction::Wrti16 => "wrti16",
+            Instruction::Wrti32 => "wrti32",
+            Instruction::Wrti64 => "wrti64",
+            Instruction::Wrtu8 => "wrtu8",
+            Instruction::Wrtu16 => "wrtu16",
+            Instruction::Wrtu32 => "wrtu32",
+            Instruction::Wrtu64 => "wrtu64",
+            Instruction::Wrtf32 => "wrtf32",
+            Instruction::Wrtf64 => "wrtf64",
+            Instruction::Set(_) => "set",
+            Instruction::Seti8(_) => "seti8",
+            Instruction::Seti16(_) => "seti16",
+            Instruction::Seti32(_) => "seti32",
+            Instruction::Seti64(_) => "seti64",
+            Instruction::Setu8(_) => "setu8",
+            Instruction::Setu16(_) => "setu16",
+            Instruction::Setu32(_) => "setu32",
+            Instruction::Setu64(_) => "setu64",
+            Instruction::Setf32(_) => "setf32",
+            Instruction::Setf64(_) => "setf64",
+            Instruction::Wra => "wra",
+            Instruction::Clr => "clr",
+            Instruction::Psh => "psh",
+            Instruction::Psh16 => "psh16",
+            Instruction::Psh32 => "psh32",
+            Instruction::Psh64 => "psh64",
+            Instruction::Pop => "pop",
+            Instruction::Pek => "pek",
+            Instruction::Dup => "dup",
+            Instruction::Swp => "swp",
+            Instruction::Ast(_) => "ast",
+            Instruction::Asti8(_) => "asti8",
+            Instruction::Asti16(_) => "asti16",
+            Instruction::Asti32(_) => "asti32",
+            Instruction::Asti64(_) => "asti64",
+            Instruction::Astu8(_) => "astu8",
+            Instruction::Astu16(_) => "astu16",
+            Instruction::Astu32(_) => "astu32",
+            Instruction::Astu64(_) => "astu64",
+            Instruction::Astf32(..) => "astf32",
+            Instruction::Astf64(..) => "astf64",
+            Instruction::Asp(_) => "asp",
+        }
+    }
+
+    /// Returns the operand of the instruction as written in TRNG source, if there is one.
+    pub fn operand(&self) -> Option<String> {
+        match self {
+            Instruction::Pfw(v) | Instruction::Pbw(v) | Instruction::Ptr(v) => Some(v.to_string()),
+            Instruction::Inc(v) | Instruction::Dec(v) | Instruction::Setu8(v) => {
+                Some(v.to_string())
+            }
+            Instruction::Set(v) => Some(v.clone()),
+            Instruction::Seti8(v) => Some(v.to_string()),
+            Instruction::Seti16(v) => Some(v.to_string()),
+            Instruction::Seti32(v) => Some(v.to_string()),
+            Instruction::Seti64(v) => Some(v.to_string()),
+            Instruction::Setu16(v) => Some(v.to_string()),
+            Instruction::Setu32(v) => Some(v.to_string()),
+            Instruction::Setu64(v) => Some(v.to_string()),
+            Instruction::Setf32(v) => Some(v.to_string()),
+            Instruction::Setf64(v) => Some(v.to_string()),
+            Instruction::Ast(v) | Instruction::Astu8(v) => Some(v.to_string()),
+            Instruction::Asti8(v) => Some(v.to_string()),
+            Instruction::Asti16(v) => Some(v.to_string()),
+            Instruction::Asti32(v) => Some(v.to_string()),
+            Instruction::Asti64(v) => Some(v.to_string()),
+            Instruction::Astu16(v) => Some(v.to_string()),
+            Instruction::Astu32(v) => Some(v.to_string()),
+            Instruction::Astu64(v) => Some(v.to_string()),
+            Instruction::Astf32(v, eps) => Some(format!("{} {}", v, eps)),
+            Instruction::Astf64(v, eps) => Some(format!("{} {}", v, eps)),
+            Instruction::Asp(v) => Some(v.to_string()),
+            _ => None,
+        }
+    }
+
+    /// Returns whether the instruction is an assertion like `ast` or `asp`.
+    pub fn is_assertion(&self) -> bool {
+        matches!(
+            self,
+            Instruction::Ast(_)
+                | Instruction::Asti8(_)
+                | Instruction::Asti16(_)
+                | Instruction::Asti32(_)
+                | Instruction::Asti64(_)
+                | Instruction::Astu8(_)
+                | Instruction::Astu16(_)
+                | Instruction::Astu32(_)
+                | Instruction::Astu64(_)
+                | Instruction::Astf32(..)
+                | Instruction::Astf64(..)
+                | Instruction::Asp(_)
+        )
+    }
+}
+
+impl std::fmt::Display for Instruction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.operand() {
+            Some(operand) => write!(f, "{} {}", self.op_code(), operand),
+            None => write!(f, "{}", self.op_code()),
+        }
+    }
+}
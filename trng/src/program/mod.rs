@@ -0,0 +1,528 @@
+// TRNG - Brainfucks pretty sister.
+// Copyright (C) 2023 Lukas Pfeifer
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+mod error;
+mod instruction;
+
+pub use error::{ParseError, ParseErrorType};
+pub use instruction::Instruction;
+
+use crate::brainfuck;
+use crate::lexer::{self, LexError, Token, TokenType};
+use std::io::Read;
+use std::path::Path;
+
+/// Type alias for a simple result with a ParseError.
+pub type ParseResult<T> = Result<T, ParseError>;
+
+/// Defines the position of an instruction in its source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Position {
+    /// Line number of the instruction.
+    pub line: u32,
+    /// Column number of the instruction.
+    pub column: u32,
+}
+
+/// A parsed program that can be executed by the interpreter.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Program {
+    /// The instructions of the program.
+    pub instructions: Vec<Instruction>,
+    /// The source position of each instruction.
+    pub positions: Vec<Position>,
+    /// The source positions of all instructions that were merged into each instruction,
+    /// e.g. `+++` in Brainfuck becomes a single `inc 3`. Empty if nothing was merged.
+    pub origins: Vec<Vec<Position>>,
+    /// Whether cells wrap around on overflow instead of failing, e.g. for Brainfuck programs.
+    pub wrapping: bool,
+}
+
+impl Program {
+    /// Parses TRNG code.
+    /// # Arguments
+    /// * `read_from` - Source for TRNG code. Can be anything that implements the `Read` trait.
+    pub fn parse<T>(read_from: T) -> ParseResult<Program>
+    where
+        T: Read,
+    {
+        Program::from_lexer(lexer::ReadLexer::new(read_from))
+    }
+
+    /// Parses TRNG code held in memory without copying the tokens.
+    /// # Arguments
+    /// * `code` - The TRNG code.
+    pub fn parse_str(code: &str) -> ParseResult<Program> {
+        Program::from_lexer(lexer::Lexer::new(code))
+    }
+
+    /// Builds a program from the tokens of a lexer and stops at the first lexer error.
+    fn from_lexer<'a, I>(lexer: I) -> ParseResult<Program>
+    where
+        I: Iterator<Item = Result<Token<'a>, LexError>>,
+    {
+        let mut error = None;
+        let tokens = lexer
+            .map_while(|t| t.map_err(|e| error = Some(e)).ok())
+            .filter(|t| t._type != TokenType::Comment);
+
+        let program = Program::from_tokens(tokens);
+        match error {
+            Some(e) => Err(ParseError::from(e)),
+            None => program,
+        }
+    }
+
+    /// Parses Brainfuck code. See `brainfuck::parse` for how Brainfuck maps onto TRNG.
+    /// # Arguments
+    /// * `read_from` - Source for Brainfuck code. Can be anything that implements the `Read` trait.
+    pub fn parse_brainfuck<T>(read_from: T) -> ParseResult<Program>
+    where
+        T: Read,
+    {
+        brainfuck::parse(read_from)
+    }
+
+    /// Parses the given file.
+    /// Files ending in `.bf` or `.b` are parsed as Brainfuck, all other files as TRNG.
+    /// # Arguments
+    /// * `path` - Path of the source file.
+    pub fn from_file<P>(path: P) -> ParseResult<Program>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let file = std::fs::File::open(path)?;
+
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("bf") | Some("b") => Program::parse_brainfuck(file),
+            _ => Program::parse(file),
+        }
+    }
+
+    /// Creates a program from the tokens of TRNG code without comments.
+    /// # Arguments
+    /// * `tokens` - The tokens of the program.
+    pub(crate) fn from_tokens<'a, I>(tokens: I) -> ParseResult<Program>
+    where
+        I: IntoIterator<Item = Token<'a>>,
+    {
+        let mut program = Program::default();
+
+        let mut tokens = tokens.into_iter();
+        while let Some(token) = tokens.next() {
+            let instruction = match token._type {
+                TokenType::Pfw => Instruction::Pfw(expect_num(tokens.next().as_ref(), &token)?),
+                TokenType::Pbw => Instruction::Pbw(expect_num(tokens.next().as_ref(), &token)?),
+                TokenType::Ptr => Instruction::Ptr(expect_num(tokens.next().as_ref(), &token)?),
+                TokenType::Mrk => Instruction::Mrk,
+                TokenType::Ret => Instruction::Ret,
+                TokenType::Inc => Instruction::Inc(expect_num(tokens.next().as_ref(), &token)?),
+                TokenType::Dec => Instruction::Dec(expect_num(tokens.next().as_ref(), &token)?),
+                TokenType::Lop => Instruction::Lop(0),
+                TokenType::Whl => Instruction::Whl(0),
+                TokenType::Pol => Instruction::Pol(0),
+                TokenType::Rdi => Instruction::Rdi,
+                TokenType::Rda => Instruction::Rda,
+                TokenType::Wrt => Instruction::Wrt,
+                TokenType::Wrti8 => Instruction::Wrti8,
+                TokenType::Wrti16 => Instruction::Wrti16,
+                TokenType::Wrti32 => Instruction::Wrti32,
+                TokenType::Wrti64 => Instruction::Wrti64,
+                TokenType::Wrtu8 => Instruction::Wrtu8,
+                TokenType::Wrtu16 => Instruction::Wrtu16,
+                TokenType::Wrtu32 => Instruction::Wrtu32,
+                TokenType::Wrtu64 => Instruction::Wrtu64,
+                TokenType::Wrtf32 => Instruction::Wrtf32,
+                TokenType::Wrtf64 => Instruction::Wrtf64,
+                TokenType::Set => match tokens.next() {
+                    Some(t) => Instruction::Set(t.value.into_owned()),
+                    None => {
+                        return Err(ParseError::new(
+                            ParseErrorType::Operand,
+                            format!(
+                                "Expected a value. Found nothing. - ln: {}, col: {}",
+                                token.line, token.column
+                            ),
+                            token.line,
+                            token.column,
+                        ))
+                    }
+                },
+                TokenType::Seti8 => Instruction::Seti8(expect_num(tokens.next().as_ref(), &token)?),
+                TokenType::Seti16 => {
+                    Instruction::Seti16(expect_num(tokens.next().as_ref(), &token)?)
+                }
+                TokenType::Seti32 => {
+                    Instruction::Seti32(expect_num(tokens.next().as_ref(), &token)?)
+                }
+                TokenType::Seti64 => {
+                    Instruction::Seti64(expect_num(tokens.next().as_ref(), &token)?)
+                }
+                TokenType::Setu8 => Instruction::Setu8(expect_num(tokens.next().as_ref(), &token)?),
+                TokenType::Setu16 => {
+                    Instruction::Setu16(expect_num(tokens.next().as_ref(), &token)?)
+                }
+                TokenType::Setu32 => {
+                    Instruction::Setu32(expect_num(tokens.next().as_ref(), &token)?)
+                }
+                TokenType::Setu64 => {
+                    Instruction::Setu64(expect_num(tokens.next().as_ref(), &token)?)
+                }
+                TokenType::Setf32 => {
+                    Instruction::Setf32(expect_num(tokens.next().as_ref(), &token)?)
+                }
+                TokenType::Setf64 => {
+                    Instruction::Setf64(expect_num(tokens.next().as_ref(), &token)?)
+                }
+                TokenType::Wra => Instruction::Wra,
+                TokenType::Clr => Instruction::Clr,
+                TokenType::Psh => Instruction::Psh,
+                TokenType::Psh16 => Instruction::Psh16,
+                TokenType::Psh32 => Instruction::Psh32,
+                TokenType::Psh64 => Instruction::Psh64,
+                TokenType::Pop => Instruction::Pop,
+                TokenType::Pek => Instruction::Pek,
+                TokenType::Dup => Instruction::Dup,
+                TokenType::Swp => Instruction::Swp,
+                TokenType::Ast => Instruction::Ast(expect_num(tokens.next().as_ref(), &token)?),
+                TokenType::Asti8 => Instruction::Asti8(expect_num(tokens.next().as_ref(), &token)?),
+                TokenType::Asti16 => {
+                    Instruction::Asti16(expect_num(tokens.next().as_ref(), &token)?)
+                }
+                TokenType::Asti32 => {
+                    Instruction::Asti32(expect_num(tokens.next().as_ref(), &token)?)
+                }
+                TokenType::Asti64 => {
+                    Instruction::Asti64(expect_num(tokens.next().as_ref(), &token)?)
+                }
+                TokenType::Astu8 => Instruction::Astu8(expect_num(tokens.next().as_ref(), &token)?),
+                TokenType::Astu16 => {
+                    Instruction::Astu16(expect_num(tokens.next().as_ref(), &token)?)
+                }
+                TokenType::Astu32 => {
+                    Instruction::Astu32(expect_num(tokens.next().as_ref(), &token)?)
+                }
+                TokenType::Astu64 => {
+                    Instruction::Astu64(expect_num(tokens.next().as_ref(), &token)?)
+                }
+                TokenType::Astf32 => {
+                    let value = expect_num(tokens.next().as_ref(), &token)?;
+                    let epsilon = expect_num(tokens.next().as_ref(), &token)?;
+                    Instruction::Astf32(value, epsilon)
+                }
+                TokenType::Astf64 => {
+                    let value = expect_num(tokens.next().as_ref(), &token)?;
+                    let epsilon = expect_num(tokens.next().as_ref(), &token)?;
+                    Instruction::Astf64(value, epsilon)
+                }
+                TokenType::Asp => Instruction::Asp(expect_num(tokens.next().as_ref(), &token)?),
+                TokenType::Num | TokenType::Comment | TokenType::Unknown => {
+                    return Err(ParseError::new(
+                        ParseErrorType::UnknownToken,
+                        format!(
+                            "Found unknown token '{}'. - ln: {}, col: {}",
+                            token.value, token.line, token.column
+                        ),
+                        token.line,
+                        token.column,
+                    ))
+                }
+            };
+
+            program.push(
+                instruction,
+                Position {
+                    line: token.line,
+                    column: token.column,
+                },
+            );
+        }
+
+        program.resolve_loops()?;
+
+        Ok(program)
+    }
+
+    /// Returns the number of instructions.
+    pub fn len(&self) -> usize {
+        self.instructions.len()
+    }
+
+    /// Returns whether the program has no instructions.
+    pub fn is_empty(&self) -> bool {
+        self.instructions.is_empty()
+    }
+
+    /// Returns a hash identifying the program by its instructions.
+    ///
+    /// Uses 64-bit FNV-1a so the value stays stable across builds and platforms.
+    pub fn hash(&self) -> u64 {
+        let mut hash: u64 = 0xcbf29ce484222325;
+
+        for instruction in &self.instructions {
+            for b in instruction
+                .to_string()
+                .bytes()
+                .chain(std::iter::once(b'\n'))
+            {
+                hash ^= b as u64;
+                hash = hash.wrapping_mul(0x100000001b3);
+            }
+        }
+
+        hash
+    }
+
+    /// Removes all assertions like `ast` and `asp`, e.g. for a release build.
+    /// Loop instructions are updated to point to the new indices of their counterparts.
+    pub fn strip_assertions(&mut self) {
+        let mut new_indices = Vec::with_capacity(self.len());
+        let mut kept = 0;
+        for instruction in &self.instructions {
+            new_indices.push(kept);
+            if !instruction.is_assertion() {
+                kept += 1;
+            }
+        }
+
+        let program = std::mem::take(self);
+        let entries = program
+            .instructions
+            .into_iter()
+            .zip(program.positions)
+            .zip(program.origins);
+
+        for ((instruction, position), origins) in entries {
+            let instruction = match instruction {
+                _ if instruction.is_assertion() => continue,
+                Instruction::Lop(end) => Instruction::Lop(new_indices[end]),
+                Instruction::Whl(end) => Instruction::Whl(new_indices[end]),
+                Instruction::Pol(start) => Instruction::Pol(new_indices[start]),
+                other => other,
+            };
+
+            self.instructions.push(instruction);
+            self.positions.push(position);
+            self.origins.push(origins);
+        }
+    }
+
+    /// Appends an instruction.
+    pub(crate) fn push(&mut self, instruction: Instruction, position: Position) {
+        self.instructions.push(instruction);
+        self.positions.push(position);
+        self.origins.push(vec![]);
+    }
+
+    /// Sets the targets of all loop instructions.
+    /// Returns an error if a loop is not closed or closed without being opened.
+    pub(crate) fn resolve_loops(&mut self) -> ParseResult<()> {
+        let mut open: Vec<usize> = vec![];
+
+        for i in 0..self.instructions.len() {
+            match self.instructions[i] {
+                Instruction::Lop(_) | Instruction::Whl(_) => open.push(i),
+                Instruction::Pol(_) => {
+                    let start = match open.pop() {
+                        Some(start) => start,
+                        None => {
+                            let pos = self.positions[i];
+                            return Err(ParseError::new(
+                                ParseErrorType::Loop,
+                                format!(
+                                    "Found 'pol' without a matching loop start. - ln: {}, col: {}",
+                                    pos.line, pos.column
+                                ),
+                                pos.line,
+                                pos.column,
+                            ));
+                        }
+                    };
+
+                    self.instructions[i] = Instruction::Pol(start);
+                    self.instructions[start] = match self.instructions[start] {
+                        Instruction::Whl(_) => Instruction::Whl(i),
+                        _ => Instruction::Lop(i),
+                    };
+                }
+                _ => (),
+            }
+        }
+
+        match open.pop() {
+            Some(start) => {
+                let pos = self.positions[start];
+                Err(ParseError::new(
+                    ParseErrorType::Loop,
+                    format!(
+                        "Found '{}' without a matching 'pol'. - ln: {}, col: {}",
+                        self.instructions[start].op_code(),
+                        pos.line,
+                        pos.column
+                    ),
+                    pos.line,
+                    pos.column,
+                ))
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+/// Expects the given `Token` to be a `Num` token and parses its value.
+/// Returns an error if the token is different from what was expected.
+/// # Arguments
+/// * `token` - Option of a token.
+/// * `instruction` - The token of the instruction the operand belongs to.
+fn expect_num<T>(token: Option<&Token>, instruction: &Token) -> ParseResult<T>
+where
+    T: std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    match token {
+        Some(tok) => match tok._type {
+            TokenType::Num => match tok.value.parse::<T>() {
+                Ok(n) => Ok(n),
+                Err(e) => Err(ParseError::new(
+                    ParseErrorType::Operand,
+                    format!(
+                        "Invalid operand '{}': {} - ln: {}, col: {}",
+                        tok.value, e, tok.line, tok.column
+                    ),
+                    tok.line,
+                    tok.column,
+                )),
+            },
+            _ => Err(ParseError::new(
+                ParseErrorType::Operand,
+                format!(
+                    "Expected 'num'. Found '{}' - ln: {}, col: {}",
+                    tok.value, tok.line, tok.column
+                ),
+                tok.line,
+                tok.column,
+            )),
+        },
+        None => Err(ParseError::new(
+            ParseErrorType::Operand,
+            format!(
+                "Expected 'num'. Found nothing instead. - ln: {}, col: {}",
+                instruction.line, instruction.column
+            ),
+            instruction.line,
+            instruction.column,
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Instruction;
+
+    #[test]
+    fn parse_resolves_loops_test() {
+        let program = super::Program::parse("inc 2 lop whl dec 1 pol pol".as_bytes()).unwrap();
+
+        assert_eq!(
+            program.instructions,
+            vec![
+                Instruction::Inc(2),
+                Instruction::Lop(5),
+                Instruction::Whl(4),
+                Instruction::Dec(1),
+                Instruction::Pol(2),
+                Instruction::Pol(1),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_unbalanced_loop_is_err_test() {
+        let err = super::Program::parse("lop inc 1".as_bytes()).unwrap_err();
+        assert_eq!(err._type, super::ParseErrorType::Loop);
+
+        let err = super::Program::parse("pol".as_bytes()).unwrap_err();
+        assert_eq!(err._type, super::ParseErrorType::Loop);
+    }
+
+    #[test]
+    fn parse_invalid_operand_is_err_test() {
+        let err = super::Program::parse("inc 256".as_bytes()).unwrap_err();
+        assert_eq!(err._type, super::ParseErrorType::Operand);
+
+        let err = super::Program::parse("pfw".as_bytes()).unwrap_err();
+        assert_eq!(err._type, super::ParseErrorType::Operand);
+    }
+
+    #[test]
+    fn display_roundtrip_test() {
+        let code = "set Hello seti32 -7 setf64 1.5 pfw 3 psh32 wrti8";
+        let program = super::Program::parse(code.as_bytes()).unwrap();
+
+        let printed: Vec<String> = program.instructions.iter().map(|i| i.to_string()).collect();
+        let reparsed = super::Program::parse(printed.join("\n").as_bytes()).unwrap();
+
+        assert_eq!(reparsed.instructions, program.instructions);
+        assert_eq!(reparsed.hash(), program.hash());
+    }
+
+    #[test]
+    fn parse_assertions_test() {
+        let code = "ast 3 asti32 -7 astf64 1.5 0.01 asp 4";
+        let program = super::Program::parse(code.as_bytes()).unwrap();
+
+        assert_eq!(
+            program.instructions,
+            vec![
+                Instruction::Ast(3),
+                Instruction::Asti32(-7),
+                Instruction::Astf64(1.5, 0.01),
+                Instruction::Asp(4),
+            ]
+        );
+        assert_eq!(program.instructions[2].to_string(), "astf64 1.5 0.01");
+
+        let err = super::Program::parse("astf32 1.5".as_bytes()).unwrap_err();
+        assert_eq!(err._type, super::ParseErrorType::Operand);
+    }
+
+    #[test]
+    fn strip_assertions_updates_loops_test() {
+        let code = "inc 2 ast 2 lop asp 0 whl dec 1 ast 1 pol pol";
+        let mut program = super::Program::parse(code.as_bytes()).unwrap();
+
+        program.strip_assertions();
+
+        let expected = super::Program::parse("inc 2 lop whl dec 1 pol pol".as_bytes()).unwrap();
+        assert_eq!(program.instructions, expected.instructions);
+        assert_eq!(program.positions[3].column, 27);
+    }
+
+    #[test]
+    fn parse_str_matches_parse_test() {
+        let code = std::fs::read_to_string("../examples/loop.trng").unwrap();
+
+        let from_str = super::Program::parse_str(&code).unwrap();
+        let from_reader = super::Program::parse(code.as_bytes()).unwrap();
+
+        assert_eq!(from_str, from_reader);
+        assert_eq!(
+            super::Program::parse_str("pol").unwrap_err()._type,
+            super::ParseErrorType::Loop
+        );
+    }
+}
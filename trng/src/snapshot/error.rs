@@ -0,0 +1,72 @@
+// TRNG - Brainfucks pretty sister.
+// Copyright (C) 2023 Lukas Pfeifer
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+/// Defines error types for snapshot errors.
+#[derive(Debug)]
+pub enum SnapshotErrorType {
+    Format,
+    Version,
+    Mismatch,
+    Io,
+}
+
+impl std::fmt::Display for SnapshotErrorType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SnapshotErrorType::Format => {
+                write!(f, "Format error")
+            }
+            SnapshotErrorType::Version => {
+                write!(f, "Version error")
+            }
+            SnapshotErrorType::Mismatch => {
+                write!(f, "Mismatch error")
+            }
+            SnapshotErrorType::Io => {
+                write!(f, "IO error")
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct SnapshotError {
+    description: String,
+    _type: SnapshotErrorType,
+}
+
+impl std::fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Snapshot Error [{}]: {}", self._type, self.description)
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+impl From<std::io::Error> for SnapshotError {
+    fn from(value: std::io::Error) -> Self {
+        Self {
+            description: value.to_string(),
+            _type: SnapshotErrorType::Io,
+        }
+    }
+}
+
+impl SnapshotError {
+    pub fn new(_type: SnapshotErrorType, description: String) -> SnapshotError {
+        Self { _type, description }
+    }
+}
@@ -0,0 +1,288 @@
+// TRNG - Brainfucks pretty sister.
+// Copyright (C) 2023 Lukas Pfeifer
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+mod error;
+
+pub use error::{SnapshotError, SnapshotErrorType};
+
+use std::io::{Read, Write};
+
+/// Type alias for a simple result with a SnapshotError.
+pub type SnapshotResult<T> = Result<T, SnapshotError>;
+
+/// Magic bytes every binary snapshot starts with.
+const MAGIC: &[u8; 8] = b"TRNGSNAP";
+
+/// The version of the binary snapshot format.
+const VERSION: u16 = 1;
+
+/// The complete execution state of an interpreter.
+///
+/// A snapshot can only be restored into an interpreter that has loaded the same program
+/// and uses a tape of the same size.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Snapshot {
+    /// Hash identifying the program the snapshot was taken from.
+    pub program_hash: u64,
+    /// The cells of the tape.
+    pub data: Vec<u8>,
+    /// The pointer index of the tape.
+    pub ptr_index: usize,
+    /// The bookmark stack of the tape.
+    pub bookmarks: Vec<usize>,
+    /// The index of the next instruction to execute.
+    pub instruction_index: usize,
+    /// The number of instructions executed so far.
+    pub instruction_count: u64,
+    /// The instruction indizes of the currently open loops.
+    pub loop_stack: Vec<usize>,
+    /// The values on the value stack.
+    pub stack: Vec<Vec<u8>>,
+}
+
+impl Snapshot {
+    /// Encodes the snapshot in the versioned binary format.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.data.len() + 64);
+
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&VERSION.to_be_bytes());
+        out.extend_from_slice(&self.program_hash.to_be_bytes());
+        put_bytes(&mut out, &self.data);
+        put_usize(&mut out, self.ptr_index);
+        put_usizes(&mut out, &self.bookmarks);
+        put_usize(&mut out, self.instruction_index);
+        out.extend_from_slice(&self.instruction_count.to_be_bytes());
+        put_usizes(&mut out, &self.loop_stack);
+        put_usize(&mut out, self.stack.len());
+        for value in &self.stack {
+            put_bytes(&mut out, value);
+        }
+
+        out
+    }
+
+    /// Decodes a snapshot from the versioned binary format.
+    /// # Arguments
+    /// * `bytes` - The encoded snapshot.
+    pub fn from_bytes(bytes: &[u8]) -> SnapshotResult<Snapshot> {
+        let mut decoder = Decoder { bytes, pos: 0 };
+
+        if decoder.take(MAGIC.len())? != MAGIC {
+            return Err(SnapshotError::new(
+                SnapshotErrorType::Format,
+                "The data is not a TRNG snapshot.".to_string(),
+            ));
+        }
+
+        let version = u16::from_be_bytes([decoder.u8()?, decoder.u8()?]);
+        if version != VERSION {
+            return Err(SnapshotError::new(
+                SnapshotErrorType::Version,
+                format!(
+                    "Snapshot version {} is not supported. Expected version {}.",
+                    version, VERSION
+                ),
+            ));
+        }
+
+        let program_hash = decoder.u64()?;
+        let data = decoder.bytes()?;
+        let ptr_index = decoder.usize()?;
+        let bookmarks = decoder.usizes()?;
+        let instruction_index = decoder.usize()?;
+        let instruction_count = decoder.u64()?;
+        let loop_stack = decoder.usizes()?;
+
+        let stack_len = decoder.usize()?;
+        let mut stack = vec![];
+        for _ in 0..stack_len {
+            stack.push(decoder.bytes()?);
+        }
+
+        if decoder.pos != bytes.len() {
+            return Err(SnapshotError::new(
+                SnapshotErrorType::Format,
+                format!(
+                    "Found {} unexpected trailing byte(s).",
+                    bytes.len() - decoder.pos
+                ),
+            ));
+        }
+
+        Ok(Snapshot {
+            program_hash,
+            data,
+            ptr_index,
+            bookmarks,
+            instruction_index,
+            instruction_count,
+            loop_stack,
+            stack,
+        })
+    }
+
+    /// Writes the snapshot in the versioned binary format.
+    /// # Arguments
+    /// * `write_to` - The `Write` instance to write the snapshot to.
+    pub fn write<T>(&self, mut write_to: T) -> SnapshotResult<()>
+    where
+        T: Write,
+    {
+        write_to.write_all(&self.to_bytes())?;
+        Ok(())
+    }
+
+    /// Reads a snapshot in the versioned binary format.
+    /// # Arguments
+    /// * `read_from` - The `Read` instance to read the snapshot from.
+    pub fn read<T>(mut read_from: T) -> SnapshotResult<Snapshot>
+    where
+        T: Read,
+    {
+        let mut bytes = vec![];
+        read_from.read_to_end(&mut bytes)?;
+        Snapshot::from_bytes(&bytes)
+    }
+}
+
+fn put_usize(out: &mut Vec<u8>, v: usize) {
+    out.extend_from_slice(&(v as u64).to_be_bytes());
+}
+
+fn put_usizes(out: &mut Vec<u8>, v: &[usize]) {
+    put_usize(out, v.len());
+    for i in v {
+        put_usize(out, *i);
+    }
+}
+
+fn put_bytes(out: &mut Vec<u8>, v: &[u8]) {
+    put_usize(out, v.len());
+    out.extend_from_slice(v);
+}
+
+/// Reads the fields of a binary snapshot.
+struct Decoder<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn take(&mut self, count: usize) -> SnapshotResult<&'a [u8]> {
+        match self
+            .pos
+            .checked_add(count)
+            .and_then(|end| self.bytes.get(self.pos..end))
+        {
+            Some(slice) => {
+                self.pos += count;
+                Ok(slice)
+            }
+            None => Err(SnapshotError::new(
+                SnapshotErrorType::Format,
+                format!("Unexpected end of snapshot at byte {}.", self.pos),
+            )),
+        }
+    }
+
+    fn u8(&mut self) -> SnapshotResult<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u64(&mut self) -> SnapshotResult<u64> {
+        let mut b = [0; 8];
+        b.copy_from_slice(self.take(8)?);
+        Ok(u64::from_be_bytes(b))
+    }
+
+    fn usize(&mut self) -> SnapshotResult<usize> {
+        let v = self.u64()?;
+        usize::try_from(v).map_err(|_| {
+            SnapshotError::new(
+                SnapshotErrorType::Format,
+                format!("Value {} does not fit into the address space.", v),
+            )
+        })
+    }
+
+    fn usizes(&mut self) -> SnapshotResult<Vec<usize>> {
+        let len = self.usize()?;
+        let mut v = vec![];
+        for _ in 0..len {
+            v.push(self.usize()?);
+        }
+        Ok(v)
+    }
+
+    fn bytes(&mut self) -> SnapshotResult<Vec<u8>> {
+        let len = self.usize()?;
+        Ok(self.take(len)?.to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    fn snapshot() -> super::Snapshot {
+        super::Snapshot {
+            program_hash: 0x0123456789abcdef,
+            data: vec![1, 2, 3, 0],
+            ptr_index: 2,
+            bookmarks: vec![1],
+            instruction_index: 4,
+            instruction_count: 17,
+            loop_stack: vec![0, 2],
+            stack: vec![vec![9], vec![0, 0, 3, 232]],
+        }
+    }
+
+    #[test]
+    fn bytes_roundtrip_test() {
+        let snapshot = snapshot();
+
+        let decoded = super::Snapshot::from_bytes(&snapshot.to_bytes()).unwrap();
+
+        assert_eq!(decoded, snapshot);
+    }
+
+    #[test]
+    fn truncated_bytes_is_err_test() {
+        let bytes = snapshot().to_bytes();
+
+        assert!(super::Snapshot::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn unknown_version_is_err_test() {
+        let mut bytes = snapshot().to_bytes();
+        bytes[9] = 99;
+
+        assert!(super::Snapshot::from_bytes(&bytes).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json_roundtrip_test() {
+        let snapshot = snapshot();
+
+        let json = serde_json::to_string(&snapshot).unwrap();
+        let decoded: super::Snapshot = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(decoded, snapshot);
+    }
+}
@@ -0,0 +1,55 @@
+// TRNG - Brainfucks pretty sister.
+// Copyright (C) 2023 Lukas Pfeifer
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+/// Defines error types for value stack errors.
+#[derive(Debug)]
+pub enum StackErrorType {
+    Underflow,
+    Overflow,
+}
+
+impl std::fmt::Display for StackErrorType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StackErrorType::Underflow => {
+                write!(f, "Underflow error")
+            }
+            StackErrorType::Overflow => {
+                write!(f, "Overflow error")
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct StackError {
+    description: String,
+    _type: StackErrorType,
+}
+
+impl std::fmt::Display for StackError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Stack Error [{}]: {}", self._type, self.description)
+    }
+}
+
+impl std::error::Error for StackError {}
+
+impl StackError {
+    pub fn new(_type: StackErrorType, description: String) -> StackError {
+        Self { _type, description }
+    }
+}
@@ -0,0 +1,178 @@
+// TRNG - Brainfucks pretty sister.
+// Copyright (C) 2023 Lukas Pfeifer
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+mod error;
+
+pub use error::{StackError, StackErrorType};
+
+/// Type alias for a simple result with a StackError.
+pub type StackResult<T> = Result<T, StackError>;
+
+/// Simulates the auxiliary value stack for TRNG.
+///
+/// Every entry holds the bytes of a single value, e.g. one cell or the four cells of a 32-bit number.
+pub struct ValueStack {
+    pub values: Vec<Vec<u8>>,
+    pub max_depth: usize,
+}
+
+impl Default for ValueStack {
+    fn default() -> Self {
+        Self::new(1024)
+    }
+}
+
+impl ValueStack {
+    /// Function returns a new value stack.
+    ///
+    /// * `max_depth` - The maximum number of values the stack can hold.
+    pub fn new(max_depth: usize) -> Self {
+        Self {
+            values: vec![],
+            max_depth,
+        }
+    }
+
+    /// Removes all values from the stack.
+    pub fn reset(&mut self) {
+        self.values.clear();
+    }
+
+    /// Pushes the given value on top of the stack.
+    ///
+    /// * `value` - The bytes of the value to push.
+    pub fn push(&mut self, value: Vec<u8>) -> StackResult<()> {
+        if self.values.len() >= self.max_depth {
+            return Err(StackError::new(
+                StackErrorType::Overflow,
+                format!(
+                    "Pushing a value would exceed the maximum stack depth of {}.",
+                    self.max_depth
+                ),
+            ));
+        }
+
+        self.values.push(value);
+        Ok(())
+    }
+
+    /// Removes the value on top of the stack and returns it.
+    pub fn pop(&mut self) -> StackResult<Vec<u8>> {
+        match self.values.pop() {
+            Some(value) => Ok(value),
+            None => Err(StackError::new(
+                StackErrorType::Underflow,
+                "Popping a value is invalid. The stack is empty.".to_string(),
+            )),
+        }
+    }
+
+    /// Returns the value on top of the stack without removing it.
+    pub fn peek(&self) -> StackResult<&Vec<u8>> {
+        match self.values.last() {
+            Some(value) => Ok(value),
+            None => Err(StackError::new(
+                StackErrorType::Underflow,
+                "Peeking a value is invalid. The stack is empty.".to_string(),
+            )),
+        }
+    }
+
+    /// Pushes a copy of the value on top of the stack.
+    pub fn dup(&mut self) -> StackResult<()> {
+        let top = self.peek()?.clone();
+        self.push(top)
+    }
+
+    /// Swaps the two values on top of the stack.
+    pub fn swp(&mut self) -> StackResult<()> {
+        let len = self.values.len();
+
+        if len < 2 {
+            return Err(StackError::new(
+                StackErrorType::Underflow,
+                format!(
+                    "Swapping needs two values on the stack. Found {} instead.",
+                    len
+                ),
+            ));
+        }
+
+        self.values.swap(len - 1, len - 2);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    #[test]
+    fn push_pop_returns_pushed_value_test() {
+        let mut stack = super::ValueStack::default();
+
+        stack.push(vec![1, 2, 3, 4]).unwrap();
+
+        assert_eq!(stack.pop().unwrap(), vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn pop_empty_panics_test() {
+        let mut stack = super::ValueStack::default();
+
+        stack.pop().unwrap();
+    }
+
+    #[test]
+    #[should_panic]
+    fn push_beyond_max_depth_panics_test() {
+        let mut stack = super::ValueStack::new(2);
+
+        stack.push(vec![1]).unwrap();
+        stack.push(vec![2]).unwrap();
+        stack.push(vec![3]).unwrap();
+    }
+
+    #[test]
+    fn dup_copies_top_value_test() {
+        let mut stack = super::ValueStack::default();
+
+        stack.push(vec![7]).unwrap();
+        stack.dup().unwrap();
+
+        assert_eq!(stack.values, vec![vec![7], vec![7]]);
+    }
+
+    #[test]
+    fn swp_swaps_top_values_test() {
+        let mut stack = super::ValueStack::default();
+
+        stack.push(vec![1]).unwrap();
+        stack.push(vec![2]).unwrap();
+        stack.swp().unwrap();
+
+        assert_eq!(stack.values, vec![vec![2], vec![1]]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn swp_with_one_value_panics_test() {
+        let mut stack = super::ValueStack::default();
+
+        stack.push(vec![1]).unwrap();
+        stack.swp().unwrap();
+    }
+}
@@ -0,0 +1,58 @@
+// TRNG - Brainfucks pretty sister.
+// Copyright (C) 2023 Lukas Pfeifer
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::ops::{Deref, DerefMut};
+
+/// The cells of a tape. They are either owned or mapped from a file.
+pub enum Cells {
+    Owned(Vec<u8>),
+    /// The cells are the bytes of a file. Writes change the file in place.
+    #[cfg(feature = "mmap")]
+    Mapped(memmap2::MmapMut),
+}
+
+impl Cells {
+    /// Writes changed cells of a mapped file back to the file. Owned cells need no flush.
+    pub fn flush(&self) -> std::io::Result<()> {
+        match self {
+            Cells::Owned(_) => Ok(()),
+            #[cfg(feature = "mmap")]
+            Cells::Mapped(map) => map.flush(),
+        }
+    }
+}
+
+impl Deref for Cells {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Cells::Owned(data) => data,
+            #[cfg(feature = "mmap")]
+            Cells::Mapped(map) => map,
+        }
+    }
+}
+
+impl DerefMut for Cells {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        match self {
+            Cells::Owned(data) => data,
+            #[cfg(feature = "mmap")]
+            Cells::Mapped(map) => map,
+        }
+    }
+}
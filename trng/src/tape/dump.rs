@@ -0,0 +1,121 @@
+// TRNG - Brainfucks pretty sister.
+// Copyright (C) 2023 Lukas Pfeifer
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+/// The number of cells shown per row.
+const ROW: usize = 16;
+
+/// Formats cells as a hex and ASCII dump with the cell at the pointer in brackets.
+///
+/// Every row starts with the index of its first cell and shows 16 cells, followed by their
+/// printable ASCII characters. The dump shows `rows` rows before and after the row of the pointer.
+///
+/// ```text
+/// 00000000  48 65 6c 6c[6f]00 00 00  00 00 00 00 00 00 00 00  |Hello...........|
+/// ```
+/// # Arguments
+/// * `data` - The cells of the tape.
+/// * `ptr` - The index of the highlighted cell.
+/// * `rows` - The number of rows to show before and after the row of the pointer.
+pub(crate) fn dump(data: &[u8], ptr: usize, rows: usize) -> String {
+    let row = ptr / ROW;
+    let first = row.saturating_sub(rows);
+    let last = row
+        .saturating_add(rows)
+        .min(data.len().saturating_sub(1) / ROW);
+
+    let mut out = String::new();
+    for r in first..=last {
+        let start = r * ROW;
+        let cells = &data[start.min(data.len())..(start + ROW).min(data.len())];
+
+        out.push_str(&format!("{:08x} ", start));
+        for j in 0..ROW {
+            let index = start + j;
+            let close = j > 0 && index - 1 == ptr;
+            if j == ROW / 2 {
+                out.push(if close { ']' } else { ' ' });
+                out.push(if index == ptr { '[' } else { ' ' });
+            } else if index == ptr {
+                out.push('[');
+            } else if close {
+                out.push(']');
+            } else {
+                out.push(' ');
+            }
+
+            match cells.get(j) {
+                Some(cell) => out.push_str(&format!("{:02x}", cell)),
+                None => out.push_str("  "),
+            }
+        }
+        out.push(if start + ROW - 1 == ptr { ']' } else { ' ' });
+
+        out.push_str(" |");
+        for cell in cells {
+            out.push(match cell {
+                0x20..=0x7e => *cell as char,
+                _ => '.',
+            });
+        }
+        out.push_str("|\n");
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn dump_highlights_pointer_test() {
+        let mut data = vec![0u8; 40];
+        data[..5].copy_from_slice(b"Hello");
+
+        assert_eq!(
+            super::dump(&data, 4, 0),
+            "00000000  48 65 6c 6c[6f]00 00 00  00 00 00 00 00 00 00 00  |Hello...........|\n"
+        );
+        assert_eq!(
+            super::dump(&data, 7, 0),
+            "00000000  48 65 6c 6c 6f 00 00[00] 00 00 00 00 00 00 00 00  |Hello...........|\n"
+        );
+        assert_eq!(
+            super::dump(&data, 31, 0),
+            "00000010  00 00 00 00 00 00 00 00  00 00 00 00 00 00 00[00] |................|\n"
+        );
+    }
+
+    #[test]
+    fn dump_shows_rows_around_pointer_test() {
+        let data: Vec<u8> = (0..40).collect();
+
+        assert_eq!(
+            super::dump(&data, 16, 1),
+            "00000000  00 01 02 03 04 05 06 07  08 09 0a 0b 0c 0d 0e 0f  |................|\n\
+             00000010 [10]11 12 13 14 15 16 17  18 19 1a 1b 1c 1d 1e 1f  |................|\n\
+             00000020  20 21 22 23 24 25 26 27                           | !\"#$%&'|\n"
+        );
+    }
+
+    #[test]
+    fn dump_clamps_rows_to_tape_test() {
+        let data: Vec<u8> = (0..20).collect();
+
+        assert_eq!(
+            super::dump(&data, 16, usize::MAX),
+            super::dump(&data, 16, 1)
+        );
+    }
+}
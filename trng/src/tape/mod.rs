@@ -14,28 +14,56 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+mod cells;
+mod dump;
 mod error;
 mod num;
 
+pub use cells::Cells;
+pub(crate) use dump::dump;
 pub use error::{TapeError, TapeErrorType};
 
-use self::num::Num;
+pub use self::num::Num;
+#[cfg(feature = "mmap")]
+use std::fs::File;
 use std::io::{Read, Write};
+use std::ops::Range;
 
 /// Type alias for a simple result with a TapeError.
 pub type TapeResult<T> = Result<T, TapeError>;
 
 /// Simulates the tape for TRNG.
+///
+/// Output instructions write to stdout and input instructions read from stdin
+/// unless a different output or input is set.
 pub struct Tape {
-    pub data: Vec<u8>,
+    pub data: Cells,
     pub ptr_index: usize,
+    pub bookmarks: Vec<usize>,
+    /// The number of bytes read from the input so far.
+    pub bytes_read: u64,
+    /// The number of bytes written to the output so far.
+    pub bytes_written: u64,
+    /// The index and the previous value of every written cell. `None` disables the journal.
+    pub journal: Option<Vec<(usize, u8)>>,
+    /// Whether `inc` and `dec` wrap around instead of failing with an overflow error.
+    pub wrapping: bool,
+    output: Box<dyn Write + Send>,
+    input: Box<dyn Read + Send>,
 }
 
 impl Default for Tape {
     fn default() -> Self {
         Self {
-            data: vec![0; 30000],
+            data: Cells::Owned(vec![0; 30000]),
             ptr_index: 0,
+            bookmarks: vec![],
+            bytes_read: 0,
+            bytes_written: 0,
+            journal: None,
+            wrapping: false,
+            output: Box::new(std::io::stdout()),
+            input: Box::new(std::io::stdin()),
         }
     }
 }
@@ -48,17 +76,118 @@ impl Tape {
     /// * `data_band_sz` - The number of cells on the data band.
     pub fn new(data_band_sz: usize) -> Self {
         Self {
-            data: vec![0; data_band_sz],
+            data: Cells::Owned(vec![0; data_band_sz]),
             ptr_index: 0,
+            bookmarks: vec![],
+            bytes_read: 0,
+            bytes_written: 0,
+            journal: None,
+            wrapping: false,
+            output: Box::new(std::io::stdout()),
+            input: Box::new(std::io::stdin()),
         }
     }
 
-    /// Resets all cells of the tape to 0.
+    /// Resets all cells of the tape to 0 and removes all bookmarks.
+    /// A mapped file is unmapped and left unchanged. The tape then owns as many cells as the file has bytes.
     pub fn reset(&mut self) {
-        for c in &mut self.data {
-            *c = 0;
+        match &mut self.data {
+            Cells::Owned(data) => data.fill(0),
+            #[cfg(feature = "mmap")]
+            Cells::Mapped(map) => self.data = Cells::Owned(vec![0; map.len()]),
         }
         self.ptr_index = 0;
+        self.bookmarks.clear();
+    }
+
+    /// Sets the destination of all output instructions.
+    ///
+    /// * `output` - The `Write` instance to write to.
+    pub fn set_output(&mut self, output: Box<dyn Write + Send>) {
+        self.output = output;
+    }
+
+    /// Sets the source of all input instructions.
+    ///
+    /// * `input` - The `Read` instance to read from.
+    pub fn set_input(&mut self, input: Box<dyn Read + Send>) {
+        self.input = input;
+    }
+
+    /// Flushes the output and the cells of a mapped file.
+    pub fn flush(&mut self) -> TapeResult<()> {
+        self.output.flush()?;
+        self.data.flush()?;
+        Ok(())
+    }
+
+    /// Copies all bytes of the given reader into the cells starting at the given index.
+    /// Returns the number of copied bytes. The pointer is not moved.
+    ///
+    /// * `read_from` - The `Read` instance to read the bytes from.
+    /// * `offset` - The index of the first cell to write.
+    pub fn preload<R: Read>(&mut self, mut read_from: R, offset: usize) -> TapeResult<usize> {
+        let mut bytes = vec![];
+        read_from.read_to_end(&mut bytes)?;
+
+        let len = self.data.len();
+        match offset
+            .checked_add(bytes.len())
+            .and_then(|end| self.data.get_mut(offset..end))
+        {
+            Some(slice) => {
+                slice.copy_from_slice(&bytes);
+                Ok(bytes.len())
+            }
+            None => Err(TapeError::new(
+                TapeErrorType::Index,
+                format!(
+                    "Preloading {} byte(s) at index {} would result in overshooting the tape of {} cell(s).",
+                    bytes.len(),
+                    offset,
+                    len
+                ),
+            )),
+        }
+    }
+
+    /// Writes the cells in the given range to the given writer.
+    ///
+    /// * `write_to` - The `Write` instance to write the cells to.
+    /// * `range` - The indizes of the cells to write.
+    pub fn dump_to<W: Write>(&self, mut write_to: W, range: Range<usize>) -> TapeResult<()> {
+        match self.data.get(range.clone()) {
+            Some(slice) => {
+                write_to.write_all(slice)?;
+                write_to.flush()?;
+                Ok(())
+            }
+            None => Err(TapeError::new(
+                TapeErrorType::Index,
+                format!(
+                    "Dumping the cells {}..{} is invalid for a tape of {} cell(s).",
+                    range.start,
+                    range.end,
+                    self.data.len()
+                ),
+            )),
+        }
+    }
+
+    /// Replaces the cells with the bytes of the given file. The tape has as many cells as the file has bytes
+    /// and writes change the file in place. The pointer is moved to the first cell and all bookmarks are removed.
+    ///
+    /// * `file` - The file to map. It has to be opened for reading and writing.
+    #[cfg(feature = "mmap")]
+    pub fn map(&mut self, file: &File) -> TapeResult<()> {
+        // SAFETY: The map is only accessed through `Cells`. Changes of the file by other processes
+        // while the tape is mapped are not synchronised, as documented for `trng run --mmap`.
+        let map = unsafe { memmap2::MmapMut::map_mut(file)? };
+
+        self.data = Cells::Mapped(map);
+        self.ptr_index = 0;
+        self.bookmarks.clear();
+        Ok(())
     }
 
     /// Gets the value of the current cell.
@@ -77,11 +206,58 @@ impl Tape {
         }
     }
 
+    /// Returns a copy of the current cell and the following cells without moving the pointer.
+    ///
+    /// * `count` - The number of cells to read.
+    pub fn get_bytes(&self, count: usize) -> TapeResult<Vec<u8>> {
+        match self.data.get(self.ptr_index..self.ptr_index + count) {
+            Some(slice) => Ok(slice.to_vec()),
+            None => Err(TapeError::new(
+                TapeErrorType::Index,
+                format!(
+                    "Reading {} cell(s) at pointer index {} would result in overshooting the tape.",
+                    count, self.ptr_index
+                ),
+            )),
+        }
+    }
+
+    /// Interprets the current cell and the following cells as a number in BE byte order without moving the pointer.
+    pub fn get_num<T: Num>(&self) -> TapeResult<T> {
+        Ok(T::from(self.get_bytes(T::number_of_bytes())?))
+    }
+
+    /// Stores the given bytes in the current cell and the following cells without moving the pointer.
+    ///
+    /// * `bytes` - The bytes to store.
+    pub fn put_bytes(&mut self, bytes: &[u8]) -> TapeResult<()> {
+        match self
+            .data
+            .get_mut(self.ptr_index..self.ptr_index + bytes.len())
+        {
+            Some(slice) => {
+                if let Some(journal) = &mut self.journal {
+                    journal.extend((self.ptr_index..).zip(slice.iter().copied()));
+                }
+                slice.copy_from_slice(bytes);
+                Ok(())
+            }
+            None => Err(TapeError::new(
+                TapeErrorType::Index,
+                format!(
+                    "Writing {} cell(s) at pointer index {} would result in overshooting the tape.",
+                    bytes.len(),
+                    self.ptr_index
+                ),
+            )),
+        }
+    }
+
     /// Moves the pointer (read/write head) forward.
     ///
     /// * `steps` - The number of steps to move forward on the tape.
     pub fn pfw(&mut self, steps: usize) -> TapeResult<()> {
-        if self.ptr_index + steps >= self.data.capacity() {
+        if self.ptr_index + steps >= self.data.len() {
             return Err(TapeError::new(
                 TapeErrorType::Index,
                 format!(
@@ -116,17 +292,54 @@ impl Tape {
         }
     }
 
+    /// Moves the pointer (read/write head) to the given absolute position.
+    ///
+    /// * `index` - The index of the cell the pointer should point to.
+    pub fn ptr(&mut self, index: usize) -> TapeResult<()> {
+        if index >= self.data.len() {
+            return Err(TapeError::new(
+                TapeErrorType::Index,
+                format!(
+                    "Moving the pointer to index {} would result in overshooting the tape.",
+                    index
+                ),
+            ));
+        }
+
+        self.ptr_index = index;
+        Ok(())
+    }
+
+    /// Pushes the current pointer position onto the bookmark stack.
+    pub fn mrk(&mut self) -> TapeResult<()> {
+        self.bookmarks.push(self.ptr_index);
+        Ok(())
+    }
+
+    /// Pops the last position from the bookmark stack and moves the pointer there.
+    pub fn ret(&mut self) -> TapeResult<()> {
+        match self.bookmarks.pop() {
+            Some(index) => self.ptr(index),
+            None => Err(TapeError::new(
+                TapeErrorType::Index,
+                "Returning to a bookmark is invalid. The bookmark stack is empty.".to_string(),
+            )),
+        }
+    }
+
     /// Increments the value of the current cell
     ///
     /// * `by` - This value gets added to the value of the current cell.
     pub fn inc(&mut self, by: u8) -> TapeResult<()> {
-        let added = self.get_current_value()?.checked_add(by);
+        let value = self.get_current_value()?;
+        let added = if self.wrapping {
+            Some(value.wrapping_add(by))
+        } else {
+            value.checked_add(by)
+        };
 
         match added {
-            Some(n) => {
-                self.data[self.ptr_index] = n;
-                Ok(())
-            }
+            Some(n) => self.store(n),
             None => Err(TapeError::new(
                 TapeErrorType::Overflow,
                 format!(
@@ -141,13 +354,15 @@ impl Tape {
     ///
     /// * `by` - This value gets subtracted from the value of the current cell.
     pub fn dec(&mut self, by: u8) -> TapeResult<()> {
-        let subbed = self.get_current_value()?.checked_sub(by);
+        let value = self.get_current_value()?;
+        let subbed = if self.wrapping {
+            Some(value.wrapping_sub(by))
+        } else {
+            value.checked_sub(by)
+        };
 
         match subbed {
-            Some(n) => {
-                self.data[self.ptr_index] = n;
-                Ok(())
-            }
+            Some(n) => self.store(n),
             None => Err(TapeError::new(
                 TapeErrorType::Overflow,
                 format!(
@@ -158,97 +373,86 @@ impl Tape {
         }
     }
 
-    /// Writes the value of the current cell to stdout.
+    /// Writes the value of the current cell to the output.
     pub fn wrt(&mut self) -> TapeResult<()> {
-        if let Err(e) = std::io::stdout()
-            .lock()
-            .write_all(&[self.get_current_value()?])
-        {
-            return Err(TapeError::from(e));
-        }
-
-        Ok(())
+        let b = self.get_current_value()?;
+        self.write_output(&[b])
     }
 
-    /// Writes the value of the current cell as an 8-bit signed integer to stdout.
+    /// Writes the value of the current cell as an 8-bit signed integer to the output.
     pub fn wrti8(&mut self) -> TapeResult<()> {
         self.wrt_tape_num::<i8>()?;
 
         Ok(())
     }
 
-    /// Writes the current cell and the next interpreted as an 16-bit signed integer to stdout.
+    /// Writes the current cell and the next interpreted as an 16-bit signed integer to the output.
     pub fn wrti16(&mut self) -> TapeResult<()> {
         self.wrt_tape_num::<i16>()?;
 
         Ok(())
     }
 
-    /// Writes the current cell and the next three interpreted as an 32-bit signed integer to stdout.
+    /// Writes the current cell and the next three interpreted as an 32-bit signed integer to the output.
     pub fn wrti32(&mut self) -> TapeResult<()> {
         self.wrt_tape_num::<i32>()?;
 
         Ok(())
     }
 
-    /// Writes the current cell and the next seven interpreted as an 64-bit signed integer to stdout.
+    /// Writes the current cell and the next seven interpreted as an 64-bit signed integer to the output.
     pub fn wrti64(&mut self) -> TapeResult<()> {
         self.wrt_tape_num::<i64>()?;
 
         Ok(())
     }
 
-    /// Writes the value of the current cell as an 8-bit unsigned integer to stdout.
+    /// Writes the value of the current cell as an 8-bit unsigned integer to the output.
     pub fn wrtu8(&mut self) -> TapeResult<()> {
         self.wrt_tape_num::<u8>()?;
 
         Ok(())
     }
 
-    /// Writes the current cell and the next interpreted as an 16-bit unsigned integer to stdout.
+    /// Writes the current cell and the next interpreted as an 16-bit unsigned integer to the output.
     pub fn wrtu16(&mut self) -> TapeResult<()> {
         self.wrt_tape_num::<u16>()?;
 
         Ok(())
     }
 
-    /// Writes the current cell and the next three interpreted as an 32-bit signed integer to stdout.
+    /// Writes the current cell and the next three interpreted as an 32-bit signed integer to the output.
     pub fn wrtu32(&mut self) -> TapeResult<()> {
         self.wrt_tape_num::<u32>()?;
 
         Ok(())
     }
 
-    /// Writes the current cell and the next seven interpreted as an 64-bit unsigned integer to stdout.
+    /// Writes the current cell and the next seven interpreted as an 64-bit unsigned integer to the output.
     pub fn wrtu64(&mut self) -> TapeResult<()> {
         self.wrt_tape_num::<u64>()?;
 
         Ok(())
     }
 
-    /// Writes the current cell and the next three interpreted as an 32-bit floating point number to stdout.
+    /// Writes the current cell and the next three interpreted as an 32-bit floating point number to the output.
     pub fn wrtf32(&mut self) -> TapeResult<()> {
         self.wrt_tape_num::<f32>()?;
 
         Ok(())
     }
 
-    /// Writes the current cell and the next seven interpreted as an 64-bit floating point number to stdout.
+    /// Writes the current cell and the next seven interpreted as an 64-bit floating point number to the output.
     pub fn wrtf64(&mut self) -> TapeResult<()> {
         self.wrt_tape_num::<f64>()?;
 
         Ok(())
     }
 
-    /// Reads a character from stdin and stores it in the current cell.
+    /// Reads a character from the input and stores it in the current cell.
     pub fn rdi(&mut self) -> TapeResult<()> {
-        let b = std::io::stdin().lock().bytes().next();
-
-        match b {
-            Some(res) => match res {
-                Ok(byte) => Ok(self.store(byte)?),
-                Err(e) => Err(TapeError::from(e)),
-            },
+        match self.read_byte()? {
+            Some(byte) => Ok(self.store(byte)?),
             None => Ok(()),
         }
     }
@@ -344,7 +548,7 @@ impl Tape {
         Ok(())
     }
 
-    /// Writes the current cell and all following cells to stdout until a null byte is encountered.
+    /// Writes the current cell and all following cells to the output until a null byte is encountered.
     /// The pointer is incremented accordingly.
     pub fn wra(&mut self) -> TapeResult<()> {
         loop {
@@ -354,30 +558,23 @@ impl Tape {
                 break;
             }
 
-            match std::io::stdout().lock().write_all(&[b]) {
-                Ok(_) => self.step_fw()?,
-                Err(e) => return Err(TapeError::from(e)),
-            }
+            self.write_output(&[b])?;
+            self.step_fw()?;
         }
 
         Ok(())
     }
 
-    /// Reads all bytes from stdin until LF is encountered.
+    /// Reads all bytes from the input until LF is encountered.
     /// The pointer is incremented accordingly.
     pub fn rda(&mut self) -> TapeResult<()> {
-        for b in std::io::stdin().lock().bytes() {
-            match b {
-                Ok(byte) => {
-                    if byte == 10 {
-                        break;
-                    }
-
-                    self.store(byte)?;
-                    self.step_fw()?;
-                }
-                Err(e) => return Err(TapeError::from(e)),
+        while let Some(byte) = self.read_byte()? {
+            if byte == 10 {
+                break;
             }
+
+            self.store(byte)?;
+            self.step_fw()?;
         }
 
         Ok(())
@@ -402,6 +599,29 @@ impl Tape {
         Ok(())
     }
 
+    /// Writes the given bytes to the output.
+    fn write_output(&mut self, bytes: &[u8]) -> TapeResult<()> {
+        self.output.write_all(bytes)?;
+        self.bytes_written += bytes.len() as u64;
+        Ok(())
+    }
+
+    /// Reads a single byte from the input. Returns `None` at the end of the input.
+    fn read_byte(&mut self) -> TapeResult<Option<u8>> {
+        let mut buf = [0; 1];
+        loop {
+            match self.input.read(&mut buf) {
+                Ok(0) => return Ok(None),
+                Ok(_) => {
+                    self.bytes_read += 1;
+                    return Ok(Some(buf[0]));
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(TapeError::from(e)),
+            }
+        }
+    }
+
     fn step_fw(&mut self) -> TapeResult<()> {
         let moved = self.ptr_index.checked_add(1);
         match moved {
@@ -418,8 +638,22 @@ impl Tape {
     }
 
     fn store(&mut self, byte: u8) -> TapeResult<()> {
-        self.data[self.ptr_index] = byte;
-        Ok(())
+        match self.data.get_mut(self.ptr_index) {
+            Some(cell) => {
+                if let Some(journal) = &mut self.journal {
+                    journal.push((self.ptr_index, *cell));
+                }
+                *cell = byte;
+                Ok(())
+            }
+            None => Err(TapeError::new(
+                TapeErrorType::Index,
+                format!(
+                    "Writing to pointer index {} would result in overshooting the tape.",
+                    self.ptr_index
+                ),
+            )),
+        }
     }
 
     fn set_num<T: Num>(&mut self, v: T) -> TapeResult<()> {
@@ -431,20 +665,14 @@ impl Tape {
     }
 
     fn wrt_tape_num<T: Num>(&mut self) -> TapeResult<()> {
-        let slice = &self.data[self.ptr_index..self.ptr_index + T::number_of_bytes()];
-        let v = slice.to_vec();
-
-        let tv = T::from(v).to_string();
-
-        if let Err(e) = std::io::stdout().lock().write_all(tv.as_bytes()) {
-            return Err(TapeError::from(e));
-        }
+        let tv = self.get_num::<T>()?.to_string();
 
-        Ok(())
+        self.write_output(tv.as_bytes())
     }
 }
 
 #[cfg(test)]
+#[allow(clippy::nonminimal_bool)]
 mod tests {
 
     #[test]
@@ -477,6 +705,68 @@ mod tests {
         assert_eq!(tape.ptr_index, fwd - bwd);
     }
 
+    #[test]
+    fn tape_ptr_moves_to_absolute_index_test() {
+        let mut tape = super::Tape::default();
+
+        tape.pfw(20).unwrap();
+        tape.ptr(7).unwrap();
+
+        assert_eq!(tape.ptr_index, 7);
+    }
+
+    #[test]
+    #[should_panic]
+    fn tape_ptr_out_of_bounds_panics_test() {
+        let mut tape = super::Tape::new(10);
+
+        tape.ptr(10).unwrap();
+    }
+
+    #[test]
+    fn tape_mrk_ret_restores_pointer_test() {
+        let mut tape = super::Tape::default();
+
+        tape.pfw(3).unwrap();
+        tape.mrk().unwrap();
+        tape.set("Hello").unwrap();
+        tape.mrk().unwrap();
+        tape.pfw(10).unwrap();
+
+        tape.ret().unwrap();
+        assert_eq!(tape.ptr_index, 8);
+
+        tape.ret().unwrap();
+        assert_eq!(tape.ptr_index, 3);
+    }
+
+    #[test]
+    #[should_panic]
+    fn tape_ret_without_mrk_panics_test() {
+        let mut tape = super::Tape::default();
+
+        tape.ret().unwrap();
+    }
+
+    #[test]
+    fn tape_put_bytes_get_bytes_roundtrip_test() {
+        let mut tape = super::Tape::default();
+
+        tape.put_bytes(&[1, 2, 3, 4]).unwrap();
+
+        assert_eq!(tape.ptr_index, 0);
+        assert_eq!(tape.get_bytes(4).unwrap(), vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn tape_get_bytes_beyond_end_panics_test() {
+        let mut tape = super::Tape::new(4);
+
+        tape.pfw(2).unwrap();
+        tape.get_bytes(4).unwrap();
+    }
+
     #[test]
     fn tape_inc_by_n_equals_cell_value_plus_n_test() {
         let mut tape = super::Tape::default();
@@ -817,6 +1107,62 @@ mod tests {
         assert_eq!(tape.get_current_value().unwrap(), 0);
     }
 
+    #[test]
+    fn rda_reads_until_lf_test() {
+        let mut tape = super::Tape::default();
+        tape.set_input(Box::new(std::io::Cursor::new(b"Hi\nthere".to_vec())));
+
+        tape.rda().unwrap();
+
+        assert_eq!(tape.ptr_index, 2);
+        assert_eq!(&tape.data[0..3], b"Hi\0");
+    }
+
+    #[test]
+    fn rdi_wrt_with_buffers_test() {
+        let mut tape = super::Tape::default();
+        let output = crate::SharedBuffer::new();
+        tape.set_input(Box::new(std::io::Cursor::new(b"A".to_vec())));
+        tape.set_output(Box::new(output.clone()));
+
+        tape.rdi().unwrap();
+        tape.wrt().unwrap();
+        tape.wrtu8().unwrap();
+
+        assert_eq!(output.contents(), b"A65");
+    }
+
+    #[test]
+    fn preload_dump_to_test() {
+        let mut tape = super::Tape::new(8);
+
+        assert_eq!(tape.preload(&b"abc"[..], 4).unwrap(), 3);
+        assert!(tape.preload(&b"abc"[..], 6).is_err());
+
+        let mut out = vec![];
+        tape.dump_to(&mut out, 3..7).unwrap();
+        assert_eq!(out, b"\0abc");
+        assert!(tape.dump_to(&mut out, 6..9).is_err());
+    }
+
+    #[test]
+    fn wrti32_beyond_tape_is_err_test() {
+        let mut tape = super::Tape::new(30000);
+
+        tape.ptr(29999).unwrap();
+        let err = tape.wrti32().unwrap_err();
+
+        assert!(err.to_string().contains("Index error"));
+    }
+
+    #[test]
+    fn set_beyond_tape_is_err_test() {
+        let mut tape = super::Tape::new(2);
+
+        assert!(tape.set("abc").is_err());
+        assert_eq!(&tape.data[..], b"ab");
+    }
+
     #[allow(dead_code)]
     fn rdi_successful_test() {
         let mut tape = super::Tape::default();
@@ -17,6 +17,9 @@
 use std::fmt::Display;
 
 /// Defines functions for numeric types used in the tape.
+///
+/// Implemented for the types of the typed instructions, e.g. `i32` for `seti32` and `wrti32`.
+/// `Interpreter::read_typed` uses it to decode cells.
 pub trait Num: Display {
     /// Returns a vector with the bytes of the current instance in big-endian byte order.
     fn get_bytes(self) -> Vec<u8>;
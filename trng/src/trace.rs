@@ -0,0 +1,235 @@
+// TRNG - Brainfucks pretty sister.
+// Copyright (C) 2023 Lukas Pfeifer
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::program::Program;
+use std::io::Write;
+use std::ops::RangeInclusive;
+
+/// Defines how trace records are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceFormat {
+    /// One human-readable line per instruction.
+    Text,
+    /// One JSON object per line.
+    JsonLines,
+}
+
+/// Records every executed instruction with the pointer movement and the changed cells.
+///
+/// Set it with `Interpreter::set_tracer`. Without a tracer the interpreter runs a dispatch loop
+/// that contains no tracing code at all.
+pub struct Tracer {
+    output: Box<dyn Write + Send>,
+    format: TraceFormat,
+    /// Only instructions on these source lines are recorded. `None` records all instructions.
+    lines: Option<RangeInclusive<u32>>,
+    /// Copy of the tape as of the last recorded instruction. Used to find changed cells.
+    shadow: Vec<u8>,
+}
+
+impl Tracer {
+    /// Creates a tracer that writes all records to the given output.
+    /// # Arguments
+    /// * `output` - The `Write` instance to write to.
+    /// * `format` - The format of the records.
+    pub fn new<W>(output: W, format: TraceFormat) -> Self
+    where
+        W: Write + Send + 'static,
+    {
+        Self {
+            output: Box::new(output),
+            format,
+            lines: None,
+            shadow: vec![],
+        }
+    }
+
+    /// Restricts the records to instructions on the given source lines.
+    /// # Arguments
+    /// * `lines` - The range of lines. `None` records all instructions.
+    pub fn set_lines(&mut self, lines: Option<RangeInclusive<u32>>) {
+        self.lines = lines;
+    }
+
+    /// Takes a copy of the tape before the execution starts.
+    pub(crate) fn begin(&mut self, data: &[u8]) {
+        self.shadow.clear();
+        self.shadow.extend_from_slice(data);
+    }
+
+    /// Records an executed instruction.
+    /// # Arguments
+    /// * `program` - The executed program.
+    /// * `index` - The index of the executed instruction.
+    /// * `ptr_before` - The pointer index before the instruction was executed.
+    /// * `ptr_after` - The pointer index after the instruction was executed.
+    /// * `data` - The tape after the instruction was executed.
+    pub(crate) fn record(
+        &mut self,
+        program: &Program,
+        index: usize,
+        ptr_before: usize,
+        ptr_after: usize,
+        data: &[u8],
+    ) -> std::io::Result<()> {
+        // An instruction writes at most from the lower pointer index to the higher one
+        // or the 8 bytes of a stack value from the current cell on.
+        let start = ptr_before.min(ptr_after).min(data.len());
+        let end = (ptr_before.max(ptr_after) + 8).min(data.len());
+
+        let mut changes = vec![];
+        for (cell, (old, new)) in self.shadow[start..end]
+            .iter_mut()
+            .zip(&data[start..end])
+            .enumerate()
+        {
+            if *old != *new {
+                changes.push((start + cell, *old, *new));
+                *old = *new;
+            }
+        }
+
+        let position = program.positions.get(index).copied().unwrap_or_default();
+        if let Some(lines) = &self.lines {
+            if !lines.contains(&position.line) {
+                return Ok(());
+            }
+        }
+
+        let instruction = &program.instructions[index];
+        match self.format {
+            TraceFormat::Text => {
+                let changes: Vec<String> = changes
+                    .iter()
+                    .map(|(cell, old, new)| format!("[{}] {} -> {}", cell, old, new))
+                    .collect();
+                writeln!(
+                    self.output,
+                    "{} {}:{} {} | ptr {} -> {} | {}",
+                    index,
+                    position.line,
+                    position.column,
+                    instruction,
+                    ptr_before,
+                    ptr_after,
+                    changes.join(", ")
+                )
+            }
+            TraceFormat::JsonLines => {
+                let changes: Vec<String> = changes
+                    .iter()
+                    .map(|(cell, old, new)| {
+                        format!("{{\"cell\":{},\"old\":{},\"new\":{}}}", cell, old, new)
+                    })
+                    .collect();
+                writeln!(
+                    self.output,
+                    "{{\"index\":{},\"line\":{},\"column\":{},\"instruction\":{},\"ptr_before\":{},\"ptr_after\":{},\"changes\":[{}]}}",
+                    index,
+                    position.line,
+                    position.column,
+                    json_string(&instruction.to_string()),
+                    ptr_before,
+                    ptr_after,
+                    changes.join(",")
+                )
+            }
+        }
+    }
+
+    /// Flushes the output.
+    pub(crate) fn flush(&mut self) -> std::io::Result<()> {
+        self.output.flush()
+    }
+}
+
+/// Returns the given text as a quoted JSON string.
+pub(crate) fn json_string(text: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TraceFormat, Tracer};
+    use crate::{Interpreter, SharedBuffer};
+
+    fn trace(
+        code: &str,
+        format: TraceFormat,
+        lines: Option<std::ops::RangeInclusive<u32>>,
+    ) -> String {
+        let buffer = SharedBuffer::new();
+        let mut tracer = Tracer::new(buffer.clone(), format);
+        tracer.set_lines(lines);
+
+        let mut interpreter = Interpreter::default();
+        interpreter.set_output(SharedBuffer::new());
+        interpreter.set_tracer(Some(tracer));
+        interpreter.run(code.as_bytes()).unwrap();
+
+        String::from_utf8(buffer.contents()).unwrap()
+    }
+
+    #[test]
+    fn text_trace_shows_pointer_and_changes_test() {
+        let trace = trace("inc 2\nset ab\npbw 1\nwrt", TraceFormat::Text, None);
+        let lines: Vec<&str> = trace.lines().collect();
+
+        assert_eq!(
+            lines,
+            vec![
+                "0 1:1 inc 2 | ptr 0 -> 0 | [0] 0 -> 2",
+                "1 2:1 set ab | ptr 0 -> 2 | [0] 2 -> 97, [1] 0 -> 98",
+                "2 3:1 pbw 1 | ptr 2 -> 1 | ",
+                "3 4:1 wrt | ptr 1 -> 1 | ",
+            ]
+        );
+    }
+
+    #[test]
+    fn json_trace_records_loops_test() {
+        let trace = trace("inc 2\nlop\ndec 1\npol", TraceFormat::JsonLines, None);
+        let lines: Vec<&str> = trace.lines().collect();
+
+        assert_eq!(lines.len(), 6);
+        assert_eq!(
+            lines[2],
+            r#"{"index":2,"line":3,"column":1,"instruction":"dec 1","ptr_before":0,"ptr_after":0,"changes":[{"cell":0,"old":2,"new":1}]}"#
+        );
+    }
+
+    #[test]
+    fn trace_is_filtered_by_lines_test() {
+        let trace = trace("inc 1\ninc 1\nclr", TraceFormat::Text, Some(3..=3));
+
+        assert_eq!(trace, "2 3:1 clr | ptr 0 -> 1 | [0] 2 -> 0\n");
+    }
+
+    #[test]
+    fn json_string_escapes_test() {
+        assert_eq!(super::json_string("a\"b\\\n"), r#""a\"b\\\u000a""#);
+    }
+}